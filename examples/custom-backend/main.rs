@@ -19,8 +19,8 @@ use std::path::Path;
 use jj_cli::cli_util::{CliRunner, CommandError, CommandHelper};
 use jj_cli::ui::Ui;
 use jj_lib::backend::{
-    Backend, BackendInitError, BackendLoadError, BackendResult, ChangeId, Commit, CommitId,
-    Conflict, ConflictId, FileId, SymlinkId, Tree, TreeId,
+    AnyObjectId, Backend, BackendInitError, BackendLoadError, BackendResult, ChangeId, Commit,
+    CommitId, Conflict, ConflictId, FileId, SymlinkId, Tree, TreeId,
 };
 use jj_lib::git_backend::GitBackend;
 use jj_lib::repo::StoreFactories;
@@ -154,4 +154,8 @@ impl Backend for JitBackend {
     fn write_commit(&self, contents: Commit) -> BackendResult<(CommitId, Commit)> {
         self.inner.write_commit(contents)
     }
+
+    fn all_object_ids(&self) -> BackendResult<Vec<AnyObjectId>> {
+        self.inner.all_object_ids()
+    }
 }
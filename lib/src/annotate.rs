@@ -0,0 +1,134 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line-level attribution of file contents to the commits that introduced
+//! them.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::backend::{BackendError, BackendResult, CommitId, ObjectId, TreeValue};
+use crate::commit::Commit;
+use crate::diff::{find_line_ranges, Diff, DiffHunk};
+use crate::repo_path::RepoPath;
+use crate::store::Store;
+
+/// The contents of a single line, including the trailing newline if any.
+pub type LineContent = Vec<u8>;
+
+/// Attributes each line of the file at `path` in `commit` to the commit that
+/// last changed it. Only the first parent of merge commits is followed, so
+/// lines coming from other parents are attributed to the merge commit.
+/// Returns an empty list if the path isn't a regular file in `commit`.
+pub fn annotate(
+    store: &Arc<Store>,
+    commit: &Commit,
+    path: &RepoPath,
+) -> BackendResult<Vec<(LineContent, CommitId)>> {
+    let content = match read_file_content(store, commit, path)? {
+        Some(content) => content,
+        None => return Ok(vec![]),
+    };
+    let lines: Vec<LineContent> = find_line_ranges(&content)
+        .into_iter()
+        .map(|range| content[range].to_vec())
+        .collect();
+    let mut attributions: Vec<Option<CommitId>> = vec![None; lines.len()];
+    // Pairs of (line number in `current_commit`, line number in `commit`) for lines
+    // that haven't been attributed yet.
+    let mut pending: Vec<(usize, usize)> = (0..lines.len()).map(|i| (i, i)).collect();
+    let mut current_commit = commit.clone();
+    let mut current_content = content;
+    while !pending.is_empty() {
+        let parent = match current_commit.parent_ids().first() {
+            Some(parent_id) => Some(store.get_commit(parent_id)?),
+            None => None,
+        };
+        let parent_content = match &parent {
+            Some(parent) => read_file_content(store, parent, path)?,
+            None => None,
+        };
+        let (parent, parent_content) = match (parent, parent_content) {
+            (Some(parent), Some(parent_content)) => (parent, parent_content),
+            _ => {
+                for (_, original_line) in pending.drain(..) {
+                    attributions[original_line] = Some(current_commit.id().clone());
+                }
+                break;
+            }
+        };
+        if parent_content != current_content {
+            let line_map = map_lines_to_parent(&parent_content, &current_content);
+            let mut new_pending = vec![];
+            for (current_line, original_line) in pending {
+                match line_map[current_line] {
+                    Some(parent_line) => new_pending.push((parent_line, original_line)),
+                    None => attributions[original_line] = Some(current_commit.id().clone()),
+                }
+            }
+            pending = new_pending;
+        }
+        current_commit = parent;
+        current_content = parent_content;
+    }
+    Ok(lines
+        .into_iter()
+        .zip(attributions)
+        .map(|(line, commit_id)| (line, commit_id.unwrap()))
+        .collect())
+}
+
+fn read_file_content(
+    store: &Arc<Store>,
+    commit: &Commit,
+    path: &RepoPath,
+) -> BackendResult<Option<Vec<u8>>> {
+    match commit.tree().path_value(path) {
+        Some(TreeValue::File { id, .. }) => {
+            let mut content = vec![];
+            store
+                .read_file(path, &id)?
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: id.object_type(),
+                    hash: id.hex(),
+                    source: Box::new(err),
+                })?;
+            Ok(Some(content))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns, for each line in `child`, the number of the matching line in
+/// `parent`, or `None` if the line was added or changed.
+fn map_lines_to_parent(parent: &[u8], child: &[u8]) -> Vec<Option<usize>> {
+    let diff = Diff::for_tokenizer(&[parent, child], &find_line_ranges);
+    let mut line_map = vec![];
+    let mut parent_line = 0;
+    for hunk in diff.hunks() {
+        match hunk {
+            DiffHunk::Matching(content) => {
+                let num_lines = find_line_ranges(content).len();
+                line_map.extend((parent_line..parent_line + num_lines).map(Some));
+                parent_line += num_lines;
+            }
+            DiffHunk::Different(contents) => {
+                parent_line += find_line_ranges(contents[0]).len();
+                line_map.extend(std::iter::repeat(None).take(find_line_ranges(contents[1]).len()));
+            }
+        }
+    }
+    line_map
+}
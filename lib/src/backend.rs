@@ -17,7 +17,7 @@
 use std::any::Any;
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Error, Formatter};
-use std::io::Read;
+use std::io::{self, Read};
 use std::result::Result;
 use std::vec::Vec;
 
@@ -25,6 +25,7 @@ use thiserror::Error;
 
 use crate::content_hash::ContentHash;
 use crate::repo_path::{RepoPath, RepoPathComponent};
+use crate::settings::DurabilityMode;
 
 pub trait ObjectId {
     fn new(value: Vec<u8>) -> Self;
@@ -87,9 +88,53 @@ macro_rules! impl_id_type {
                 hex::encode(&self.0)
             }
         }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+                f.write_str(&self.hex())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = ObjectIdParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_object_id_hex(s).map(Self)
+            }
+        }
     };
 }
 
+/// Error returned when parsing an object id from its string form fails.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ObjectIdParseError {
+    #[error("Object id is empty")]
+    Empty,
+    #[error("Object id has odd length {0}")]
+    OddLength(usize),
+    #[error("Invalid character {character:?} at offset {offset} in object id")]
+    InvalidCharacter { character: char, offset: usize },
+}
+
+/// Parses the canonical string form of an object id: a non-empty, even-length
+/// string of lowercase hex digits. The length isn't otherwise checked since it
+/// depends on the backend.
+fn parse_object_id_hex(s: &str) -> Result<Vec<u8>, ObjectIdParseError> {
+    if s.is_empty() {
+        return Err(ObjectIdParseError::Empty);
+    }
+    if let Some((offset, character)) = s
+        .char_indices()
+        .find(|(_, c)| !matches!(c, '0'..='9' | 'a'..='f'))
+    {
+        return Err(ObjectIdParseError::InvalidCharacter { character, offset });
+    }
+    if s.len() % 2 != 0 {
+        return Err(ObjectIdParseError::OddLength(s.len()));
+    }
+    Ok(hex::decode(s).unwrap())
+}
+
 id_type!(pub CommitId);
 id_type!(pub ChangeId);
 id_type!(pub TreeId);
@@ -230,6 +275,16 @@ pub enum BackendError {
 
 pub type BackendResult<T> = Result<T, BackendError>;
 
+/// The id of an object of any kind.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum AnyObjectId {
+    Commit(CommitId),
+    Tree(TreeId),
+    File(FileId),
+    Symlink(SymlinkId),
+    Conflict(ConflictId),
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum TreeValue {
     File { id: FileId, executable: bool },
@@ -412,6 +467,19 @@ pub trait Backend: Send + Sync + Debug {
 
     fn write_file(&self, path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId>;
 
+    /// Returns the size in bytes of the file's contents, i.e. the number of
+    /// bytes `read_file()` yields, not the size of the stored, possibly
+    /// compressed object. Backends that can look up the size without reading
+    /// the contents should override this.
+    fn file_size(&self, path: &RepoPath, id: &FileId) -> BackendResult<u64> {
+        let mut reader = self.read_file(path, id)?;
+        io::copy(&mut reader, &mut io::sink()).map_err(|err| BackendError::ReadObject {
+            object_type: id.object_type(),
+            hash: id.hex(),
+            source: Box::new(err),
+        })
+    }
+
     fn read_symlink(&self, path: &RepoPath, id: &SymlinkId) -> BackendResult<String>;
 
     fn write_symlink(&self, path: &RepoPath, target: &str) -> BackendResult<SymlinkId>;
@@ -432,6 +500,12 @@ pub trait Backend: Send + Sync + Debug {
 
     fn read_commit(&self, id: &CommitId) -> BackendResult<Commit>;
 
+    /// Reads several commits at once. The results are in the same order as
+    /// `ids`. Backends that can batch reads should override this.
+    fn read_commits(&self, ids: &[CommitId]) -> Vec<BackendResult<Commit>> {
+        ids.iter().map(|id| self.read_commit(id)).collect()
+    }
+
     /// Writes a commit and returns its ID and the commit itself. The commit
     /// should contain the data that was actually written, which may differ
     /// from the data passed in. For example, the backend may change the
@@ -439,4 +513,74 @@ pub trait Backend: Send + Sync + Debug {
     /// timestamps may have less precision than the millisecond precision in
     /// `Commit`.
     fn write_commit(&self, contents: Commit) -> BackendResult<(CommitId, Commit)>;
+
+    /// Lists the ids of all objects stored in the backend, whether they're
+    /// reachable or not. Backends that don't distinguish between kinds of
+    /// blobs may report symlinks and conflicts as files. Virtual objects
+    /// such as the root commit are not included. Backends that can't list
+    /// their objects return an error.
+    fn all_object_ids(&self) -> BackendResult<Vec<AnyObjectId>> {
+        Err(BackendError::Other("not supported".into()))
+    }
+
+    /// Tells the backend which durability mode the repo was loaded with. With
+    /// `DurabilityMode::Sync`, the backend should keep track of the objects it
+    /// writes so `flush()` can sync them.
+    fn set_durability_mode(&self, _mode: DurabilityMode) {}
+
+    /// Makes sure all objects written since the durability mode was set to
+    /// `DurabilityMode::Sync` are on disk. Backends that already write
+    /// objects durably don't need to do anything.
+    fn flush(&self) -> BackendResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_id_display_parse_round_trip() {
+        let commit_id = CommitId::from_hex("0123456789abcdef");
+        assert_eq!(commit_id.to_string(), "0123456789abcdef");
+        assert_eq!("0123456789abcdef".parse(), Ok(commit_id));
+
+        let change_id = ChangeId::new(vec![0, 0xff, 0x12]);
+        assert_eq!(change_id.to_string(), "00ff12");
+        assert_eq!(change_id.to_string().parse(), Ok(change_id));
+
+        let tree_id = TreeId::new(vec![0xab; 20]);
+        assert_eq!(tree_id.to_string().parse(), Ok(tree_id));
+    }
+
+    #[test]
+    fn test_object_id_parse_malformed() {
+        assert_eq!("".parse::<CommitId>(), Err(ObjectIdParseError::Empty));
+        assert_eq!(
+            "abc".parse::<CommitId>(),
+            Err(ObjectIdParseError::OddLength(3))
+        );
+        assert_eq!(
+            "abcg".parse::<ChangeId>(),
+            Err(ObjectIdParseError::InvalidCharacter {
+                character: 'g',
+                offset: 3
+            })
+        );
+        assert_eq!(
+            "ABCD".parse::<TreeId>(),
+            Err(ObjectIdParseError::InvalidCharacter {
+                character: 'A',
+                offset: 0
+            })
+        );
+        assert_eq!(
+            "ab cd".parse::<TreeId>(),
+            Err(ObjectIdParseError::InvalidCharacter {
+                character: ' ',
+                offset: 2
+            })
+        );
+    }
 }
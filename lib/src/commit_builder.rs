@@ -16,7 +16,7 @@
 
 use std::sync::Arc;
 
-use crate::backend::{self, BackendResult, ChangeId, CommitId, Signature, TreeId};
+use crate::backend::{self, BackendResult, ChangeId, CommitId, Signature, Timestamp, TreeId};
 use crate::commit::Commit;
 use crate::repo::{MutableRepo, Repo};
 use crate::settings::{JJRng, UserSettings};
@@ -95,6 +95,11 @@ impl CommitBuilder<'_> {
         &self.commit.predecessors
     }
 
+    /// Sets the commits this commit replaces, e.g. all the inputs of a
+    /// squash. Only the commit passed to `for_rewrite_from()` is recorded as
+    /// rewritten on `write()`; callers should record any other predecessors
+    /// with `MutableRepo::record_rewritten_commit()` or
+    /// `MutableRepo::record_abandoned_commit()` as appropriate.
     pub fn set_predecessors(mut self, predecessors: Vec<CommitId>) -> Self {
         self.commit.predecessors = predecessors;
         self
@@ -152,6 +157,14 @@ impl CommitBuilder<'_> {
         self
     }
 
+    /// Sets both the author and committer timestamps, e.g. for reproducible
+    /// commits.
+    pub fn set_both_timestamps(mut self, timestamp: Timestamp) -> Self {
+        self.commit.author.timestamp = timestamp.clone();
+        self.commit.committer.timestamp = timestamp;
+        self
+    }
+
     pub fn write(self) -> BackendResult<Commit> {
         let mut rewrite_source_id = None;
         if let Some(rewrite_source) = self.rewrite_source {
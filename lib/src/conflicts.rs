@@ -227,10 +227,24 @@ impl Conflict<Option<TreeValue>> {
         store: &Store,
         path: &RepoPath,
         output: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        self.materialize_with_labels(store, path, &[], output)
+    }
+
+    /// Like `materialize()`, but the marker of each added term is followed by
+    /// the corresponding label in `labels`, such as a branch name. Terms
+    /// without a label get a bare marker. The labels are ignored when parsing
+    /// the markers back.
+    pub fn materialize_with_labels(
+        &self,
+        store: &Store,
+        path: &RepoPath,
+        labels: &[String],
+        output: &mut dyn Write,
     ) -> std::io::Result<()> {
         if let Some(file_conflict) = self.to_file_conflict() {
             let content = file_conflict.extract_as_single_hunk(store, path);
-            materialize_merge_result(&content, output)
+            materialize_merge_result_with_labels(&content, labels, output)
         } else {
             // Unless all terms are regular files, we can't do much better than to try to
             // describe the conflict.
@@ -440,9 +454,44 @@ fn write_diff_hunks(hunks: &[DiffHunk], file: &mut dyn Write) -> std::io::Result
     Ok(())
 }
 
+fn write_marker(
+    output: &mut dyn Write,
+    marker: &[u8],
+    label: Option<&String>,
+) -> std::io::Result<()> {
+    match label.and_then(|label| label.lines().next()) {
+        Some(label) if !label.is_empty() => {
+            output.write_all(marker.strip_suffix(b"\n").unwrap())?;
+            output.write_all(b" ")?;
+            output.write_all(label.as_bytes())?;
+            output.write_all(b"\n")
+        }
+        _ => output.write_all(marker),
+    }
+}
+
+/// Returns true if `line` is `marker`, possibly with a label after it.
+fn is_marker(line: &[u8], marker: &[u8]) -> bool {
+    let bare_marker = marker.strip_suffix(b"\n").unwrap();
+    match line.strip_prefix(bare_marker) {
+        Some(rest) => rest == b"\n" || (rest.starts_with(b" ") && rest.ends_with(b"\n")),
+        None => false,
+    }
+}
+
 pub fn materialize_merge_result(
     single_hunk: &Conflict<ContentHunk>,
     output: &mut dyn Write,
+) -> std::io::Result<()> {
+    materialize_merge_result_with_labels(single_hunk, &[], output)
+}
+
+/// Like `materialize_merge_result()`, but labels the markers of the added
+/// terms. See `Conflict::materialize_with_labels()`.
+pub fn materialize_merge_result_with_labels(
+    single_hunk: &Conflict<ContentHunk>,
+    labels: &[String],
+    output: &mut dyn Write,
 ) -> std::io::Result<()> {
     let removed_slices = single_hunk
         .removes
@@ -491,23 +540,27 @@ pub fn materialize_merge_result(
                                 // If the next positive term is a better match, emit
                                 // the current positive term as a snapshot and the next
                                 // positive term as a diff.
-                                output.write_all(CONFLICT_PLUS_LINE)?;
+                                write_marker(output, CONFLICT_PLUS_LINE, labels.get(add_index))?;
                                 output.write_all(&right1.0)?;
-                                output.write_all(CONFLICT_DIFF_LINE)?;
+                                write_marker(
+                                    output,
+                                    CONFLICT_DIFF_LINE,
+                                    labels.get(add_index + 1),
+                                )?;
                                 write_diff_hunks(&diff2, output)?;
                                 add_index += 2;
                                 continue;
                             }
                         }
 
-                        output.write_all(CONFLICT_DIFF_LINE)?;
+                        write_marker(output, CONFLICT_DIFF_LINE, labels.get(add_index))?;
                         write_diff_hunks(&diff1, output)?;
                         add_index += 1;
                     }
 
                     //  Emit the remaining positive terms as snapshots.
-                    for slice in &hunk.adds()[add_index..] {
-                        output.write_all(CONFLICT_PLUS_LINE)?;
+                    for (i, slice) in hunk.adds().iter().enumerate().skip(add_index) {
+                        write_marker(output, CONFLICT_PLUS_LINE, labels.get(i))?;
                         output.write_all(&slice.0)?;
                     }
                     output.write_all(CONFLICT_END_LINE)?;
@@ -577,6 +630,27 @@ pub fn parse_conflict(
     }
 }
 
+/// Returns true if `input` contains valid conflict markers for a conflict
+/// with any number of sides. Unlike `parse_conflict()`, the arity doesn't
+/// need to be known, so this can check files for leftover markers.
+pub fn has_conflict_markers(input: &[u8]) -> bool {
+    let mut pos = 0;
+    let mut conflict_start = None;
+    for line in input.split_inclusive(|b| *b == b'\n') {
+        if line == CONFLICT_START_LINE {
+            conflict_start = Some(pos + line.len());
+        } else if let (Some(start), true) = (conflict_start, line == CONFLICT_END_LINE) {
+            let hunk = parse_conflict_hunk(&input[start..pos]);
+            if !hunk.removes().is_empty() && hunk.adds().len() == hunk.removes().len() + 1 {
+                return true;
+            }
+            conflict_start = None;
+        }
+        pos += line.len();
+    }
+    false
+}
+
 fn parse_conflict_hunk(input: &[u8]) -> Conflict<ContentHunk> {
     enum State {
         Diff,
@@ -589,18 +663,18 @@ fn parse_conflict_hunk(input: &[u8]) -> Conflict<ContentHunk> {
     let mut adds = vec![];
     for line in input.split_inclusive(|b| *b == b'\n') {
         match line {
-            CONFLICT_DIFF_LINE => {
+            _ if is_marker(line, CONFLICT_DIFF_LINE) => {
                 state = State::Diff;
                 removes.push(ContentHunk(vec![]));
                 adds.push(ContentHunk(vec![]));
                 continue;
             }
-            CONFLICT_MINUS_LINE => {
+            _ if is_marker(line, CONFLICT_MINUS_LINE) => {
                 state = State::Minus;
                 removes.push(ContentHunk(vec![]));
                 continue;
             }
-            CONFLICT_PLUS_LINE => {
+            _ if is_marker(line, CONFLICT_PLUS_LINE) => {
                 state = State::Plus;
                 adds.push(ContentHunk(vec![]));
                 continue;
@@ -36,7 +36,7 @@ use thiserror::Error;
 
 use crate::backend::{ChangeId, CommitId, ObjectId};
 use crate::commit::Commit;
-use crate::file_util::persist_content_addressed_temp_file;
+use crate::file_util::{persist_content_addressed_temp_file, PendingSyncs};
 use crate::index::{
     HexPrefix, Index, IndexStore, IndexWriteError, MutableIndex, PrefixResolution, ReadonlyIndex,
 };
@@ -48,12 +48,14 @@ use crate::nightly_shims::BTreeSetExt;
 use crate::op_store::OperationId;
 use crate::operation::Operation;
 use crate::revset::{ResolvedExpression, Revset, RevsetEvaluationError};
+use crate::settings::DurabilityMode;
 use crate::store::Store;
 use crate::{backend, dag_walk, default_revset_engine};
 
 #[derive(Debug)]
 pub struct DefaultIndexStore {
     dir: PathBuf,
+    pending_syncs: PendingSyncs,
 }
 
 impl DefaultIndexStore {
@@ -61,12 +63,14 @@ impl DefaultIndexStore {
         std::fs::create_dir(dir.join("operations")).unwrap();
         DefaultIndexStore {
             dir: dir.to_owned(),
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
     pub fn load(dir: &Path) -> DefaultIndexStore {
         DefaultIndexStore {
             dir: dir.to_owned(),
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
@@ -227,8 +231,21 @@ impl IndexStore for DefaultIndexStore {
                     "Failed to associate commit index file with a operation {op_id:?}: {err:?}"
                 ))
             })?;
+        self.pending_syncs.push(self.dir.join(index.name()));
+        self.pending_syncs
+            .push(self.dir.join("operations").join(op_id.hex()));
         Ok(Box::new(ReadonlyIndexWrapper(index)))
     }
+
+    fn set_durability_mode(&self, mode: DurabilityMode) {
+        self.pending_syncs.set_enabled(mode == DurabilityMode::Sync);
+    }
+
+    fn flush(&self) -> Result<(), IndexWriteError> {
+        self.pending_syncs.sync_all().map_err(|err| {
+            IndexWriteError::Other(format!("Failed to sync commit index files: {err}"))
+        })
+    }
 }
 
 // Returns the ancestors of heads with parents and predecessors come before the
@@ -705,6 +722,15 @@ impl Index for MutableIndexImpl {
         CompositeIndex(self).is_ancestor(ancestor_id, descendant_id)
     }
 
+    fn distance(
+        &self,
+        ancestor_id: &CommitId,
+        descendant_id: &CommitId,
+        store: &Arc<Store>,
+    ) -> Result<Option<usize>, RevsetEvaluationError> {
+        CompositeIndex(self).distance(ancestor_id, descendant_id, store)
+    }
+
     fn common_ancestors(&self, set1: &[CommitId], set2: &[CommitId]) -> Vec<CommitId> {
         CompositeIndex(self).common_ancestors(set1, set2)
     }
@@ -946,6 +972,33 @@ impl<'a> CompositeIndex<'a> {
         false
     }
 
+    fn distance_pos(
+        &self,
+        ancestor_pos: IndexPosition,
+        descendant_pos: IndexPosition,
+    ) -> Option<usize> {
+        let ancestor_generation = self.entry_by_pos(ancestor_pos).generation_number();
+        let mut current = vec![descendant_pos];
+        let mut visited = HashSet::new();
+        let mut distance = 0;
+        while !current.is_empty() {
+            let mut next = vec![];
+            for pos in current {
+                if pos == ancestor_pos {
+                    return Some(distance);
+                }
+                let entry = self.entry_by_pos(pos);
+                if !visited.insert(pos) || entry.generation_number() <= ancestor_generation {
+                    continue;
+                }
+                next.extend(entry.parent_positions());
+            }
+            current = next;
+            distance += 1;
+        }
+        None
+    }
+
     fn common_ancestors_pos(
         &self,
         set1: &[IndexPosition],
@@ -1087,6 +1140,17 @@ impl Index for CompositeIndex<'_> {
         self.is_ancestor_pos(ancestor_pos, descendant_pos)
     }
 
+    fn distance(
+        &self,
+        ancestor_id: &CommitId,
+        descendant_id: &CommitId,
+        _store: &Arc<Store>,
+    ) -> Result<Option<usize>, RevsetEvaluationError> {
+        let ancestor_pos = self.commit_id_to_pos(ancestor_id).unwrap();
+        let descendant_pos = self.commit_id_to_pos(descendant_id).unwrap();
+        Ok(self.distance_pos(ancestor_pos, descendant_pos))
+    }
+
     fn common_ancestors(&self, set1: &[CommitId], set2: &[CommitId]) -> Vec<CommitId> {
         let pos1 = set1
             .iter()
@@ -2007,6 +2071,15 @@ impl Index for ReadonlyIndexImpl {
         CompositeIndex(self).is_ancestor(ancestor_id, descendant_id)
     }
 
+    fn distance(
+        &self,
+        ancestor_id: &CommitId,
+        descendant_id: &CommitId,
+        store: &Arc<Store>,
+    ) -> Result<Option<usize>, RevsetEvaluationError> {
+        CompositeIndex(self).distance(ancestor_id, descendant_id, store)
+    }
+
     fn common_ancestors(&self, set1: &[CommitId], set2: &[CommitId]) -> Vec<CommitId> {
         CompositeIndex(self).common_ancestors(set1, set2)
     }
@@ -2035,7 +2108,7 @@ mod tests {
 
     use super::*;
     use crate::backend::{ChangeId, CommitId, ObjectId};
-    use crate::index::Index;
+    use crate::index::{Index, IndexStore};
 
     /// Generator of unique 16-byte ChangeId excluding root id
     fn change_id_generator() -> impl FnMut() -> ChangeId {
@@ -2117,6 +2190,25 @@ mod tests {
         assert_eq!(entry.parents(), Vec::<IndexEntry>::new());
     }
 
+    #[test]
+    fn write_index_tracks_files_to_sync() {
+        let temp_dir = testutils::new_temp_dir();
+        let store = DefaultIndexStore::init(temp_dir.path());
+        store.set_durability_mode(DurabilityMode::Sync);
+        let mut new_change_id = change_id_generator();
+        let mut index = MutableIndexImpl::full(3, 16);
+        index.add_commit_data(CommitId::from_hex("000000"), new_change_id(), &[]);
+        store
+            .write_index(Box::new(index), &OperationId::from_hex("abcdef"))
+            .unwrap();
+        // The index file and the operation's link to it
+        let pending_paths = store.pending_syncs.paths();
+        assert_eq!(pending_paths.len(), 2);
+        assert!(pending_paths.iter().all(|path| path.is_file()));
+        store.flush().unwrap();
+        assert!(store.pending_syncs.paths().is_empty());
+    }
+
     #[test]
     #[should_panic(expected = "parent commit is not indexed")]
     fn index_missing_parent_commit() {
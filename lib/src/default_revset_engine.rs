@@ -140,6 +140,10 @@ impl ChangeIdIndex for ChangeIdIndexImpl<'_> {
             .map(|(_, commit_ids)| commit_ids)
     }
 
+    fn change_ids_with_prefix(&self, prefix: &HexPrefix) -> Vec<ChangeId> {
+        self.pos_by_change.keys_with_prefix(self.index, prefix)
+    }
+
     fn shortest_unique_prefix_len(&self, change_id: &ChangeId) -> usize {
         self.pos_by_change
             .shortest_unique_prefix_len(self.index, change_id)
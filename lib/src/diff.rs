@@ -612,6 +612,104 @@ impl<'diff, 'input> Iterator for DiffHunkIterator<'diff, 'input> {
     }
 }
 
+/// The kind of a line in a unified diff hunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffLineType {
+    Context,
+    Removed,
+    Added,
+}
+
+/// A hunk of a unified diff. The line ranges are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnifiedDiffHunk<'content> {
+    pub left_line_range: Range<usize>,
+    pub right_line_range: Range<usize>,
+    pub lines: Vec<(DiffLineType, &'content [u8])>,
+}
+
+/// Splits the line-level diff between the two inputs into unified diff hunks
+/// with up to `num_context_lines` lines of context around each change.
+pub fn unified_diff_hunks<'content>(
+    left_content: &'content [u8],
+    right_content: &'content [u8],
+    num_context_lines: usize,
+) -> Vec<UnifiedDiffHunk<'content>> {
+    let mut hunks = vec![];
+    let mut current_hunk = UnifiedDiffHunk {
+        left_line_range: 1..1,
+        right_line_range: 1..1,
+        lines: vec![],
+    };
+    let mut show_context_after = false;
+    let diff = Diff::for_tokenizer(&[left_content, right_content], &find_line_ranges);
+    for hunk in diff.hunks() {
+        match hunk {
+            DiffHunk::Matching(content) => {
+                let lines = content.split_inclusive(|b| *b == b'\n').collect_vec();
+                // Number of context lines to print after the previous non-matching hunk.
+                let num_after_lines = lines.len().min(if show_context_after {
+                    num_context_lines
+                } else {
+                    0
+                });
+                current_hunk.left_line_range.end += num_after_lines;
+                current_hunk.right_line_range.end += num_after_lines;
+                for line in lines.iter().take(num_after_lines) {
+                    current_hunk.lines.push((DiffLineType::Context, line));
+                }
+                let num_skip_lines = lines
+                    .len()
+                    .saturating_sub(num_after_lines)
+                    .saturating_sub(num_context_lines);
+                if num_skip_lines > 0 {
+                    let left_start = current_hunk.left_line_range.end + num_skip_lines;
+                    let right_start = current_hunk.right_line_range.end + num_skip_lines;
+                    if !current_hunk.lines.is_empty() {
+                        hunks.push(current_hunk);
+                    }
+                    current_hunk = UnifiedDiffHunk {
+                        left_line_range: left_start..left_start,
+                        right_line_range: right_start..right_start,
+                        lines: vec![],
+                    };
+                }
+                let num_before_lines = lines.len() - num_after_lines - num_skip_lines;
+                current_hunk.left_line_range.end += num_before_lines;
+                current_hunk.right_line_range.end += num_before_lines;
+                for line in lines.iter().skip(num_after_lines + num_skip_lines) {
+                    current_hunk.lines.push((DiffLineType::Context, line));
+                }
+            }
+            DiffHunk::Different(content) => {
+                show_context_after = true;
+                let left_lines = content[0].split_inclusive(|b| *b == b'\n').collect_vec();
+                let right_lines = content[1].split_inclusive(|b| *b == b'\n').collect_vec();
+                if !left_lines.is_empty() {
+                    current_hunk.left_line_range.end += left_lines.len();
+                    for line in left_lines {
+                        current_hunk.lines.push((DiffLineType::Removed, line));
+                    }
+                }
+                if !right_lines.is_empty() {
+                    current_hunk.right_line_range.end += right_lines.len();
+                    for line in right_lines {
+                        current_hunk.lines.push((DiffLineType::Added, line));
+                    }
+                }
+            }
+        }
+    }
+    if !current_hunk
+        .lines
+        .iter()
+        .all(|(diff_type, _line)| *diff_type == DiffLineType::Context)
+    {
+        hunks.push(current_hunk);
+    }
+    hunks
+}
+
 /// Diffs two slices of bytes. The returned diff hunks may be any length (may
 /// span many lines or may be only part of a line). This currently uses
 /// Histogram diff (or maybe something similar; I'm not sure I understood the
@@ -14,8 +14,11 @@
 
 #![allow(missing_docs)]
 
+use std::collections::HashSet;
 use std::fs::File;
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::{io, iter};
 
 use tempfile::{NamedTempFile, PersistError};
@@ -108,6 +111,87 @@ pub fn persist_content_addressed_temp_file<P: AsRef<Path>>(
     }
 }
 
+/// Files that have been written but not yet synced to disk. Paths are only
+/// tracked while enabled, i.e. with the `Sync` durability mode, so nothing
+/// accumulates otherwise. Each path is tracked once, however many times it's
+/// written, and is forgotten once it has been synced.
+#[derive(Debug, Default)]
+pub struct PendingSyncs {
+    enabled: AtomicBool,
+    paths: Mutex<HashSet<PathBuf>>,
+}
+
+impl PendingSyncs {
+    /// Starts or stops tracking written files. Stopping forgets the files
+    /// tracked so far.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.paths.lock().unwrap().clear();
+        }
+    }
+
+    pub fn push(&self, path: PathBuf) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.paths.lock().unwrap().insert(path);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn paths(&self) -> Vec<PathBuf> {
+        self.paths.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Syncs all pending files to disk, followed by the directories containing
+    /// them so the renames that created the files are durable too. Files that
+    /// no longer exist are skipped, e.g. Git objects that have been packed
+    /// since. If syncing fails, all files stay pending.
+    pub fn sync_all(&self) -> io::Result<()> {
+        let mut paths = self.paths.lock().unwrap();
+        let mut dirs = HashSet::new();
+        for path in paths.iter() {
+            match sync_file(path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            }
+            if let Some(dir) = path.parent() {
+                dirs.insert(dir);
+            }
+        }
+        for dir in dirs {
+            sync_dir(dir)?;
+        }
+        paths.clear();
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn sync_file(path: &Path) -> io::Result<()> {
+    // Object files may be read-only, which doesn't stop them from being synced.
+    File::open(path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_file(path: &Path) -> io::Result<()> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .sync_all()
+}
+
+#[cfg(unix)]
+pub(crate) fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // Directories can't be opened for syncing on Windows.
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -155,4 +239,23 @@ mod tests {
 
         assert!(persist_content_addressed_temp_file(temp_file, &target).is_ok());
     }
+
+    #[test]
+    fn test_pending_syncs() {
+        let temp_dir = testutils::new_temp_dir();
+        let target = temp_dir.path().join("file");
+        std::fs::write(&target, b"contents").unwrap();
+
+        let pending_syncs = PendingSyncs::default();
+        pending_syncs.push(target.clone());
+        assert!(pending_syncs.paths.lock().unwrap().is_empty());
+
+        pending_syncs.set_enabled(true);
+        pending_syncs.push(target.clone());
+        pending_syncs.push(target);
+        pending_syncs.push(temp_dir.path().join("missing"));
+        assert_eq!(pending_syncs.paths.lock().unwrap().len(), 2);
+        pending_syncs.sync_all().unwrap();
+        assert!(pending_syncs.paths.lock().unwrap().is_empty());
+    }
 }
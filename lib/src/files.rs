@@ -168,7 +168,101 @@ struct SyncRegion {
     right: Range<usize>,
 }
 
+/// How whitespace differences are treated when merging lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespaceHandling {
+    /// Any difference in whitespace is a change.
+    #[default]
+    Strict,
+    /// Trailing whitespace (including `\r` before the newline) is ignored.
+    IgnoreTrailing,
+    /// All whitespace is ignored.
+    IgnoreAll,
+}
+
+impl WhitespaceHandling {
+    fn normalize(self, content: &[u8]) -> Vec<u8> {
+        match self {
+            WhitespaceHandling::Strict => content.to_vec(),
+            WhitespaceHandling::IgnoreTrailing => {
+                let mut normalized = vec![];
+                for line in content.split_inclusive(|b| *b == b'\n') {
+                    let has_newline = line.ends_with(b"\n");
+                    let end = line
+                        .iter()
+                        .rposition(|b| !b.is_ascii_whitespace())
+                        .map_or(0, |pos| pos + 1);
+                    normalized.extend_from_slice(&line[..end]);
+                    if has_newline {
+                        normalized.push(b'\n');
+                    }
+                }
+                normalized
+            }
+            WhitespaceHandling::IgnoreAll => content
+                .iter()
+                .filter(|b| !b.is_ascii_whitespace() || **b == b'\n')
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+/// Which side's exact content to use when a hunk only resolves after ignoring
+/// whitespace and several sides match the resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreferredSide {
+    #[default]
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    pub whitespace: WhitespaceHandling,
+    pub preferred_side: PreferredSide,
+}
+
+/// Resolves a hunk that doesn't resolve trivially by comparing the parts with
+/// whitespace differences normalized away. Returns one of the adds' exact
+/// content.
+fn resolve_ignoring_whitespace<'a>(
+    removes: &[&'a [u8]],
+    adds: &[&'a [u8]],
+    options: &MergeOptions,
+) -> Option<&'a [u8]> {
+    if options.whitespace == WhitespaceHandling::Strict {
+        return None;
+    }
+    let normalize = |parts: &[&[u8]]| {
+        parts
+            .iter()
+            .map(|part| options.whitespace.normalize(part))
+            .collect_vec()
+    };
+    let normalized_removes = normalize(removes);
+    let normalized_adds = normalize(adds);
+    let resolved = trivial_merge(&normalized_removes, &normalized_adds)?;
+    let mut candidates = adds
+        .iter()
+        .zip(&normalized_adds)
+        .filter(|(_, normalized)| *normalized == resolved)
+        .map(|(add, _)| *add);
+    match options.preferred_side {
+        PreferredSide::First => candidates.next(),
+        PreferredSide::Last => candidates.next_back(),
+    }
+}
+
 pub fn merge(removes: &[&[u8]], adds: &[&[u8]]) -> MergeResult {
+    merge_with_options(removes, adds, &MergeOptions::default())
+}
+
+pub fn merge_with_options(
+    removes: &[&[u8]],
+    adds: &[&[u8]],
+    options: &MergeOptions,
+) -> MergeResult {
     assert_eq!(adds.len(), removes.len() + 1);
     let num_diffs = removes.len();
     // TODO: Using the first remove as base (first in the inputs) is how it's
@@ -188,6 +282,10 @@ pub fn merge(removes: &[&[u8]], adds: &[&[u8]]) -> MergeResult {
             DiffHunk::Different(parts) => {
                 if let Some(resolved) = trivial_merge(&parts[..num_diffs], &parts[num_diffs..]) {
                     resolved_hunk.0.extend(*resolved);
+                } else if let Some(resolved) =
+                    resolve_ignoring_whitespace(&parts[..num_diffs], &parts[num_diffs..], options)
+                {
+                    resolved_hunk.0.extend(resolved);
                 } else {
                     if !resolved_hunk.0.is_empty() {
                         merge_hunks.push(Conflict::resolved(resolved_hunk));
@@ -358,6 +456,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_merge_whitespace() {
+        let options = |whitespace, preferred_side| MergeOptions {
+            whitespace,
+            preferred_side,
+        };
+        let strict = options(WhitespaceHandling::Strict, PreferredSide::First);
+        let ignore_trailing = options(WhitespaceHandling::IgnoreTrailing, PreferredSide::First);
+        let ignore_all = options(WhitespaceHandling::IgnoreAll, PreferredSide::First);
+
+        // One side only added trailing whitespace, the other side changed the line
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"a  \n", b"b\n"], &strict),
+            MergeResult::Conflict(vec![Conflict::new(
+                vec![hunk(b"a\n")],
+                vec![hunk(b"a  \n"), hunk(b"b\n")]
+            )])
+        );
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"a  \n", b"b\n"], &ignore_trailing),
+            MergeResult::Resolved(hunk(b"b\n"))
+        );
+        // Line ending changed from LF to CRLF on one side
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"b\n", b"a\r\n"], &ignore_trailing),
+            MergeResult::Resolved(hunk(b"b\n"))
+        );
+        // Leading whitespace is only ignored with IgnoreAll
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"  a\n", b"b\n"], &ignore_trailing),
+            MergeResult::Conflict(vec![Conflict::new(
+                vec![hunk(b"a\n")],
+                vec![hunk(b"  a\n"), hunk(b"b\n")]
+            )])
+        );
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"  a\n", b"b\n"], &ignore_all),
+            MergeResult::Resolved(hunk(b"b\n"))
+        );
+        // Both sides changed only whitespace; the preferred side's content is used
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"a \n", b"a\t\n"], &ignore_trailing),
+            MergeResult::Resolved(hunk(b"a \n"))
+        );
+        assert_eq!(
+            merge_with_options(
+                &[b"a\n"],
+                &[b"a \n", b"a\t\n"],
+                &options(WhitespaceHandling::IgnoreTrailing, PreferredSide::Last)
+            ),
+            MergeResult::Resolved(hunk(b"a\t\n"))
+        );
+        // Real changes on both sides still conflict
+        assert_eq!(
+            merge_with_options(&[b"a\n"], &[b"b \n", b"c\n"], &ignore_all),
+            MergeResult::Conflict(vec![Conflict::new(
+                vec![hunk(b"a\n")],
+                vec![hunk(b"b \n"), hunk(b"c\n")]
+            )])
+        );
+    }
+
     #[test]
     fn test_merge_multi_hunk() {
         // Two sides left one line unchanged, and added conflicting additional lines
@@ -27,13 +27,14 @@ use prost::Message;
 use thiserror::Error;
 
 use crate::backend::{
-    make_root_commit, Backend, BackendError, BackendInitError, BackendLoadError, BackendResult,
-    ChangeId, Commit, CommitId, Conflict, ConflictId, ConflictTerm, FileId, MillisSinceEpoch,
-    ObjectId, Signature, SymlinkId, Timestamp, Tree, TreeId, TreeValue,
+    make_root_commit, AnyObjectId, Backend, BackendError, BackendInitError, BackendLoadError,
+    BackendResult, ChangeId, Commit, CommitId, Conflict, ConflictId, ConflictTerm, FileId,
+    MillisSinceEpoch, ObjectId, Signature, SymlinkId, Timestamp, Tree, TreeId, TreeValue,
 };
-use crate::file_util::{IoResultExt as _, PathError};
+use crate::file_util::{IoResultExt as _, PathError, PendingSyncs};
 use crate::lock::FileLock;
 use crate::repo_path::{RepoPath, RepoPathComponent};
+use crate::settings::DurabilityMode;
 use crate::stacked_table::{
     MutableTable, ReadonlyTable, TableSegment, TableStore, TableStoreError,
 };
@@ -96,6 +97,7 @@ pub struct GitBackend {
     empty_tree_id: TreeId,
     extra_metadata_store: TableStore,
     cached_extra_metadata: Mutex<Option<Arc<ReadonlyTable>>>,
+    pending_syncs: PendingSyncs,
 }
 
 impl GitBackend {
@@ -110,6 +112,7 @@ impl GitBackend {
             empty_tree_id,
             extra_metadata_store,
             cached_extra_metadata: Mutex::new(None),
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
@@ -161,6 +164,15 @@ impl GitBackend {
         git2::Repository::open(path).unwrap()
     }
 
+    /// Records the loose object file Git wrote for `oid` so `flush()` syncs
+    /// it. If the object was already in a pack, there's no such file and
+    /// nothing to sync.
+    fn record_written_object(&self, repo: &git2::Repository, oid: Oid) {
+        let hex = oid.to_string();
+        let path = repo.path().join("objects").join(&hex[..2]).join(&hex[2..]);
+        self.pending_syncs.push(path);
+    }
+
     fn cached_extra_metadata_table(&self) -> BackendResult<Arc<ReadonlyTable>> {
         let mut locked_head = self.cached_extra_metadata.lock().unwrap();
         match locked_head.as_ref() {
@@ -380,6 +392,18 @@ impl Backend for GitBackend {
         Ok(Box::new(Cursor::new(content)))
     }
 
+    fn file_size(&self, _path: &RepoPath, id: &FileId) -> BackendResult<u64> {
+        let git_blob_id = validate_git_object_id(id)?;
+        let locked_repo = self.repo.lock().unwrap();
+        let odb = locked_repo
+            .odb()
+            .map_err(|err| map_not_found_err(err, id))?;
+        let (size, _) = odb
+            .read_header(git_blob_id)
+            .map_err(|err| map_not_found_err(err, id))?;
+        Ok(size as u64)
+    }
+
     fn write_file(&self, _path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
         let mut bytes = Vec::new();
         contents.read_to_end(&mut bytes).unwrap();
@@ -390,6 +414,7 @@ impl Backend for GitBackend {
                 object_type: "file",
                 source: Box::new(err),
             })?;
+        self.record_written_object(&locked_repo, oid);
         Ok(FileId::new(oid.as_bytes().to_vec()))
     }
 
@@ -417,6 +442,7 @@ impl Backend for GitBackend {
                 object_type: "symlink",
                 source: Box::new(err),
             })?;
+        self.record_written_object(&locked_repo, oid);
         Ok(SymlinkId::new(oid.as_bytes().to_vec()))
     }
 
@@ -524,6 +550,7 @@ impl Backend for GitBackend {
             object_type: "tree",
             source: Box::new(err),
         })?;
+        self.record_written_object(&locked_repo, oid);
         Ok(TreeId::from_bytes(oid.as_bytes()))
     }
 
@@ -659,9 +686,10 @@ impl Backend for GitBackend {
         // repository is rsync-ed.
         let (table, table_lock) = self.read_extra_metadata_table_locked()?;
         let id = loop {
+            let no_gc_ref = create_no_gc_ref();
             let git_id = locked_repo
                 .commit(
-                    Some(&create_no_gc_ref()),
+                    Some(&no_gc_ref),
                     &author,
                     &committer,
                     message,
@@ -672,6 +700,8 @@ impl Backend for GitBackend {
                     object_type: "commit",
                     source: Box::new(err),
                 })?;
+            self.record_written_object(&locked_repo, git_id);
+            self.pending_syncs.push(locked_repo.path().join(&no_gc_ref));
             let id = CommitId::from_bytes(git_id.as_bytes());
             match table.get_value(id.as_bytes()) {
                 Some(existing_extras) if existing_extras != extras => {
@@ -703,6 +733,54 @@ impl Backend for GitBackend {
         self.save_extra_metadata_table(mut_table, &table_lock)?;
         Ok((id, contents))
     }
+
+    fn set_durability_mode(&self, mode: DurabilityMode) {
+        let enabled = mode == DurabilityMode::Sync;
+        self.pending_syncs.set_enabled(enabled);
+        self.extra_metadata_store.set_sync_enabled(enabled);
+    }
+
+    /// Syncs the loose objects and refs written by this backend, and the
+    /// extra metadata tables, to disk.
+    fn flush(&self) -> BackendResult<()> {
+        self.pending_syncs
+            .sync_all()
+            .map_err(|err| BackendError::Other(err.into()))?;
+        self.extra_metadata_store
+            .sync()
+            .map_err(|err| BackendError::Other(err.into()))
+    }
+
+    /// Lists the commits, trees and blobs in the git object database. Git
+    /// doesn't record what a blob is used for, so symlinks and conflicts are
+    /// reported as files too.
+    fn all_object_ids(&self) -> BackendResult<Vec<AnyObjectId>> {
+        let locked_repo = self.repo.lock().unwrap();
+        let odb = locked_repo
+            .odb()
+            .map_err(|err| BackendError::Other(err.into()))?;
+        let mut oids = vec![];
+        odb.foreach(|oid| {
+            oids.push(*oid);
+            true
+        })
+        .map_err(|err| BackendError::Other(err.into()))?;
+        let mut ids = vec![];
+        // Objects may be listed more than once if they're in several packs
+        for oid in oids.into_iter().sorted().dedup() {
+            let (_, kind) = odb
+                .read_header(oid)
+                .map_err(|err| BackendError::Other(err.into()))?;
+            let bytes = oid.as_bytes().to_vec();
+            match kind {
+                git2::ObjectType::Commit => ids.push(AnyObjectId::Commit(CommitId::new(bytes))),
+                git2::ObjectType::Tree => ids.push(AnyObjectId::Tree(TreeId::new(bytes))),
+                git2::ObjectType::Blob => ids.push(AnyObjectId::File(FileId::new(bytes))),
+                _ => {}
+            }
+        }
+        Ok(ids)
+    }
 }
 
 fn conflict_term_list_to_json(parts: &[ConflictTerm]) -> serde_json::Value {
@@ -1024,6 +1102,38 @@ mod tests {
         assert_eq!(actual_commit2, commit2);
     }
 
+    #[test]
+    fn flush_syncs_written_objects() {
+        let temp_dir = testutils::new_temp_dir();
+        let store = GitBackend::init_internal(temp_dir.path()).unwrap();
+        store.set_durability_mode(DurabilityMode::Sync);
+        let commit = Commit {
+            parents: vec![store.root_commit_id().clone()],
+            predecessors: vec![],
+            root_tree: store.empty_tree_id().clone(),
+            change_id: ChangeId::new(vec![]),
+            description: "initial".to_string(),
+            author: create_signature(),
+            committer: create_signature(),
+        };
+        let commit_id = store.write_commit(commit).unwrap().0;
+        let pending_paths = store.pending_syncs.paths();
+        // The commit object and the ref keeping it alive
+        assert_eq!(pending_paths.len(), 2);
+        assert!(pending_paths
+            .iter()
+            .any(|path| path.ends_with(&commit_id.hex()[2..])));
+        assert!(pending_paths.iter().all(|path| path.is_file()));
+        store.flush().unwrap();
+        assert!(store.pending_syncs.paths().is_empty());
+
+        store.set_durability_mode(DurabilityMode::Async);
+        store
+            .write_file(&RepoPath::root(), &mut "contents".as_bytes())
+            .unwrap();
+        assert!(store.pending_syncs.paths().is_empty());
+    }
+
     fn git_id(commit_id: &CommitId) -> Oid {
         Oid::from_bytes(commit_id.as_bytes()).unwrap()
     }
@@ -18,7 +18,7 @@ use std::iter;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
-use itertools::Itertools as _;
+use itertools::{Either, Itertools as _};
 use once_cell::unsync::OnceCell;
 
 use crate::backend::{self, ChangeId, CommitId, ObjectId};
@@ -302,6 +302,32 @@ where
             return PrefixResolution::AmbiguousMatch;
         }
 
+        collect(self.prefix_range(&source, prefix), entry_mapper)
+    }
+
+    /// Returns the distinct keys with the given prefix, in ascending order.
+    pub fn keys_with_prefix<S>(&self, source: S, prefix: &HexPrefix) -> Vec<K>
+    where
+        S: IdIndexSource<P>,
+        S::Entry: IdIndexSourceEntry<K>,
+    {
+        self.prefix_range(&source, prefix)
+            .map(|(key, _)| key)
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    fn prefix_range<'a, S>(
+        &'a self,
+        source: &'a S,
+        prefix: &'a HexPrefix,
+    ) -> impl Iterator<Item = (K, S::Entry)> + 'a
+    where
+        S: IdIndexSource<P>,
+        S::Entry: IdIndexSourceEntry<K>,
+    {
+        let min_bytes = prefix.min_prefix_bytes();
         let to_key_entry_pair = |(_, pointer): &(_, P)| -> (K, S::Entry) {
             let entry = source.entry_at(pointer);
             (entry.to_key(), entry)
@@ -314,10 +340,10 @@ where
             let pos = self.index.partition_point(|(s, _)| s < short_bytes);
             let range = self.index[pos..]
                 .iter()
-                .take_while(|(s, _)| s == short_bytes)
+                .take_while(move |(s, _)| s == short_bytes)
                 .map(to_key_entry_pair)
                 .filter(|(k, _)| prefix.matches(k));
-            collect(range, entry_mapper)
+            Either::Left(range)
         } else {
             // Otherwise, use prefix.matches() to deal with odd byte. Since the prefix is
             // covered by short key width, we're sure that the matching prefixes are sorted.
@@ -326,7 +352,7 @@ where
                 .iter()
                 .map(to_key_entry_pair)
                 .take_while(|(k, _)| prefix.matches(k));
-            collect(range, entry_mapper)
+            Either::Right(range)
         }
     }
 
@@ -576,6 +602,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_id_index_keys_with_prefix() {
+        let source = vec![
+            (ChangeId::from_hex("0000"), 0),
+            (ChangeId::from_hex("0099"), 1),
+            (ChangeId::from_hex("0099"), 2),
+            (ChangeId::from_hex("0aaa"), 3),
+            (ChangeId::from_hex("0aab"), 4),
+        ];
+        let id_index = build_id_index::<_, 1>(&source);
+        let keys_with_prefix =
+            |prefix: &str| id_index.keys_with_prefix(&*source, &HexPrefix::new(prefix).unwrap());
+        assert_eq!(
+            keys_with_prefix("0"),
+            vec![
+                ChangeId::from_hex("0000"),
+                ChangeId::from_hex("0099"),
+                ChangeId::from_hex("0aaa"),
+                ChangeId::from_hex("0aab"),
+            ]
+        );
+        assert_eq!(
+            keys_with_prefix("0aa"),
+            vec![ChangeId::from_hex("0aaa"), ChangeId::from_hex("0aab")]
+        );
+        assert_eq!(keys_with_prefix("0099"), vec![ChangeId::from_hex("0099")]);
+        assert_eq!(keys_with_prefix("1"), vec![]);
+    }
+
     #[test]
     fn test_lookup_exact() {
         // No crash if empty
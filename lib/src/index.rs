@@ -25,6 +25,7 @@ use crate::commit::Commit;
 use crate::op_store::OperationId;
 use crate::operation::Operation;
 use crate::revset::{ResolvedExpression, Revset, RevsetEvaluationError};
+use crate::settings::DurabilityMode;
 use crate::store::Store;
 
 #[derive(Debug, Error)]
@@ -45,6 +46,17 @@ pub trait IndexStore: Send + Sync + Debug {
         index: Box<dyn MutableIndex>,
         op_id: &OperationId,
     ) -> Result<Box<dyn ReadonlyIndex>, IndexWriteError>;
+
+    /// Tells the store which durability mode the repo was loaded with. With
+    /// `DurabilityMode::Sync`, the store should keep track of the index files
+    /// it writes so `flush()` can sync them.
+    fn set_durability_mode(&self, _mode: DurabilityMode) {}
+
+    /// Makes sure all index files written by `write_index()` since the
+    /// durability mode was set to `DurabilityMode::Sync` are on disk.
+    fn flush(&self) -> Result<(), IndexWriteError> {
+        Ok(())
+    }
 }
 
 pub trait Index: Send + Sync {
@@ -56,6 +68,36 @@ pub trait Index: Send + Sync {
 
     fn is_ancestor(&self, ancestor_id: &CommitId, descendant_id: &CommitId) -> bool;
 
+    /// Returns the number of edges on the shortest path from `descendant_id`
+    /// up to `ancestor_id`, or `None` if it's not an ancestor.
+    ///
+    /// The default implementation evaluates the ancestors of `descendant_id`
+    /// one generation at a time until it reaches `ancestor_id`. Indexes that
+    /// know the generation numbers of their commits should override it with
+    /// a search bounded by them.
+    fn distance(
+        &self,
+        ancestor_id: &CommitId,
+        descendant_id: &CommitId,
+        store: &Arc<Store>,
+    ) -> Result<Option<usize>, RevsetEvaluationError> {
+        if !self.is_ancestor(ancestor_id, descendant_id) {
+            return Ok(None);
+        }
+        let mut distance = 0;
+        loop {
+            let expression = ResolvedExpression::Ancestors {
+                heads: Box::new(ResolvedExpression::Commits(vec![descendant_id.clone()])),
+                generation: distance..distance + 1,
+            };
+            let revset = self.evaluate_revset(&expression, store)?;
+            if revset.iter().any(|id| id == *ancestor_id) {
+                return Ok(Some(distance as usize));
+            }
+            distance += 1;
+        }
+    }
+
     fn common_ancestors(&self, set1: &[CommitId], set2: &[CommitId]) -> Vec<CommitId>;
 
     fn heads(&self, candidates: &mut dyn Iterator<Item = &CommitId>) -> Vec<CommitId>;
@@ -20,7 +20,9 @@
 #[macro_use]
 mod content_hash;
 
+pub mod annotate;
 pub mod backend;
+pub mod cancellation;
 pub mod commit;
 pub mod commit_builder;
 pub mod conflicts;
@@ -47,6 +49,7 @@ pub mod nightly_shims;
 pub mod op_heads_store;
 pub mod op_store;
 pub mod operation;
+pub mod patch;
 #[allow(missing_docs)]
 pub mod protos;
 pub mod refs;
@@ -18,7 +18,7 @@ use std::any::Any;
 use std::fmt::Debug;
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
 use blake2::{Blake2b512, Digest};
@@ -26,16 +26,18 @@ use prost::Message;
 use tempfile::NamedTempFile;
 
 use crate::backend::{
-    make_root_commit, Backend, BackendError, BackendResult, ChangeId, Commit, CommitId, Conflict,
-    ConflictId, ConflictTerm, FileId, MillisSinceEpoch, ObjectId, Signature, SymlinkId, Timestamp,
-    Tree, TreeId, TreeValue,
+    make_root_commit, AnyObjectId, Backend, BackendError, BackendResult, ChangeId, Commit,
+    CommitId, Conflict, ConflictId, ConflictTerm, FileId, MillisSinceEpoch, ObjectId, Signature,
+    SymlinkId, Timestamp, Tree, TreeId, TreeValue,
 };
 use crate::content_hash::blake2b_hash;
-use crate::file_util::persist_content_addressed_temp_file;
+use crate::file_util::{persist_content_addressed_temp_file, PendingSyncs};
 use crate::repo_path::{RepoPath, RepoPathComponent};
+use crate::settings::DurabilityMode;
 
 const COMMIT_ID_LENGTH: usize = 64;
 const CHANGE_ID_LENGTH: usize = 16;
+const ZSTD_FRAME_HEADER_SIZE_MAX: u64 = 18;
 
 fn map_not_found_err(err: std::io::Error, id: &impl ObjectId) -> BackendError {
     if err.kind() == std::io::ErrorKind::NotFound {
@@ -63,6 +65,7 @@ pub struct LocalBackend {
     root_commit_id: CommitId,
     root_change_id: ChangeId,
     empty_tree_id: TreeId,
+    pending_syncs: PendingSyncs,
 }
 
 impl LocalBackend {
@@ -89,9 +92,16 @@ impl LocalBackend {
             root_commit_id,
             root_change_id,
             empty_tree_id,
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
+    fn persist_object(&self, temp_file: NamedTempFile, path: PathBuf) -> BackendResult<()> {
+        persist_content_addressed_temp_file(temp_file, &path).map_err(to_other_err)?;
+        self.pending_syncs.push(path);
+        Ok(())
+    }
+
     fn file_path(&self, id: &FileId) -> PathBuf {
         self.path.join("files").join(id.hex())
     }
@@ -136,6 +146,23 @@ impl Backend for LocalBackend {
         Ok(Box::new(zstd::Decoder::new(file).map_err(to_other_err)?))
     }
 
+    fn file_size(&self, _path: &RepoPath, id: &FileId) -> BackendResult<u64> {
+        let path = self.file_path(id);
+        let mut file = File::open(path).map_err(|err| map_not_found_err(err, id))?;
+        // The zstd frame header includes the content size if it was known when
+        // the file was compressed. Otherwise, decompress the file to count it.
+        let mut header = vec![];
+        (&mut file)
+            .take(ZSTD_FRAME_HEADER_SIZE_MAX)
+            .read_to_end(&mut header)
+            .map_err(to_other_err)?;
+        if let Ok(Some(size)) = zstd::zstd_safe::get_frame_content_size(&header) {
+            return Ok(size);
+        }
+        let mut decoder = zstd::Decoder::new(header.chain(file)).map_err(to_other_err)?;
+        io::copy(&mut decoder, &mut io::sink()).map_err(to_other_err)
+    }
+
     fn write_file(&self, _path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
         let temp_file = NamedTempFile::new_in(&self.path).map_err(to_other_err)?;
         let mut encoder = zstd::Encoder::new(temp_file.as_file(), 0).map_err(to_other_err)?;
@@ -157,8 +184,7 @@ impl Backend for LocalBackend {
         encoder.finish().map_err(to_other_err)?;
         let id = FileId::new(hasher.finalize().to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.file_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.file_path(&id))?;
         Ok(id)
     }
 
@@ -179,8 +205,7 @@ impl Backend for LocalBackend {
         hasher.update(target.as_bytes());
         let id = SymlinkId::new(hasher.finalize().to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.symlink_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.symlink_path(&id))?;
         Ok(id)
     }
 
@@ -215,8 +240,7 @@ impl Backend for LocalBackend {
 
         let id = TreeId::new(blake2b_hash(tree).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.tree_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.tree_path(&id))?;
         Ok(id)
     }
 
@@ -239,8 +263,7 @@ impl Backend for LocalBackend {
 
         let id = ConflictId::new(blake2b_hash(conflict).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.conflict_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.conflict_path(&id))?;
         Ok(id)
     }
 
@@ -270,10 +293,44 @@ impl Backend for LocalBackend {
 
         let id = CommitId::new(blake2b_hash(&commit).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.commit_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.commit_path(&id))?;
         Ok((id, commit))
     }
+
+    fn set_durability_mode(&self, mode: DurabilityMode) {
+        self.pending_syncs.set_enabled(mode == DurabilityMode::Sync);
+    }
+
+    fn flush(&self) -> BackendResult<()> {
+        self.pending_syncs.sync_all().map_err(to_other_err)
+    }
+
+    fn all_object_ids(&self) -> BackendResult<Vec<AnyObjectId>> {
+        let mut ids = vec![];
+        for dir_name in ["commits", "trees", "files", "symlinks", "conflicts"] {
+            for entry in fs::read_dir(self.path.join(dir_name)).map_err(to_other_err)? {
+                let entry = entry.map_err(to_other_err)?;
+                let file_name = entry.file_name();
+                let bytes = file_name
+                    .to_str()
+                    .and_then(|name| hex::decode(name).ok())
+                    .ok_or_else(|| {
+                        to_other_err(format!(
+                            "Unexpected file {} in the store",
+                            entry.path().display()
+                        ))
+                    })?;
+                ids.push(match dir_name {
+                    "commits" => AnyObjectId::Commit(CommitId::new(bytes)),
+                    "trees" => AnyObjectId::Tree(TreeId::new(bytes)),
+                    "files" => AnyObjectId::File(FileId::new(bytes)),
+                    "symlinks" => AnyObjectId::Symlink(SymlinkId::new(bytes)),
+                    _ => AnyObjectId::Conflict(ConflictId::new(bytes)),
+                });
+            }
+        }
+        Ok(ids)
+    }
 }
 
 pub fn commit_to_proto(commit: &Commit) -> crate::protos::local_store::Commit {
@@ -437,3 +494,57 @@ fn conflict_term_to_proto(part: &ConflictTerm) -> crate::protos::local_store::co
         content: Some(tree_value_to_proto(&part.value)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_file_compressed() {
+        let temp_dir = testutils::new_temp_dir();
+        let backend = LocalBackend::init(temp_dir.path());
+        let path = RepoPath::from_internal_string("file");
+
+        let content = "some compressible text\n".repeat(1000).into_bytes();
+        let id = backend.write_file(&path, &mut content.as_slice()).unwrap();
+        // The id is computed over the uncompressed content
+        assert_eq!(id.as_bytes(), Blake2b512::digest(&content).as_slice());
+
+        let mut read_content = vec![];
+        backend
+            .read_file(&path, &id)
+            .unwrap()
+            .read_to_end(&mut read_content)
+            .unwrap();
+        assert_eq!(read_content, content);
+        let stored_size = fs::metadata(backend.file_path(&id)).unwrap().len();
+        assert!(stored_size < content.len() as u64 / 10);
+    }
+
+    #[test]
+    fn file_size() {
+        let temp_dir = testutils::new_temp_dir();
+        let backend = LocalBackend::init(temp_dir.path());
+        let path = RepoPath::from_internal_string("file");
+
+        // Files are written in a streaming fashion, so the size isn't known up
+        // front and the file has to be decompressed to count it
+        let content = "some compressible text\n".repeat(1000).into_bytes();
+        let id = backend.write_file(&path, &mut content.as_slice()).unwrap();
+        let stored_content = fs::read(backend.file_path(&id)).unwrap();
+        assert!(matches!(
+            zstd::zstd_safe::get_frame_content_size(&stored_content),
+            Ok(None)
+        ));
+        assert_eq!(backend.file_size(&path, &id).unwrap(), content.len() as u64);
+
+        // If the frame header has the size, it's used as is
+        let compressed = zstd::bulk::compress(&content, 0).unwrap();
+        assert!(matches!(
+            zstd::zstd_safe::get_frame_content_size(&compressed),
+            Ok(Some(size)) if size == content.len() as u64
+        ));
+        fs::write(backend.file_path(&id), compressed).unwrap();
+        assert_eq!(backend.file_size(&path, &id).unwrap(), content.len() as u64);
+    }
+}
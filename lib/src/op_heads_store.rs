@@ -16,6 +16,7 @@
 
 use std::collections::HashSet;
 use std::fmt::Debug;
+use std::io;
 use std::sync::Arc;
 
 use itertools::Itertools;
@@ -49,6 +50,12 @@ pub trait OpHeadsStore: Send + Sync + Debug {
 
     fn lock<'a>(&'a self) -> Box<dyn OpHeadsStoreLock<'a> + 'a>;
 
+    /// Makes sure the operation heads added and removed so far are on disk.
+    /// Called after publishing an operation with the `Sync` durability mode.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
     /// Removes operations in the input that are ancestors of other operations
     /// in the input. The ancestors are removed both from the list and from
     /// storage.
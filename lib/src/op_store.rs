@@ -22,6 +22,7 @@ use thiserror::Error;
 
 use crate::backend::{CommitId, Timestamp};
 use crate::content_hash::ContentHash;
+use crate::settings::DurabilityMode;
 
 content_hash! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
@@ -253,4 +254,15 @@ pub trait OpStore: Send + Sync + Debug {
     fn read_operation(&self, id: &OperationId) -> OpStoreResult<Operation>;
 
     fn write_operation(&self, contents: &Operation) -> OpStoreResult<OperationId>;
+
+    /// Tells the store which durability mode the repo was loaded with. With
+    /// `DurabilityMode::Sync`, the store should keep track of the views and
+    /// operations it writes so `flush()` can sync them.
+    fn set_durability_mode(&self, _mode: DurabilityMode) {}
+
+    /// Makes sure all views and operations written since the durability mode
+    /// was set to `DurabilityMode::Sync` are on disk.
+    fn flush(&self) -> OpStoreResult<()> {
+        Ok(())
+    }
 }
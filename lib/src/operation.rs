@@ -15,12 +15,18 @@
 #![allow(missing_docs)]
 
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt::{Debug, Error, Formatter};
 use std::hash::{Hash, Hasher};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use tempfile::NamedTempFile;
+
 use crate::backend::CommitId;
+use crate::file_util::{IoResultExt as _, PathError};
+use crate::lock::FileLock;
 use crate::op_store::{OpStore, OperationId, ViewId};
 use crate::{dag_walk, op_store};
 
@@ -93,6 +99,10 @@ impl Operation {
         parents
     }
 
+    /// Reads the view as of this operation. Views are stored in full rather
+    /// than as deltas from the parent operations, so this reads a single
+    /// object and doesn't load the index. That makes it cheap enough for
+    /// decorating every entry when rendering the operation log.
     pub fn view(&self) -> View {
         let data = self.op_store.read_view(&self.data.view_id).unwrap();
         View::new(self.op_store.clone(), self.data.view_id.clone(), data)
@@ -198,3 +208,83 @@ pub fn walk_ancestors(head_op: &Operation) -> impl Iterator<Item = Operation> {
     )
     .map(|OperationByEndTime(op)| op)
 }
+
+/// An operation in the operation graph, with edges to its parent operations.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpGraphNode {
+    pub operation: Operation,
+    /// The ids of the parent operations. There is more than one for merge
+    /// operations and none for the root operation. The parents may not be
+    /// included in the graph if it was truncated.
+    pub edges: Vec<OperationId>,
+}
+
+/// Returns up to `limit` operations from `head_op` and its ancestors in
+/// reverse topological order, each with edges to its parents.
+pub fn operation_graph(head_op: &Operation, limit: usize) -> Vec<OpGraphNode> {
+    walk_ancestors(head_op)
+        .take(limit)
+        .map(|operation| {
+            let edges = operation.parent_ids().clone();
+            OpGraphNode { operation, edges }
+        })
+        .collect()
+}
+
+fn pinned_operations_path(repo_path: &Path) -> PathBuf {
+    repo_path.join("op_store").join("pinned")
+}
+
+/// Returns the ids of the operations pinned in the repo at `repo_path`.
+pub fn pinned_operations(repo_path: &Path) -> Result<BTreeSet<OperationId>, PathError> {
+    let path = pinned_operations_path(repo_path);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(err) => return Err(err).context(&path),
+    };
+    content
+        .lines()
+        .map(|line| {
+            hex::decode(line)
+                .map(OperationId::new)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                .context(&path)
+        })
+        .collect()
+}
+
+/// Marks the operation as pinned, so it's meant to be kept even if the
+/// operation log is ever compacted.
+pub fn pin(repo_path: &Path, op_id: &OperationId) -> Result<(), PathError> {
+    update_pinned_operations(repo_path, |pinned| {
+        pinned.insert(op_id.clone());
+    })
+}
+
+/// Removes the operation from the set of pinned operations.
+pub fn unpin(repo_path: &Path, op_id: &OperationId) -> Result<(), PathError> {
+    update_pinned_operations(repo_path, |pinned| {
+        pinned.remove(op_id);
+    })
+}
+
+fn update_pinned_operations(
+    repo_path: &Path,
+    f: impl FnOnce(&mut BTreeSet<OperationId>),
+) -> Result<(), PathError> {
+    let path = pinned_operations_path(repo_path);
+    let _lock = FileLock::lock(path.with_extension("lock"));
+    let mut pinned = pinned_operations(repo_path)?;
+    f(&mut pinned);
+    let dir = path.parent().unwrap();
+    let mut temp_file = NamedTempFile::new_in(dir).context(dir)?;
+    for op_id in &pinned {
+        writeln!(temp_file, "{}", op_id.hex()).context(temp_file.path())?;
+    }
+    temp_file
+        .persist(&path)
+        .map_err(|err| err.error)
+        .context(&path)?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of commits as textual patches in the format used by
+//! `git format-patch`.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use chrono::{FixedOffset, TimeZone};
+
+use crate::backend::{BackendError, CommitId, ObjectId, Signature, TreeValue};
+use crate::commit::Commit;
+use crate::diff::{unified_diff_hunks, DiffLineType};
+use crate::matchers::EverythingMatcher;
+use crate::repo_path::RepoPath;
+use crate::store::Store;
+use crate::tree::Tree;
+
+/// A commit rendered as a patch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    /// The commit the patch was created from.
+    pub commit_id: CommitId,
+    /// The patch text: an mbox-style header with the commit's author, date and
+    /// description, followed by a unified diff of each changed file.
+    pub text: Vec<u8>,
+}
+
+/// Renders `commit` as the patch numbered `number` out of `total` in a series.
+/// The diff is taken against `base_tree`, which is usually the merged tree of
+/// the commit's parents.
+pub fn format_patch(
+    store: &Arc<Store>,
+    base_tree: &Tree,
+    commit: &Commit,
+    number: usize,
+    total: usize,
+) -> Result<Patch, BackendError> {
+    let mut text = vec![];
+    write_header(&mut text, commit, number, total).unwrap();
+    for (path, diff) in base_tree.diff(&commit.tree(), &EverythingMatcher) {
+        let (left_value, right_value) = diff.into_options();
+        let left_content = match &left_value {
+            Some(value) => value_content(store, &path, value)?,
+            None => vec![],
+        };
+        let right_content = match &right_value {
+            Some(value) => value_content(store, &path, value)?,
+            None => vec![],
+        };
+        let path_string = path.to_internal_file_string();
+        writeln!(text, "diff --git a/{path_string} b/{path_string}").unwrap();
+        match (&left_value, &right_value) {
+            (None, Some(value)) => {
+                writeln!(text, "new file mode {}", value_mode(value)).unwrap();
+                writeln!(text, "--- /dev/null").unwrap();
+                writeln!(text, "+++ b/{path_string}").unwrap();
+            }
+            (Some(value), None) => {
+                writeln!(text, "deleted file mode {}", value_mode(value)).unwrap();
+                writeln!(text, "--- a/{path_string}").unwrap();
+                writeln!(text, "+++ /dev/null").unwrap();
+            }
+            (Some(left), Some(right)) => {
+                if value_mode(left) != value_mode(right) {
+                    writeln!(text, "old mode {}", value_mode(left)).unwrap();
+                    writeln!(text, "new mode {}", value_mode(right)).unwrap();
+                }
+                if left_content != right_content {
+                    writeln!(text, "--- a/{path_string}").unwrap();
+                    writeln!(text, "+++ b/{path_string}").unwrap();
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+        write_hunks(&mut text, &left_content, &right_content);
+    }
+    Ok(Patch {
+        commit_id: commit.id().clone(),
+        text,
+    })
+}
+
+fn write_header(
+    output: &mut Vec<u8>,
+    commit: &Commit,
+    number: usize,
+    total: usize,
+) -> std::io::Result<()> {
+    let author = commit.author();
+    writeln!(
+        output,
+        "From {} Mon Sep 17 00:00:00 2001",
+        commit.id().hex()
+    )?;
+    writeln!(output, "From: {} <{}>", author.name, author.email)?;
+    writeln!(output, "Date: {}", format_date(author))?;
+    let description = commit.description().trim_end();
+    let (subject, body) = match description.split_once('\n') {
+        Some((subject, body)) => (subject, body.trim_start_matches('\n')),
+        None => (description, ""),
+    };
+    if total > 1 {
+        writeln!(output, "Subject: [PATCH {number}/{total}] {subject}")?;
+    } else {
+        writeln!(output, "Subject: [PATCH] {subject}")?;
+    }
+    writeln!(output)?;
+    if !body.is_empty() {
+        writeln!(output, "{body}")?;
+    }
+    writeln!(output, "---")
+}
+
+fn format_date(signature: &Signature) -> String {
+    let timestamp = &signature.timestamp;
+    FixedOffset::east_opt(timestamp.tz_offset * 60)
+        .and_then(|tz| tz.timestamp_millis_opt(timestamp.timestamp.0).single())
+        .map(|datetime| datetime.to_rfc2822())
+        .unwrap_or_default()
+}
+
+fn write_hunks(output: &mut Vec<u8>, left_content: &[u8], right_content: &[u8]) {
+    for hunk in unified_diff_hunks(left_content, right_content, 3) {
+        writeln!(
+            output,
+            "@@ -{},{} +{},{} @@",
+            hunk.left_line_range.start,
+            hunk.left_line_range.len(),
+            hunk.right_line_range.start,
+            hunk.right_line_range.len()
+        )
+        .unwrap();
+        for (line_type, content) in hunk.lines {
+            let prefix = match line_type {
+                DiffLineType::Context => b" ",
+                DiffLineType::Removed => b"-",
+                DiffLineType::Added => b"+",
+            };
+            output.extend_from_slice(prefix);
+            output.extend_from_slice(content);
+            if !content.ends_with(b"\n") {
+                output.extend_from_slice(b"\n\\ No newline at end of file\n");
+            }
+        }
+    }
+}
+
+fn value_mode(value: &TreeValue) -> &'static str {
+    match value {
+        TreeValue::File {
+            executable: true, ..
+        } => "100755",
+        TreeValue::Symlink(_) => "120000",
+        TreeValue::GitSubmodule(_) => "160000",
+        TreeValue::Tree(_) => "040000",
+        _ => "100644",
+    }
+}
+
+fn value_content(
+    store: &Arc<Store>,
+    path: &RepoPath,
+    value: &TreeValue,
+) -> Result<Vec<u8>, BackendError> {
+    let mut content = vec![];
+    match value {
+        TreeValue::File { id, .. } => {
+            store
+                .read_file(path, id)?
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: id.object_type(),
+                    hash: id.hex(),
+                    source: Box::new(err),
+                })?;
+        }
+        TreeValue::Symlink(id) => {
+            content = store.read_symlink(path, id)?.into_bytes();
+        }
+        TreeValue::Conflict(id) => {
+            let conflict = store.read_conflict(path, id)?;
+            conflict.materialize(store, path, &mut content).unwrap();
+        }
+        TreeValue::GitSubmodule(_) | TreeValue::Tree(_) => {}
+    }
+    Ok(content)
+}
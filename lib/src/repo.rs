@@ -14,47 +14,62 @@
 
 #![allow(missing_docs)]
 
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Formatter};
-use std::fs;
-use std::io::ErrorKind;
+use std::io::{self, ErrorKind, Read};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::{fs, slice};
 
+use blake2::{Blake2b512, Digest};
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
 use thiserror::Error;
 
 use self::dirty_cell::DirtyCell;
+use crate::annotate::{self, LineContent};
 use crate::backend::{
-    Backend, BackendError, BackendInitError, BackendLoadError, BackendResult, ChangeId, CommitId,
-    ObjectId, TreeId,
+    AnyObjectId, Backend, BackendError, BackendInitError, BackendLoadError, BackendResult,
+    ChangeId, CommitId, FileId, ObjectId, TreeId, TreeValue,
 };
 use crate::commit::Commit;
 use crate::commit_builder::CommitBuilder;
 use crate::default_index_store::DefaultIndexStore;
 use crate::default_submodule_store::DefaultSubmoduleStore;
+use crate::diff::{find_line_ranges, DiffHunk};
 use crate::file_util::{IoResultExt as _, PathError};
 use crate::git_backend::GitBackend;
-use crate::index::{HexPrefix, Index, IndexStore, MutableIndex, PrefixResolution, ReadonlyIndex};
+use crate::index::{
+    HexPrefix, Index, IndexStore, IndexWriteError, MutableIndex, PrefixResolution, ReadonlyIndex,
+};
 use crate::local_backend::LocalBackend;
+use crate::matchers::EverythingMatcher;
 use crate::op_heads_store::{self, OpHeadResolutionError, OpHeadsStore};
-use crate::op_store::{BranchTarget, OpStore, OperationId, RefTarget, WorkspaceId};
-use crate::operation::Operation;
+use crate::op_store::{BranchTarget, OpStore, OpStoreError, OperationId, RefTarget, WorkspaceId};
+use crate::operation::{self, OpGraphNode, Operation};
+use crate::patch::{self, Patch};
 use crate::refs::merge_ref_targets;
-use crate::revset::{self, ChangeIdIndex, Revset, RevsetExpression};
-use crate::rewrite::DescendantRebaser;
-use crate::settings::{RepoSettings, UserSettings};
+use crate::repo_path::{RepoPath, RepoPathJoin};
+use crate::revset::{
+    self, ChangeIdIndex, ResolvedExpression, Revset, RevsetEvaluationError, RevsetExpression,
+    RevsetIteratorExt, GENERATION_RANGE_FULL,
+};
+use crate::rewrite::{
+    merge_commit_trees, merge_commit_trees_without_repo, rebase_commit, DescendantRebaser,
+    RebaseAction, RebasePlan, RebaseResult, RebaseStats,
+};
+use crate::settings::{LogOrder, RepoSettings, UserSettings};
 use crate::simple_op_heads_store::SimpleOpHeadsStore;
 use crate::simple_op_store::SimpleOpStore;
-use crate::store::Store;
+use crate::store::{self, Store};
 use crate::submodule_store::SubmoduleStore;
 use crate::transaction::Transaction;
-use crate::tree::TreeMergeError;
+use crate::tree::{merge_trees, Diff, DiffKind, Tree, TreeMergeError};
 use crate::view::{RefName, View};
-use crate::{backend, dag_walk, op_store};
+use crate::{backend, dag_walk, diff, op_store};
 
 pub trait Repo {
     fn store(&self) -> &Arc<Store>;
@@ -67,6 +82,16 @@ pub trait Repo {
 
     fn submodule_store(&self) -> &Arc<dyn SubmoduleStore>;
 
+    /// Returns true if the commit is one of the visible heads.
+    fn is_head(&self, id: &CommitId) -> bool {
+        self.view().is_head(id)
+    }
+
+    /// Loads several commits at once. See `Store::get_commits()`.
+    fn get_commits(&self, ids: &[CommitId]) -> Vec<BackendResult<Commit>> {
+        self.store().get_commits(ids)
+    }
+
     fn resolve_change_id(&self, change_id: &ChangeId) -> Option<Vec<CommitId>> {
         // Replace this if we added more efficient lookup method.
         let prefix = HexPrefix::from_bytes(change_id.as_bytes());
@@ -79,9 +104,1075 @@ pub trait Repo {
 
     fn resolve_change_id_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<Vec<CommitId>>;
 
+    /// Returns the distinct change ids of visible commits matching `prefix`,
+    /// in ascending order.
+    fn change_ids_with_prefix(
+        &self,
+        prefix: &HexPrefix,
+    ) -> Result<Vec<ChangeId>, RevsetEvaluationError> {
+        let revset = evaluate_visible_commits(self.index(), self.view(), self.store())?;
+        let change_ids = revset.change_id_index().change_ids_with_prefix(prefix);
+        Ok(change_ids)
+    }
+
     fn shortest_unique_change_id_prefix_len(&self, target_id_bytes: &ChangeId) -> usize;
+
+    /// Returns the parents of the workspace's working-copy commit, or an empty
+    /// list if the workspace has no working-copy commit.
+    fn checkout_parents(&self, workspace_id: &WorkspaceId) -> BackendResult<Vec<Commit>> {
+        match self.view().get_wc_commit_id(workspace_id) {
+            Some(wc_commit_id) => {
+                let wc_commit = self.store().get_commit(wc_commit_id)?;
+                wc_commit
+                    .parent_ids()
+                    .iter()
+                    .map(|parent_id| self.store().get_commit(parent_id))
+                    .try_collect()
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the workspace's current branch: the local branch pointing to
+    /// the parent of the working-copy commit. Returns `None` if the
+    /// working-copy commit is a merge, if no branch or more than one branch
+    /// points to its parent, or if the workspace has no working-copy commit.
+    /// Conflicted branches are not considered.
+    fn current_branch(&self, workspace_id: &WorkspaceId) -> BackendResult<Option<String>> {
+        let parent_id = match self.checkout_parents(workspace_id)?.as_slice() {
+            [parent] => parent.id().clone(),
+            _ => return Ok(None),
+        };
+        let target = RefTarget::Normal(parent_id);
+        let names = self
+            .view()
+            .branches()
+            .iter()
+            .filter(|(_, branch_target)| branch_target.local_target.as_ref() == Some(&target))
+            .map(|(name, _)| name)
+            .collect_vec();
+        match names.as_slice() {
+            [name] => Ok(Some((*name).clone())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Collects what `jj status` shows for the workspace in one call. If
+    /// `wc_tree` is given, such as a tree just snapshotted from the working
+    /// copy, its changes and conflicts are reported instead of those of the
+    /// working-copy commit's tree. Returns `None` if the workspace has no
+    /// working-copy commit.
+    fn status(
+        &self,
+        workspace_id: &WorkspaceId,
+        wc_tree: Option<&Tree>,
+    ) -> Result<Option<RepoStatus>, TreeMergeError> {
+        let wc_commit = match self.view().get_wc_commit_id(workspace_id) {
+            Some(wc_commit_id) => self.store().get_commit(wc_commit_id)?,
+            None => return Ok(None),
+        };
+        let parents = self.checkout_parents(workspace_id)?;
+        let parent_tree = merge_commit_trees_without_repo(self.store(), self.index(), &parents)?;
+        let commit_tree;
+        let tree = match wc_tree {
+            Some(tree) => tree,
+            None => {
+                commit_tree = wc_commit.tree();
+                &commit_tree
+            }
+        };
+        let changes = parent_tree
+            .diff(tree, &EverythingMatcher)
+            .map(|(path, diff)| (path, DiffKind::from_diff(&diff)))
+            .collect();
+        let conflicts = tree.conflicts().into_iter().map(|(path, _)| path).collect();
+        let branches = self
+            .view()
+            .branches()
+            .iter()
+            .filter(|(_, branch_target)| {
+                branch_target
+                    .local_target
+                    .as_ref()
+                    .map_or(false, |target| target.adds().contains(wc_commit.id()))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        Ok(Some(RepoStatus {
+            wc_commit,
+            parents,
+            conflicts,
+            branches,
+            changes,
+        }))
+    }
+
+    /// Returns true if the commit doesn't change anything compared to its
+    /// parents. For a merge commit, that means its tree is the same as the
+    /// auto-merged parent trees.
+    fn commit_is_empty(&self, commit: &Commit) -> Result<bool, TreeMergeError> {
+        let parents: Vec<_> = commit
+            .parent_ids()
+            .iter()
+            .map(|parent_id| self.store().get_commit(parent_id))
+            .try_collect()?;
+        let parent_tree = merge_commit_trees_without_repo(self.store(), self.index(), &parents)?;
+        Ok(store::trees_equal(commit.tree_id(), parent_tree.id()))
+    }
+
+    /// Returns the paths that differ between the trees of two arbitrary
+    /// commits, in path order.
+    fn changed_paths(&self, from: &Commit, to: &Commit) -> Vec<(RepoPath, DiffKind)> {
+        from.tree()
+            .diff(&to.tree(), &EverythingMatcher)
+            .map(|(path, diff)| {
+                let kind = DiffKind::from_diff(&diff);
+                (path, kind)
+            })
+            .collect()
+    }
+
+    /// Counts the changes from `base` to `commit` per directory, grouping
+    /// paths by their first `depth` directory components. Files less than
+    /// `depth` directories deep are counted under their parent directory.
+    /// The result is sorted by directory.
+    fn diff_by_directory(
+        &self,
+        commit: &Commit,
+        base: &Commit,
+        depth: usize,
+    ) -> Vec<(RepoPath, DirDiffStat)> {
+        let mut stats: BTreeMap<RepoPath, DirDiffStat> = BTreeMap::new();
+        for (path, kind) in self.changed_paths(base, commit) {
+            let dir_components = &path.components()[..path.components().len() - 1];
+            let dir =
+                RepoPath::from_components(dir_components.iter().take(depth).cloned().collect());
+            let stat = stats.entry(dir).or_default();
+            match kind {
+                DiffKind::Added => stat.added += 1,
+                DiffKind::Removed => stat.removed += 1,
+                DiffKind::Modified => stat.modified += 1,
+                DiffKind::Conflicted => stat.conflicted += 1,
+            }
+        }
+        stats.into_iter().collect()
+    }
+
+    /// Looks up a commit given by the user, checking that it both exists and
+    /// is visible (i.e. an ancestor of one of the visible heads).
+    fn check_commit_arg(&self, id: &CommitId) -> Result<Commit, CommitArgError> {
+        let commit = match self.store().get_commit(id) {
+            Ok(commit) => commit,
+            Err(BackendError::ObjectNotFound { .. }) => {
+                return Err(CommitArgError::NotFound(id.clone()))
+            }
+            Err(err) => return Err(CommitArgError::Backend(err)),
+        };
+        if self.is_obsolete(id) {
+            Err(CommitArgError::NotVisible(id.clone()))
+        } else {
+            Ok(commit)
+        }
+    }
+
+    /// Returns true if the commit is hidden, i.e. not an ancestor of any of
+    /// the visible heads. That's the case for commits that have been rewritten
+    /// or abandoned.
+    fn is_obsolete(&self, id: &CommitId) -> bool {
+        let index = self.index();
+        !(index.has_id(id)
+            && self
+                .view()
+                .heads()
+                .iter()
+                .any(|head_id| index.is_ancestor(id, head_id)))
+    }
+
+    /// Returns the number of edges on the shortest ancestor path from `to` up
+    /// to `from`, or `None` if `from` isn't an ancestor of `to` (or either
+    /// commit isn't indexed).
+    fn distance(
+        &self,
+        from: &CommitId,
+        to: &CommitId,
+    ) -> Result<Option<usize>, RevsetEvaluationError> {
+        let index = self.index();
+        if !(index.has_id(from) && index.has_id(to)) {
+            return Ok(None);
+        }
+        index.distance(from, to, self.store())
+    }
+
+    /// Returns true if more than one visible commit has the change id.
+    fn is_divergent(&self, change_id: &ChangeId) -> bool {
+        self.resolve_change_id(change_id)
+            .map_or(false, |ids| ids.len() > 1)
+    }
+
+    /// Returns the commits in `among` that are ancestors of (or equal to) any
+    /// of the `targets`, in the order they appear in `among`. This is the
+    /// same as `among & ::targets` but doesn't walk all of `::targets`.
+    /// Commits that aren't indexed are never ancestors, nor have any.
+    fn ancestors_containing(&self, targets: &[CommitId], among: &[CommitId]) -> Vec<CommitId> {
+        let index = self.index();
+        let targets = targets.iter().filter(|id| index.has_id(id)).collect_vec();
+        among
+            .iter()
+            .filter(|id| {
+                index.has_id(id)
+                    && targets
+                        .iter()
+                        .any(|target_id| index.is_ancestor(id, target_id))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the commits that `op` added to the history, i.e. those reachable
+    /// from its view's heads but not from the heads of any of its parent
+    /// operations, parents before children. For a merge operation, commits
+    /// already reachable from either parent are excluded.
+    fn commits_introduced_by(&self, op: &Operation) -> BackendResult<Vec<CommitId>> {
+        let index = self.index();
+        let parent_heads = op
+            .parents()
+            .iter()
+            .flat_map(|parent_op| parent_op.view().heads().clone())
+            .filter(|id| index.has_id(id))
+            .collect_vec();
+        let mut introduced = HashSet::new();
+        let mut work = op
+            .view()
+            .heads()
+            .iter()
+            .filter(|id| index.has_id(id))
+            .cloned()
+            .collect_vec();
+        while let Some(id) = work.pop() {
+            if introduced.contains(&id)
+                || parent_heads
+                    .iter()
+                    .any(|head_id| index.is_ancestor(&id, head_id))
+            {
+                continue;
+            }
+            let commit = self.store().get_commit(&id)?;
+            work.extend(commit.parent_ids().iter().cloned());
+            introduced.insert(id);
+        }
+        Ok(index.topo_order(&mut introduced.iter()))
+    }
+
+    /// Renders the commits introduced by `op` (see `commits_introduced_by()`)
+    /// as a series of patches, parents before children. Each patch is a diff
+    /// against the merged tree of the commit's parents.
+    fn operation_patch_series(&self, op: &Operation) -> Result<Vec<Patch>, TreeMergeError> {
+        let store = self.store();
+        let commit_ids = self.commits_introduced_by(op)?;
+        let total = commit_ids.len();
+        let mut patches = vec![];
+        for (i, id) in commit_ids.iter().enumerate() {
+            let commit = store.get_commit(id)?;
+            let base_tree =
+                merge_commit_trees_without_repo(store, self.index(), &commit.parents())?;
+            patches.push(patch::format_patch(
+                store,
+                &base_tree,
+                &commit,
+                i + 1,
+                total,
+            )?);
+        }
+        Ok(patches)
+    }
+
+    /// Returns the point where the histories of `a` and `b` diverged, i.e.
+    /// their merge base. If `a` and `b` have several closest common
+    /// ancestors, the last one in topological order is chosen. Returns `None`
+    /// if they have no common ancestor. Use `ahead_behind()` to count the
+    /// commits on each side since then.
+    fn fork_point(
+        &self,
+        a: &CommitId,
+        b: &CommitId,
+    ) -> Result<Option<CommitId>, RevsetEvaluationError> {
+        let index = self.index();
+        check_indexed(index, [a, b])?;
+        let common_ancestors = index.common_ancestors(slice::from_ref(a), slice::from_ref(b));
+        Ok(index.topo_order(&mut common_ancestors.iter()).pop())
+    }
+
+    /// Returns the number of commits reachable from `a` but not from `b`, and
+    /// the number reachable from `b` but not from `a`.
+    fn ahead_behind(
+        &self,
+        a: &CommitId,
+        b: &CommitId,
+    ) -> Result<(usize, usize), RevsetEvaluationError> {
+        let index = self.index();
+        check_indexed(index, [a, b])?;
+        let count_range = |roots: &CommitId, heads: &CommitId| {
+            let expression = ResolvedExpression::Range {
+                roots: Box::new(ResolvedExpression::Commits(vec![roots.clone()])),
+                heads: Box::new(ResolvedExpression::Commits(vec![heads.clone()])),
+                generation: GENERATION_RANGE_FULL,
+            };
+            let revset = index.evaluate_revset(&expression, self.store())?;
+            Ok(revset.iter().count())
+        };
+        Ok((count_range(b, a)?, count_range(a, b)?))
+    }
+
+    /// Returns the visible descendants of `roots` (including the roots
+    /// themselves) that are not ancestors of any commit in `exclude`, newest
+    /// first. This is `roots:: ~ ::exclude`, i.e. the commits that would move
+    /// when rebasing `roots` while leaving `exclude` and its ancestors in
+    /// place.
+    fn descendants_excluding(
+        &self,
+        roots: &[CommitId],
+        exclude: &[CommitId],
+    ) -> Result<Vec<CommitId>, RevsetEvaluationError> {
+        check_indexed(self.index(), roots.iter().chain(exclude))?;
+        let expression = ResolvedExpression::Difference(
+            Box::new(ResolvedExpression::DagRange {
+                roots: Box::new(ResolvedExpression::Commits(roots.to_vec())),
+                heads: Box::new(ResolvedExpression::Commits(
+                    self.view().heads().iter().cloned().collect(),
+                )),
+                generation_from_roots: GENERATION_RANGE_FULL,
+            }),
+            Box::new(ResolvedExpression::Ancestors {
+                heads: Box::new(ResolvedExpression::Commits(exclude.to_vec())),
+                generation: GENERATION_RANGE_FULL,
+            }),
+        );
+        let revset = self.index().evaluate_revset(&expression, self.store())?;
+        Ok(revset.iter().collect())
+    }
+
+    /// Returns the objects reachable from the local branch targets that are
+    /// not reachable from the commits in `have`, such as the commits a remote
+    /// is known to have. These are the objects that need to be sent when
+    /// pushing the branches. Objects of older ancestors of `have` that only
+    /// reappear in new commits are reported too, since only the trees of the
+    /// commits at the boundary are compared against. Commits in `have` that
+    /// this repo doesn't know about are ignored, since they can't be ancestors
+    /// of the branch targets.
+    fn objects_missing_from(&self, have: &[CommitId]) -> BackendResult<HashSet<AnyObjectId>> {
+        let store = self.store();
+        let index = self.index();
+        let have = have.iter().filter(|id| index.has_id(id)).cloned().collect();
+        let targets = self
+            .view()
+            .branches()
+            .values()
+            .filter_map(|branch_target| branch_target.local_target.as_ref())
+            .flat_map(|target| target.adds())
+            .cloned()
+            .collect_vec();
+        let expression = ResolvedExpression::Difference(
+            Box::new(ResolvedExpression::Ancestors {
+                heads: Box::new(ResolvedExpression::Commits(targets)),
+                generation: GENERATION_RANGE_FULL,
+            }),
+            Box::new(ResolvedExpression::Ancestors {
+                heads: Box::new(ResolvedExpression::Commits(have)),
+                generation: GENERATION_RANGE_FULL,
+            }),
+        );
+        let missing_commits: Vec<Commit> = index
+            .evaluate_revset(&expression, store)
+            .map_err(revset_error_to_backend_error)?
+            .iter()
+            .filter(|id| id != store.root_commit_id())
+            .commits(store)
+            .try_collect()?;
+        let missing_commit_ids: HashSet<_> = missing_commits.iter().map(|c| c.id()).collect();
+        let mut have_objects = HashSet::new();
+        let boundary_ids: HashSet<_> = missing_commits
+            .iter()
+            .flat_map(|commit| commit.parent_ids())
+            .filter(|id| !missing_commit_ids.contains(id))
+            .collect();
+        for id in boundary_ids {
+            let commit = store.get_commit(id)?;
+            let tree_value = TreeValue::Tree(commit.tree_id().clone());
+            collect_objects(
+                store,
+                &RepoPath::root(),
+                &tree_value,
+                &HashSet::new(),
+                &mut have_objects,
+            )?;
+        }
+        let mut missing_objects = HashSet::new();
+        for commit in &missing_commits {
+            missing_objects.insert(AnyObjectId::Commit(commit.id().clone()));
+            let tree_value = TreeValue::Tree(commit.tree_id().clone());
+            collect_objects(
+                store,
+                &RepoPath::root(),
+                &tree_value,
+                &have_objects,
+                &mut missing_objects,
+            )?;
+        }
+        Ok(missing_objects)
+    }
+
+    /// Counts the visible commits whose commit id or change id shares its
+    /// first `length` hex digits with another visible commit's. Commits with
+    /// the same change id count as colliding with each other.
+    fn prefix_stats(&self, length: usize) -> Result<PrefixStats, RevsetEvaluationError> {
+        let revset = evaluate_visible_commits(self.index(), self.view(), self.store())?;
+        let mut commit_prefixes: HashMap<String, usize> = HashMap::new();
+        let mut change_prefixes: HashMap<String, usize> = HashMap::new();
+        for (commit_id, change_id) in revset.commit_change_ids() {
+            let commit_hex = commit_id.hex();
+            let change_hex = change_id.hex();
+            *commit_prefixes
+                .entry(commit_hex[..length.min(commit_hex.len())].to_owned())
+                .or_default() += 1;
+            *change_prefixes
+                .entry(change_hex[..length.min(change_hex.len())].to_owned())
+                .or_default() += 1;
+        }
+        let count_collisions = |prefixes: HashMap<String, usize>| {
+            prefixes.into_values().filter(|count| *count > 1).sum()
+        };
+        Ok(PrefixStats {
+            commit_id_collisions: count_collisions(commit_prefixes),
+            change_id_collisions: count_collisions(change_prefixes),
+        })
+    }
+
+    /// Returns groups of visible commits that have the same tree, parents,
+    /// author, and description, i.e. commits that only differ in change id
+    /// and committer. Only groups with more than one commit are returned.
+    fn duplicate_commits(&self) -> BackendResult<Vec<Vec<CommitId>>> {
+        let revset = evaluate_visible_commits(self.index(), self.view(), self.store())
+            .map_err(revset_error_to_backend_error)?;
+        let mut group_indices = HashMap::new();
+        let mut groups: Vec<Vec<CommitId>> = vec![];
+        for commit in revset.iter().commits(self.store()) {
+            let commit = commit?;
+            let author = commit.author();
+            let key = (
+                commit.tree_id().clone(),
+                commit.parent_ids().to_vec(),
+                author.name.clone(),
+                author.email.clone(),
+                author.timestamp.timestamp.0,
+                author.timestamp.tz_offset,
+                commit.description().to_owned(),
+            );
+            let index = *group_indices.entry(key).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[index].push(commit.id().clone());
+        }
+        groups.retain(|group| group.len() > 1);
+        Ok(groups)
+    }
+
+    /// Returns the first commit in `within`, other than `commit` itself, that
+    /// makes the same changes as `commit` relative to its first parent, e.g.
+    /// because one is a cherry-pick of the other. Like Git's patch ids, only
+    /// the changed lines are compared, so the commits may have different
+    /// parents as long as the changes apply cleanly to both.
+    fn find_equivalent(
+        &self,
+        commit: &Commit,
+        within: &[CommitId],
+    ) -> BackendResult<Option<CommitId>> {
+        let store = self.store();
+        let patch_id = compute_patch_id(store, commit)?;
+        for id in within {
+            if id != commit.id() && compute_patch_id(store, &store.get_commit(id)?)? == patch_id {
+                return Ok(Some(id.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the ids of commits that are referenced but can't be found in
+    /// the store: visible commits (including parents of other visible commits)
+    /// and predecessors of visible commits. This diagnoses a corrupt store
+    /// before reading the commits panics elsewhere.
+    fn missing_referenced_commits(&self) -> BackendResult<Vec<CommitId>> {
+        let store = self.store();
+        let revset = evaluate_visible_commits(self.index(), self.view(), store)
+            .map_err(revset_error_to_backend_error)?;
+        let mut missing = vec![];
+        let mut checked = HashSet::new();
+        let mut check = |id: &CommitId| -> BackendResult<Option<Commit>> {
+            if !checked.insert(id.clone()) {
+                return Ok(None);
+            }
+            match store.get_commit(id) {
+                Ok(commit) => Ok(Some(commit)),
+                Err(BackendError::ObjectNotFound { .. }) => {
+                    missing.push(id.clone());
+                    Ok(None)
+                }
+                Err(err) => Err(err),
+            }
+        };
+        for id in revset.iter() {
+            if let Some(commit) = check(&id)? {
+                for predecessor_id in commit.predecessor_ids() {
+                    check(predecessor_id)?;
+                }
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Returns the commits where the value at `path` changed, newest first,
+    /// along with the new value. Only the first parent of merge commits is
+    /// followed. Commits where the path was removed are not included.
+    fn path_history(
+        &self,
+        start: &CommitId,
+        path: &RepoPath,
+    ) -> BackendResult<Vec<(CommitId, TreeValue)>> {
+        let store = self.store();
+        let mut history = vec![];
+        let mut commit = store.get_commit(start)?;
+        let mut value = commit.tree().path_value(path);
+        loop {
+            let parent = match commit.parent_ids().first() {
+                Some(parent_id) => Some(store.get_commit(parent_id)?),
+                None => None,
+            };
+            let parent_value = parent
+                .as_ref()
+                .and_then(|parent| parent.tree().path_value(path));
+            if parent_value != value {
+                if let Some(value) = value {
+                    history.push((commit.id().clone(), value));
+                }
+            }
+            match parent {
+                Some(parent) => {
+                    commit = parent;
+                    value = parent_value;
+                }
+                None => break,
+            }
+        }
+        Ok(history)
+    }
+
+    /// Walks `start` and its ancestors lazily in reverse topological order,
+    /// yielding only the commits matching `predicate`. Ancestors are read as
+    /// the walk proceeds, so stopping early (e.g. with `take()`) avoids
+    /// reading the rest of the history.
+    fn walk_ancestors_where<'a>(
+        &self,
+        start: &Commit,
+        predicate: impl Fn(&Commit) -> bool + 'a,
+    ) -> Box<dyn Iterator<Item = Commit> + 'a>
+    where
+        Self: Sized,
+    {
+        // Lazily load commits based on a timestamp-based heuristic, like
+        // `operation::walk_ancestors()` does for operations.
+        let iter = dag_walk::topo_order_reverse_lazy(
+            vec![CommitByCommitterTimestamp(start.clone())],
+            |CommitByCommitterTimestamp(commit)| commit.id().clone(),
+            |CommitByCommitterTimestamp(commit)| {
+                commit.parents().into_iter().map(CommitByCommitterTimestamp)
+            },
+        )
+        .map(|CommitByCommitterTimestamp(commit)| commit)
+        .filter(move |commit| predicate(commit));
+        Box::new(iter)
+    }
+
+    /// Returns the newest commit without conflicts in its tree, starting at
+    /// `start` and following first parents. Returns `None` if every commit on
+    /// the way has conflicts, which can't happen for a repo whose root commit
+    /// has an empty tree.
+    fn latest_conflict_free_ancestor(&self, start: &CommitId) -> BackendResult<Option<CommitId>> {
+        let store = self.store();
+        let mut commit = store.get_commit(start)?;
+        while store
+            .get_tree(&RepoPath::root(), commit.tree_id())?
+            .has_conflict()
+        {
+            match commit.parent_ids().first() {
+                Some(parent_id) => commit = store.get_commit(parent_id)?,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(commit.id().clone()))
+    }
+
+    /// Returns the total size in bytes of the files in `tree`, which may be a
+    /// subtree. Files with identical contents are only counted once. Symlinks
+    /// and conflicts are not counted.
+    fn tree_size(&self, tree: &Tree) -> BackendResult<u64> {
+        let mut seen = HashSet::new();
+        let mut size = 0;
+        for (path, value) in tree.entries() {
+            if let TreeValue::File { id, .. } = value {
+                if seen.insert(id.clone()) {
+                    size += self.store().object_size(&path, &id)?;
+                }
+            }
+        }
+        Ok(size)
+    }
+
+    /// Returns the total size in bytes of the files in `commit`'s tree. See
+    /// `tree_size()`.
+    fn commit_tree_size(&self, commit: &Commit) -> BackendResult<u64> {
+        self.tree_size(&commit.tree())
+    }
+
+    /// Counts the conflicted paths in `merge`'s tree, classified by whether
+    /// the sides disagree about the contents or about the type of the path.
+    fn merge_conflict_summary(&self, merge: &Commit) -> BackendResult<MergeConflictSummary> {
+        let mut summary = MergeConflictSummary::default();
+        for (path, conflict_id) in merge.tree().conflicts() {
+            let conflict = self.store().read_conflict(&path, &conflict_id)?;
+            let kinds: HashSet<_> = conflict
+                .removes()
+                .iter()
+                .chain(conflict.adds())
+                .flatten()
+                .map(std::mem::discriminant)
+                .collect();
+            if kinds.len() > 1 {
+                summary.type_conflicts += 1;
+            } else {
+                summary.content_conflicts += 1;
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Estimates the total size in bytes of the conflicted files in `commit`'s
+    /// tree once materialized with conflict markers, without materializing
+    /// them. For a file conflict, that's the size of all its terms plus a
+    /// marker line per term and the start and end markers, which is usually
+    /// an overestimate since unchanged regions only appear once. Other
+    /// conflicts are materialized as a short description.
+    fn estimate_materialized_size(&self, commit: &Commit) -> BackendResult<u64> {
+        // The length of a conflict marker line, e.g. "<<<<<<<\n"
+        const MARKER_LINE_LEN: u64 = 8;
+        let mut size = 0;
+        for (path, conflict_id) in commit.tree().conflicts() {
+            let conflict = self.store().read_conflict(&path, &conflict_id)?;
+            if let Some(file_conflict) = conflict.to_file_conflict() {
+                let terms = file_conflict
+                    .removes()
+                    .iter()
+                    .chain(file_conflict.adds())
+                    .collect_vec();
+                size += MARKER_LINE_LEN * (terms.len() as u64 + 2);
+                for file_id in terms.into_iter().flatten() {
+                    size += self.store().object_size(&path, file_id)?;
+                }
+            } else {
+                let mut description = vec![];
+                conflict.describe(&mut description).unwrap();
+                size += description.len() as u64;
+            }
+        }
+        Ok(size)
+    }
+
+    /// Renders a one-line summary of `commit` without going through the
+    /// templater, e.g. "abc123def456 (main) Some One: Fix the frobnicator".
+    /// Local branches pointing to the commit are listed in parentheses, with
+    /// conflicted ones suffixed by "??". Commits that are not visible are
+    /// marked "(hidden)" and commits with conflicts "(conflict)".
+    fn describe_commit(&self, commit: &Commit) -> String {
+        let hex = commit.id().hex();
+        let mut summary = hex.get(..12).unwrap_or(&hex).to_owned();
+        let branch_names = self
+            .view()
+            .branches()
+            .iter()
+            .filter_map(|(name, branch_target)| {
+                let target = branch_target.local_target.as_ref()?;
+                if !target.adds().contains(commit.id()) {
+                    None
+                } else if target.is_conflict() {
+                    Some(format!("{name}??"))
+                } else {
+                    Some(name.clone())
+                }
+            })
+            .collect_vec();
+        if !branch_names.is_empty() {
+            summary.push_str(&format!(" ({})", branch_names.join(" ")));
+        }
+        if self.is_obsolete(commit.id()) {
+            summary.push_str(" (hidden)");
+        }
+        if commit.tree().has_conflict() {
+            summary.push_str(" (conflict)");
+        }
+        let first_line = commit
+            .description()
+            .lines()
+            .next()
+            .unwrap_or("(no description set)");
+        summary.push_str(&format!(" {}: {first_line}", commit.author().name));
+        summary
+    }
+
+    /// Attributes each line of the file at `path` in `commit` to the commit
+    /// that last changed it. See `annotate::annotate()`.
+    fn annotate(
+        &self,
+        commit: &Commit,
+        path: &RepoPath,
+    ) -> BackendResult<Vec<(LineContent, CommitId)>> {
+        annotate::annotate(self.store(), commit, path)
+    }
+
+    /// Returns all visible commits in the given order.
+    fn log_order(&self, order: LogOrder) -> BackendResult<Vec<Commit>> {
+        let revset = evaluate_visible_commits(self.index(), self.view(), self.store())
+            .map_err(revset_error_to_backend_error)?;
+        let mut commits: Vec<Commit> = revset.iter().commits(self.store()).try_collect()?;
+        order.sort_commits(&mut commits);
+        Ok(commits)
+    }
+
+    /// Returns the ids of the visible commits other than the root commit, in
+    /// topological order (children first), stopping after `limit` commits.
+    /// Unlike `log_order()`, this doesn't read the commits, so it's cheap even
+    /// with a large limit. Rewritten and abandoned commits are never included
+    /// since they're not visible.
+    fn visible_commits(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Vec<CommitId>, RevsetEvaluationError> {
+        let revset = evaluate_visible_commits(self.index(), self.view(), self.store())?;
+        let root_commit_id = self.store().root_commit_id();
+        Ok(revset
+            .iter()
+            .filter(|id| id != root_commit_id)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Returns the commits reachable from the targets of local branches.
+    /// Unlike the set of visible commits, this excludes commits that are only
+    /// reachable from anonymous heads.
+    fn commits_reachable_from_branches(&self) -> Result<HashSet<CommitId>, RevsetEvaluationError> {
+        let branch_target_ids = self
+            .view()
+            .branches()
+            .values()
+            .filter_map(|branch_target| branch_target.local_target.as_ref())
+            .flat_map(|target| target.adds())
+            .unique()
+            .cloned()
+            .collect_vec();
+        let revset = evaluate_ancestors(self.index(), branch_target_ids, self.store())?;
+        Ok(revset.iter().collect())
+    }
+
+    /// Returns the local branches whose target is conflicted, sorted by name.
+    fn conflicted_branches(&self) -> Vec<(String, RefTarget)> {
+        self.view()
+            .branches()
+            .iter()
+            .filter_map(|(name, branch_target)| match &branch_target.local_target {
+                Some(target) if target.is_conflict() => Some((name.clone(), target.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The result of `Repo::status()`.
+#[derive(Debug, Clone)]
+pub struct RepoStatus {
+    pub wc_commit: Commit,
+    pub parents: Vec<Commit>,
+    /// The conflicted paths, in path order.
+    pub conflicts: Vec<RepoPath>,
+    /// The local branches pointing to the working-copy commit, including
+    /// conflicted ones.
+    pub branches: Vec<String>,
+    /// The changes compared to the parents, in path order.
+    pub changes: Vec<(RepoPath, DiffKind)>,
+}
+
+/// Per-directory change counts returned by `Repo::diff_by_directory()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirDiffStat {
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+    /// Paths that are conflicted on either side.
+    pub conflicted: usize,
+}
+
+/// The result of `Repo::prefix_stats()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixStats {
+    /// The number of commits whose commit id prefix isn't unique.
+    pub commit_id_collisions: usize,
+    /// The number of commits whose change id prefix isn't unique.
+    pub change_id_collisions: usize,
+}
+
+/// The result of `Repo::merge_conflict_summary()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeConflictSummary {
+    /// Paths where all sides have the same type (e.g. all files) but
+    /// different contents. Sides that delete the path don't count as a type.
+    pub content_conflicts: usize,
+    /// Paths where the sides have different types, e.g. a file and a
+    /// directory.
+    pub type_conflicts: usize,
+}
+
+impl MergeConflictSummary {
+    pub fn total(&self) -> usize {
+        self.content_conflicts + self.type_conflicts
+    }
+}
+
+/// Hashes the changes `commit` makes relative to its first parent. For files
+/// changed on both sides, only the changed lines are hashed, not their context
+/// or position.
+fn compute_patch_id(store: &Arc<Store>, commit: &Commit) -> BackendResult<Vec<u8>> {
+    let base_tree = match commit.parent_ids().first() {
+        Some(parent_id) => store.get_commit(parent_id)?.tree(),
+        None => store.get_tree(&RepoPath::root(), store.empty_tree_id())?,
+    };
+    let mut hasher = Blake2b512::new();
+    for (path, diff) in base_tree.diff(&commit.tree(), &EverythingMatcher) {
+        hasher.update(path.to_internal_file_string().as_bytes());
+        hasher.update(b"\0");
+        match diff.into_options() {
+            (
+                Some(TreeValue::File {
+                    id: before_id,
+                    executable: before_executable,
+                }),
+                Some(TreeValue::File {
+                    id: after_id,
+                    executable: after_executable,
+                }),
+            ) => {
+                hasher.update([before_executable as u8, after_executable as u8]);
+                let before_content = read_file_content(store, &path, &before_id)?;
+                let after_content = read_file_content(store, &path, &after_id)?;
+                let line_diff = diff::Diff::for_tokenizer(
+                    &[&before_content, &after_content],
+                    &find_line_ranges,
+                );
+                for hunk in line_diff.hunks() {
+                    if let DiffHunk::Different(contents) = hunk {
+                        hasher.update(b"-");
+                        hasher.update(contents[0]);
+                        hasher.update(b"+");
+                        hasher.update(contents[1]);
+                    }
+                }
+            }
+            (before, after) => {
+                for value in [before, after] {
+                    hash_tree_value(store, &path, value.as_ref(), &mut hasher)?;
+                }
+            }
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+fn hash_tree_value(
+    store: &Store,
+    path: &RepoPath,
+    value: Option<&TreeValue>,
+    hasher: &mut Blake2b512,
+) -> BackendResult<()> {
+    match value {
+        None => hasher.update(b"absent"),
+        Some(TreeValue::File { id, executable }) => {
+            hasher.update(if *executable {
+                b"executable"
+            } else {
+                b"file" as &[u8]
+            });
+            hasher.update(read_file_content(store, path, id)?);
+        }
+        Some(TreeValue::Symlink(id)) => {
+            hasher.update(b"symlink");
+            hasher.update(store.read_symlink(path, id)?.as_bytes());
+        }
+        Some(TreeValue::Tree(id)) => {
+            hasher.update(b"tree");
+            hasher.update(id.as_bytes());
+        }
+        Some(TreeValue::GitSubmodule(id)) => {
+            hasher.update(b"submodule");
+            hasher.update(id.as_bytes());
+        }
+        Some(TreeValue::Conflict(id)) => {
+            hasher.update(b"conflict");
+            hasher.update(id.as_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn read_file_content(store: &Store, path: &RepoPath, id: &FileId) -> BackendResult<Vec<u8>> {
+    let mut content = vec![];
+    store
+        .read_file(path, id)?
+        .read_to_end(&mut content)
+        .map_err(|err| BackendError::ReadObject {
+            object_type: id.object_type(),
+            hash: id.hex(),
+            source: Box::new(err),
+        })?;
+    Ok(content)
 }
 
+/// Returns the ids of all commits the view refers to, with duplicates.
+fn referenced_commit_ids(view: &op_store::View) -> Vec<CommitId> {
+    let ref_targets = view
+        .branches
+        .values()
+        .flat_map(|branch| {
+            branch
+                .local_target
+                .iter()
+                .chain(branch.remote_targets.values())
+        })
+        .chain(view.tags.values())
+        .chain(view.git_refs.values())
+        .chain(view.git_head.iter());
+    let mut ids = vec![];
+    ids.extend(view.head_ids.iter().cloned());
+    ids.extend(view.public_head_ids.iter().cloned());
+    ids.extend(view.wc_commit_ids.values().cloned());
+    for target in ref_targets {
+        ids.extend(target.adds().iter().cloned());
+        ids.extend(target.removes().iter().cloned());
+    }
+    ids
+}
+
+/// Evaluates the set of all commits reachable from the visible heads.
+fn evaluate_visible_commits<'index>(
+    index: &'index dyn Index,
+    view: &View,
+    store: &Arc<Store>,
+) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
+    evaluate_ancestors(index, view.heads().iter().cloned().collect(), store)
+}
+
+/// Adds `value` and the objects it references to `objects`, skipping the ones
+/// in `exclude` and the ones already in `objects`.
+fn collect_objects(
+    store: &Arc<Store>,
+    path: &RepoPath,
+    value: &TreeValue,
+    exclude: &HashSet<AnyObjectId>,
+    objects: &mut HashSet<AnyObjectId>,
+) -> BackendResult<()> {
+    let object_id = match value {
+        TreeValue::File { id, .. } => AnyObjectId::File(id.clone()),
+        TreeValue::Symlink(id) => AnyObjectId::Symlink(id.clone()),
+        TreeValue::Tree(id) => AnyObjectId::Tree(id.clone()),
+        TreeValue::Conflict(id) => AnyObjectId::Conflict(id.clone()),
+        TreeValue::GitSubmodule(_) => return Ok(()),
+    };
+    if exclude.contains(&object_id) || !objects.insert(object_id) {
+        return Ok(());
+    }
+    match value {
+        TreeValue::Tree(id) => {
+            let tree = store.get_tree(path, id)?;
+            for entry in tree.entries_non_recursive() {
+                let entry_path = path.join(entry.name());
+                collect_objects(store, &entry_path, entry.value(), exclude, objects)?;
+            }
+        }
+        TreeValue::Conflict(id) => {
+            let conflict = store.read_conflict(path, id)?;
+            for term in conflict.removes().iter().chain(conflict.adds()).flatten() {
+                collect_objects(store, path, term, exclude, objects)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct CommitByCommitterTimestamp(Commit);
+
+impl Ord for CommitByCommitterTimestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let self_timestamp = &self.0.committer().timestamp.timestamp;
+        let other_timestamp = &other.0.committer().timestamp.timestamp;
+        self_timestamp
+            .cmp(other_timestamp)
+            .then_with(|| self.0.cmp(&other.0)) // to comply with Eq
+    }
+}
+
+impl PartialOrd for CommitByCommitterTimestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Evaluates the set of all commits reachable from the given heads.
+fn evaluate_ancestors<'index>(
+    index: &'index dyn Index,
+    heads: Vec<CommitId>,
+    store: &Arc<Store>,
+) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
+    let expression = ResolvedExpression::Ancestors {
+        heads: Box::new(ResolvedExpression::Commits(heads)),
+        generation: GENERATION_RANGE_FULL,
+    };
+    index.evaluate_revset(&expression, store)
+}
+
+/// Returns an error if any of `ids` is missing from `index`, since querying
+/// the index about unknown commits panics.
+fn check_indexed<'a>(
+    index: &dyn Index,
+    ids: impl IntoIterator<Item = &'a CommitId>,
+) -> Result<(), RevsetEvaluationError> {
+    match ids.into_iter().find(|id| !index.has_id(id)) {
+        Some(id) => Err(RevsetEvaluationError::Other(format!(
+            "Commit {} is not in the index",
+            id.hex()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Converts a revset evaluation error for queries that report `BackendError`s.
+fn revset_error_to_backend_error(err: RevsetEvaluationError) -> BackendError {
+    match err {
+        RevsetEvaluationError::StoreError(err) => err,
+        RevsetEvaluationError::Other(message) => BackendError::Other(message.into()),
+    }
+}
+
+/// A repo as of a specific operation. The view and index never change after
+/// loading, so all queries against the same `ReadonlyRepo` see the same
+/// snapshot, even if other processes or transactions commit new operations in
+/// the meantime. Use `reload_at_head()` to pick up such operations.
 pub struct ReadonlyRepo {
     repo_path: PathBuf,
     store: Arc<Store>,
@@ -113,6 +1204,8 @@ pub enum RepoInitError {
     Backend(#[from] BackendInitError),
     #[error(transparent)]
     Path(#[from] PathError),
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
 }
 
 impl ReadonlyRepo {
@@ -145,6 +1238,8 @@ impl ReadonlyRepo {
         submodule_store_factory: impl FnOnce(&Path) -> Box<dyn SubmoduleStore>,
     ) -> Result<Arc<ReadonlyRepo>, RepoInitError> {
         let repo_path = repo_path.canonicalize().context(repo_path)?;
+        let repo_settings = user_settings.with_repo(&repo_path)?;
+        let durability_mode = repo_settings.durability_mode();
 
         let store_path = repo_path.join("store");
         fs::create_dir(&store_path).context(&store_path)?;
@@ -152,11 +1247,12 @@ impl ReadonlyRepo {
         let backend_path = store_path.join("type");
         fs::write(&backend_path, backend.name()).context(&backend_path)?;
         let store = Store::new(backend);
-        let repo_settings = user_settings.with_repo(&repo_path).unwrap();
+        store.set_durability_mode(durability_mode);
 
         let op_store_path = repo_path.join("op_store");
         fs::create_dir(&op_store_path).context(&op_store_path)?;
         let op_store = op_store_factory(&op_store_path);
+        op_store.set_durability_mode(durability_mode);
         let op_store_type_path = op_store_path.join("type");
         fs::write(&op_store_type_path, op_store.name()).context(&op_store_type_path)?;
         let op_store: Arc<dyn OpStore> = Arc::from(op_store);
@@ -188,6 +1284,7 @@ impl ReadonlyRepo {
         let index_path = repo_path.join("index");
         fs::create_dir(&index_path).context(&index_path)?;
         let index_store = index_store_factory(&index_path);
+        index_store.set_durability_mode(durability_mode);
         let index_type_path = index_path.join("type");
         fs::write(&index_type_path, index_store.name()).context(&index_type_path)?;
         let index_store = Arc::from(index_store);
@@ -216,6 +1313,17 @@ impl ReadonlyRepo {
         }))
     }
 
+    /// Syncs the objects, operations and index files written so far to disk.
+    /// `Transaction::commit()` does this with the `Sync` durability mode. With
+    /// the `Async` mode, the stores don't keep track of what they write, so
+    /// there's nothing to sync.
+    pub fn flush(&self) -> Result<(), FlushError> {
+        self.store.flush()?;
+        self.op_store.flush()?;
+        self.index_store.flush()?;
+        Ok(())
+    }
+
     pub fn loader(&self) -> RepoLoader {
         RepoLoader {
             repo_path: self.repo_path.clone(),
@@ -244,6 +1352,101 @@ impl ReadonlyRepo {
         &self.view
     }
 
+    /// Returns the number of operations in the history of this repo's
+    /// operation, including the operation itself.
+    pub fn operation_count(&self) -> usize {
+        operation::walk_ancestors(&self.operation).count()
+    }
+
+    /// Returns the first operation in the history of this repo's operation,
+    /// i.e. the one that initialized the repo.
+    pub fn oldest_operation(&self) -> Operation {
+        operation::walk_ancestors(&self.operation).last().unwrap()
+    }
+
+    /// Returns up to `limit` operations of this repo's operation history,
+    /// newest first, with edges to their parent operations. See
+    /// `operation::operation_graph()`.
+    pub fn operation_graph(&self, limit: usize) -> Vec<OpGraphNode> {
+        operation::operation_graph(&self.operation, limit)
+    }
+
+    /// Returns the operations in this repo's operation history whose view made
+    /// the commit reachable when their parent operations' views didn't, newest
+    /// first. There can be several if the commit was hidden and made visible
+    /// again, or if concurrent operations both added it.
+    pub fn operations_introducing(&self, id: &CommitId) -> Vec<OperationId> {
+        let index = self.index();
+        if !index.has_id(id) {
+            return vec![];
+        }
+        let mut reachable_cache: HashMap<OperationId, bool> = HashMap::new();
+        let mut is_reachable = |op: &Operation| {
+            *reachable_cache.entry(op.id().clone()).or_insert_with(|| {
+                op.view()
+                    .heads()
+                    .iter()
+                    .any(|head_id| index.is_ancestor(id, head_id))
+            })
+        };
+        let mut op_ids = vec![];
+        for op in operation::walk_ancestors(&self.operation) {
+            if is_reachable(&op) && !op.parents().iter().any(&mut is_reachable) {
+                op_ids.push(op.id().clone());
+            }
+        }
+        op_ids
+    }
+
+    /// Returns up to `limit` working-copy commits of the workspace, newest
+    /// first, together with the operation that checked each of them out.
+    /// Consecutive operations with the same working-copy commit are reported
+    /// once, by the oldest of them.
+    pub fn checkout_history(
+        &self,
+        workspace_id: &WorkspaceId,
+        limit: usize,
+    ) -> Vec<(OperationId, CommitId)> {
+        let mut history: Vec<(OperationId, CommitId)> = vec![];
+        for op in operation::walk_ancestors(&self.operation) {
+            let view = op.view();
+            let wc_commit_id = view.store_view().wc_commit_ids.get(workspace_id);
+            match (wc_commit_id, history.last_mut()) {
+                (Some(wc_commit_id), Some((last_op_id, last_commit_id)))
+                    if last_commit_id == wc_commit_id =>
+                {
+                    *last_op_id = op.id().clone();
+                }
+                (Some(wc_commit_id), _) => {
+                    if history.len() == limit {
+                        break;
+                    }
+                    history.push((op.id().clone(), wc_commit_id.clone()));
+                }
+                (None, _) => {}
+            }
+        }
+        history
+    }
+
+    /// Resolves an operation from a unique prefix of its id among all
+    /// operations reachable from the current operation heads. `@` refers to
+    /// this repo's operation. Each trailing `-` moves to the single parent of
+    /// the operation, so `@-` is the operation before this one. See
+    /// `resolve_op()`.
+    pub fn resolve_operation(&self, op_str: &str) -> Result<Operation, OpResolveError> {
+        let mut head_ids = self.op_heads_store.get_op_heads();
+        head_ids.push(self.operation.id().clone());
+        resolve_op(&self.op_store, head_ids, op_str, || {
+            Ok(self.operation.clone())
+        })
+    }
+
+    /// Returns the ids of the pinned operations. See `operation::pin()`.
+    pub fn pinned_operations(&self) -> Result<BTreeSet<OperationId>, PathError> {
+        operation::pinned_operations(&self.repo_path)
+    }
+
     pub fn readonly_index(&self) -> &dyn ReadonlyIndex {
         self.index
             .get_or_init(|| {
@@ -332,6 +1535,13 @@ impl Repo for ReadonlyRepo {
         self.change_id_index().resolve_prefix(prefix)
     }
 
+    fn change_ids_with_prefix(
+        &self,
+        prefix: &HexPrefix,
+    ) -> Result<Vec<ChangeId>, RevsetEvaluationError> {
+        Ok(self.change_id_index().change_ids_with_prefix(prefix))
+    }
+
     fn shortest_unique_change_id_prefix_len(&self, target_id: &ChangeId) -> usize {
         self.change_id_index().shortest_unique_prefix_len(target_id)
     }
@@ -407,6 +1617,8 @@ pub enum StoreLoadError {
     },
     #[error(transparent)]
     Backend(#[from] BackendLoadError),
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
 }
 
 impl StoreFactories {
@@ -575,12 +1787,18 @@ impl RepoLoader {
         repo_path: &Path,
         store_factories: &StoreFactories,
     ) -> Result<Self, StoreLoadError> {
+        let repo_settings = user_settings.with_repo(repo_path)?;
+        let durability_mode = repo_settings.durability_mode();
         let store = Store::new(store_factories.load_backend(&repo_path.join("store"))?);
-        let repo_settings = user_settings.with_repo(repo_path).unwrap();
-        let op_store = Arc::from(store_factories.load_op_store(&repo_path.join("op_store"))?);
+        store.set_durability_mode(durability_mode);
+        let op_store: Arc<dyn OpStore> =
+            Arc::from(store_factories.load_op_store(&repo_path.join("op_store"))?);
+        op_store.set_durability_mode(durability_mode);
         let op_heads_store =
             Arc::from(store_factories.load_op_heads_store(&repo_path.join("op_heads"))?);
-        let index_store = Arc::from(store_factories.load_index_store(&repo_path.join("index"))?);
+        let index_store: Arc<dyn IndexStore> =
+            Arc::from(store_factories.load_index_store(&repo_path.join("index"))?);
+        index_store.set_durability_mode(durability_mode);
         let submodule_store =
             Arc::from(store_factories.load_submodule_store(&repo_path.join("submodule_store"))?);
         Ok(Self {
@@ -693,6 +1911,8 @@ pub struct MutableRepo {
     view: DirtyCell<View>,
     rewritten_commits: HashMap<CommitId, HashSet<CommitId>>,
     abandoned_commits: HashSet<CommitId>,
+    // Unlike `abandoned_commits`, not cleared when descendants are rebased
+    all_abandoned_commits: HashSet<CommitId>,
 }
 
 impl MutableRepo {
@@ -709,6 +1929,7 @@ impl MutableRepo {
             view: DirtyCell::with_clean(mut_view),
             rewritten_commits: Default::default(),
             abandoned_commits: Default::default(),
+            all_abandoned_commits: Default::default(),
         }
     }
 
@@ -784,6 +2005,7 @@ impl MutableRepo {
     /// has been committed.
     pub fn record_abandoned_commit(&mut self, old_id: CommitId) {
         assert_ne!(old_id, *self.store().root_commit_id());
+        self.all_abandoned_commits.insert(old_id.clone());
         self.abandoned_commits.insert(old_id);
     }
 
@@ -791,6 +2013,12 @@ impl MutableRepo {
         self.abandoned_commits.clear();
     }
 
+    /// Returns all commits recorded as abandoned in this transaction, including
+    /// those whose descendants have already been rebased.
+    pub fn all_abandoned_commits(&self) -> &HashSet<CommitId> {
+        &self.all_abandoned_commits
+    }
+
     pub fn has_rewrites(&self) -> bool {
         !(self.rewritten_commits.is_empty() && self.abandoned_commits.is_empty())
     }
@@ -819,6 +2047,19 @@ impl MutableRepo {
         Ok(rebaser.rebased().len())
     }
 
+    /// Like `rebase_descendants()`, but returns statistics about the rebase.
+    pub fn rebase_all_descendants(
+        &mut self,
+        settings: &UserSettings,
+    ) -> Result<RebaseStats, TreeMergeError> {
+        if !self.has_rewrites() {
+            return Ok(RebaseStats::default());
+        }
+        let mut rebaser = self.create_descendant_rebaser(settings);
+        rebaser.rebase_all()?;
+        Ok(rebaser.stats())
+    }
+
     pub fn set_wc_commit(
         &mut self,
         workspace_id: WorkspaceId,
@@ -831,6 +2072,20 @@ impl MutableRepo {
         Ok(())
     }
 
+    /// Records `commit` as the working-copy commit of `workspace_id` and makes
+    /// sure it's visible. Unlike `edit()` and `check_out()`, the previous
+    /// working-copy commit is left alone even if it's empty, and no new
+    /// commit is created. Meant for tools that manage their own working copy.
+    pub fn set_working_copy_commit(
+        &mut self,
+        workspace_id: WorkspaceId,
+        commit: &Commit,
+    ) -> Result<(), RewriteRootCommit> {
+        self.set_wc_commit(workspace_id, commit.id().clone())?;
+        self.add_head(commit);
+        Ok(())
+    }
+
     pub fn remove_wc_commit(&mut self, workspace_id: &WorkspaceId) {
         self.view_mut().remove_wc_commit(workspace_id);
     }
@@ -841,17 +2096,367 @@ impl MutableRepo {
         settings: &UserSettings,
         commit: &Commit,
     ) -> Result<Commit, CheckOutCommitError> {
+        let parent_ids = settings.checkout_policy()?.parent_ids(commit);
         let wc_commit = self
-            .new_commit(
-                settings,
-                vec![commit.id().clone()],
-                commit.tree_id().clone(),
-            )
+            .new_commit(settings, parent_ids, commit.tree_id().clone())
             .write()?;
         self.edit(workspace_id, &wc_commit)?;
         Ok(wc_commit)
     }
 
+    /// Rewrites the working-copy commit of `workspace_id` with `tree_id`,
+    /// keeping everything else, and makes the result the new working-copy
+    /// commit. As with other rewrites, descendants need to be rebased
+    /// afterwards.
+    pub fn amend_checkout_tree(
+        &mut self,
+        workspace_id: &WorkspaceId,
+        settings: &UserSettings,
+        tree_id: TreeId,
+    ) -> Result<Commit, AmendCheckoutError> {
+        let wc_commit_id = self
+            .view()
+            .get_wc_commit_id(workspace_id)
+            .ok_or_else(|| AmendCheckoutError::NoCheckout(workspace_id.clone()))?
+            .clone();
+        let wc_commit = self.store().get_commit(&wc_commit_id)?;
+        let new_wc_commit = self
+            .rewrite_commit(settings, &wc_commit)
+            .set_tree(tree_id)
+            .write()?;
+        self.view_mut()
+            .set_wc_commit(workspace_id.clone(), new_wc_commit.id().clone());
+        Ok(new_wc_commit)
+    }
+
+    /// Creates a merge commit with the given parents. The new commit's tree is
+    /// the auto-merged tree of the parents. With more than two parents, each
+    /// parent is merged in order into the result so far, using the common
+    /// ancestors of the parents before it as the base, so the result only
+    /// depends on the order of `parents`. The tree may contain conflicts
+    /// unless `strict` is set, in which case the merge is rejected instead.
+    pub fn create_merge(
+        &mut self,
+        settings: &UserSettings,
+        parents: &[CommitId],
+        description: &str,
+        strict: bool,
+    ) -> Result<Commit, CreateMergeError> {
+        let parent_commits: Vec<_> = parents
+            .iter()
+            .map(|id| self.store().get_commit(id))
+            .try_collect()
+            .map_err(TreeMergeError::from)?;
+        let merged_tree = merge_commit_trees(self, &parent_commits)?;
+        if strict && merged_tree.has_conflict() {
+            let paths = merged_tree
+                .conflicts()
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect();
+            return Err(CreateMergeError::Conflicts(paths));
+        }
+        let merge_commit = self
+            .new_commit(settings, parents.to_vec(), merged_tree.id().clone())
+            .set_description(description)
+            .write()
+            .map_err(TreeMergeError::from)?;
+        Ok(merge_commit)
+    }
+
+    /// Creates a commit on top of `base` by applying `diff`, e.g. from an
+    /// external patch. Each entry gives a path's value before and after the
+    /// change. Paths whose value in `base` differs from the diff's "before"
+    /// value are three-way merged, so they may end up conflicted.
+    pub fn apply_diff(
+        &mut self,
+        settings: &UserSettings,
+        base: &Commit,
+        diff: &[(RepoPath, Diff<TreeValue>)],
+        description: &str,
+    ) -> Result<Commit, TreeMergeError> {
+        let store = self.store().clone();
+        let mut before_builder = store.tree_builder(base.tree_id().clone());
+        let mut after_builder = store.tree_builder(base.tree_id().clone());
+        for (path, path_diff) in diff {
+            let (before, after) = path_diff.clone().into_options();
+            for (builder, value) in [(&mut before_builder, before), (&mut after_builder, after)] {
+                match value {
+                    Some(value) => builder.set(path.clone(), value),
+                    None => builder.remove(path.clone()),
+                }
+            }
+        }
+        let before_tree = store.get_tree(&RepoPath::root(), &before_builder.write_tree())?;
+        let after_tree = store.get_tree(&RepoPath::root(), &after_builder.write_tree())?;
+        let new_tree = merge_trees(&base.tree(), &before_tree, &after_tree)?;
+        let commit = self
+            .new_commit(settings, vec![base.id().clone()], new_tree.id().clone())
+            .set_description(description)
+            .write()?;
+        Ok(commit)
+    }
+
+    /// Rewrites `commit` with `new_parent` as an additional last parent,
+    /// keeping its tree as is, and rebases its descendants onto the result.
+    /// Other rewrites pending in the transaction are left for
+    /// `rebase_descendants()`.
+    pub fn add_parent(
+        &mut self,
+        settings: &UserSettings,
+        commit: &Commit,
+        new_parent: CommitId,
+    ) -> Result<Commit, AddParentError> {
+        if commit.id() == self.store().root_commit_id() {
+            return Err(AddParentError::RootCommit);
+        }
+        if commit.parent_ids().contains(&new_parent) {
+            return Err(AddParentError::AlreadyParent(new_parent));
+        }
+        for id in [commit.id(), &new_parent] {
+            if !self.index().has_id(id) {
+                return Err(AddParentError::NotFound(id.clone()));
+            }
+        }
+        if self.index().is_ancestor(commit.id(), &new_parent) {
+            return Err(AddParentError::Cycle(new_parent));
+        }
+        let mut parent_ids = commit.parent_ids().to_vec();
+        parent_ids.push(new_parent);
+        let old_rewrites = self.rewritten_commits.get(commit.id()).cloned();
+        let new_commit = self
+            .rewrite_commit(settings, commit)
+            .set_parents(parent_ids)
+            .write()?;
+        // The rebaser clears all pending rewrites when it's done, so set the
+        // others aside while it only rebases the descendants of this commit
+        let mut pending_rewrites = std::mem::take(&mut self.rewritten_commits);
+        let pending_abandoned = std::mem::take(&mut self.abandoned_commits);
+        match old_rewrites {
+            Some(new_ids) => pending_rewrites.insert(commit.id().clone(), new_ids),
+            None => pending_rewrites.remove(commit.id()),
+        };
+        let rewritten = HashMap::from([(
+            commit.id().clone(),
+            HashSet::from([new_commit.id().clone()]),
+        )]);
+        let result = DescendantRebaser::new(settings, self, rewritten, HashSet::new()).rebase_all();
+        self.rewritten_commits = pending_rewrites;
+        self.abandoned_commits = pending_abandoned;
+        result?;
+        Ok(new_commit)
+    }
+
+    /// Collapses a linear run of commits into a single commit, ordered from
+    /// the base to the tip, so each commit must be the only parent of the next.
+    /// The new commit has the tip's tree and the base's parents, and replaces
+    /// all commits in the run. Descendants of the run are rebased onto it.
+    pub fn squash_range(
+        &mut self,
+        settings: &UserSettings,
+        commits: &[CommitId],
+        description: &str,
+    ) -> Result<Commit, SquashError> {
+        let base_id = commits.first().ok_or(SquashError::EmptyRange)?;
+        if commits.contains(self.store().root_commit_id()) {
+            return Err(SquashError::RootCommit);
+        }
+        let mut run = vec![self.store().get_commit(base_id)?];
+        for (parent_id, id) in commits.iter().tuple_windows() {
+            let commit = self.store().get_commit(id)?;
+            if commit.parent_ids() != [parent_id.clone()] {
+                return Err(SquashError::NotLinear(id.clone()));
+            }
+            run.push(commit);
+        }
+        let tip = run.last().unwrap();
+        let new_commit = self
+            .rewrite_commit(settings, tip)
+            .set_parents(run[0].parent_ids().to_vec())
+            .set_predecessors(commits.to_vec())
+            .set_description(description)
+            .write()?;
+        for commit in &run {
+            self.record_rewritten_commit(commit.id().clone(), new_commit.id().clone());
+        }
+        self.rebase_descendants(settings)?;
+        Ok(new_commit)
+    }
+
+    /// Applies an interactive rebase plan. The whole plan is validated before
+    /// any commit is rewritten: each commit may appear once, a picked commit's
+    /// parents must be picked earlier in the plan or be unaffected by it, and
+    /// a squash must follow a pick or another squash. Descendants of the
+    /// planned commits that aren't part of the plan are rebased afterwards.
+    pub fn apply_rebase_plan(
+        &mut self,
+        settings: &UserSettings,
+        plan: &RebasePlan,
+    ) -> Result<RebaseResult, RebasePlanError> {
+        let root_commit_id = self.store().root_commit_id().clone();
+        let mut planned: HashMap<&CommitId, &RebaseAction> = HashMap::new();
+        let mut commits = HashMap::new();
+        for action in plan.actions() {
+            let id = action.commit_id();
+            if *id == root_commit_id {
+                return Err(RebasePlanError::RootCommit);
+            }
+            if planned.insert(id, action).is_some() {
+                return Err(RebasePlanError::DuplicateCommit(id.clone()));
+            }
+            commits.insert(id.clone(), self.store().get_commit(id)?);
+        }
+        let mut seen = HashSet::new();
+        let mut can_squash = false;
+        for action in plan.actions() {
+            match action {
+                RebaseAction::Pick { commit, parents } => {
+                    if parents.is_empty() {
+                        return Err(RebasePlanError::NoParents(commit.clone()));
+                    }
+                    for parent in parents {
+                        match planned.get(parent) {
+                            Some(RebaseAction::Drop(_)) => {
+                                return Err(RebasePlanError::DroppedParent {
+                                    commit: commit.clone(),
+                                    parent: parent.clone(),
+                                });
+                            }
+                            Some(_) if !seen.contains(parent) => {
+                                return Err(RebasePlanError::ParentNotYetPicked {
+                                    commit: commit.clone(),
+                                    parent: parent.clone(),
+                                });
+                            }
+                            Some(_) => {}
+                            None => {
+                                self.store().get_commit(parent)?;
+                                if planned
+                                    .keys()
+                                    .any(|id| self.index().is_ancestor(id, parent))
+                                {
+                                    return Err(RebasePlanError::ParentRewritten {
+                                        commit: commit.clone(),
+                                        parent: parent.clone(),
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    can_squash = true;
+                }
+                RebaseAction::Drop(_) => {
+                    can_squash = false;
+                }
+                RebaseAction::Squash(commit) => {
+                    if !can_squash {
+                        return Err(RebasePlanError::NothingToSquashInto(commit.clone()));
+                    }
+                }
+            }
+            seen.insert(action.commit_id());
+        }
+
+        let mut result = RebaseResult::default();
+        let mut previous: Option<Commit> = None;
+        for action in plan.actions() {
+            match action {
+                RebaseAction::Pick { commit, parents } => {
+                    let old_commit = &commits[commit];
+                    let new_parent_ids = parents
+                        .iter()
+                        .map(|id| result.rewritten.get(id).unwrap_or(id).clone())
+                        .collect_vec();
+                    let new_commit = if *old_commit.parent_ids() == new_parent_ids {
+                        old_commit.clone()
+                    } else {
+                        let new_parents: Vec<_> = new_parent_ids
+                            .iter()
+                            .map(|id| self.store().get_commit(id))
+                            .try_collect()?;
+                        rebase_commit(settings, self, old_commit, &new_parents)?
+                    };
+                    result
+                        .rewritten
+                        .insert(commit.clone(), new_commit.id().clone());
+                    previous = Some(new_commit);
+                }
+                RebaseAction::Drop(commit) => {
+                    self.record_abandoned_commit(commit.clone());
+                    result.abandoned.push(commit.clone());
+                    previous = None;
+                }
+                RebaseAction::Squash(commit) => {
+                    let target = previous.take().unwrap();
+                    let squashed = &commits[commit];
+                    let base_tree = merge_commit_trees(self, &squashed.parents())?;
+                    let new_tree = merge_trees(&target.tree(), &base_tree, &squashed.tree())?;
+                    let description = match (target.description(), squashed.description()) {
+                        (target_description, "") => target_description.to_string(),
+                        ("", squashed_description) => squashed_description.to_string(),
+                        (target_description, squashed_description) => {
+                            format!("{target_description}\n{squashed_description}")
+                        }
+                    };
+                    let mut predecessors = target.predecessor_ids().to_vec();
+                    predecessors.push(commit.clone());
+                    let new_target = self
+                        .rewrite_commit(settings, &target)
+                        .set_tree(new_tree.id().clone())
+                        .set_description(description)
+                        .set_predecessors(predecessors)
+                        .write()?;
+                    // Point rewrites recorded by the pick at the new target directly, so the
+                    // target's old id doesn't look like a second successor.
+                    for new_ids in self.rewritten_commits.values_mut() {
+                        if new_ids.remove(target.id()) {
+                            new_ids.insert(new_target.id().clone());
+                        }
+                    }
+                    self.record_rewritten_commit(commit.clone(), new_target.id().clone());
+                    for new_id in result.rewritten.values_mut() {
+                        if new_id == target.id() {
+                            *new_id = new_target.id().clone();
+                        }
+                    }
+                    result
+                        .rewritten
+                        .insert(commit.clone(), new_target.id().clone());
+                    previous = Some(new_target);
+                }
+            }
+        }
+        result.num_rebased_descendants = self.rebase_descendants(settings)?;
+        Ok(result)
+    }
+
+    /// Resolves a divergent change by keeping one of its visible commits. The
+    /// other commits are recorded as rewritten into `keep`, so they're hidden
+    /// and their descendants are rebased onto `keep`.
+    pub fn reconcile_divergence(
+        &mut self,
+        settings: &UserSettings,
+        change_id: &ChangeId,
+        keep: &CommitId,
+    ) -> Result<(), DivergenceError> {
+        let commit_ids = self.resolve_change_id(change_id).unwrap_or_default();
+        if commit_ids.len() < 2 {
+            return Err(DivergenceError::NotDivergent(change_id.clone()));
+        }
+        if !commit_ids.contains(keep) {
+            return Err(DivergenceError::NotInChange(keep.clone()));
+        }
+        let others = commit_ids.into_iter().filter(|id| id != keep).collect_vec();
+        if let Some(id) = others.iter().find(|id| self.index().is_ancestor(id, keep)) {
+            return Err(DivergenceError::AncestorOfKept(id.clone()));
+        }
+        for id in others {
+            self.record_rewritten_commit(id, keep.clone());
+        }
+        self.rebase_descendants(settings)?;
+        Ok(())
+    }
+
     pub fn edit(
         &mut self,
         workspace_id: WorkspaceId,
@@ -944,6 +2549,102 @@ impl MutableRepo {
         self.view.mark_dirty();
     }
 
+    /// Hides the head commit by replacing it by its parents in the set of
+    /// heads. Unlike abandoning, nothing is rebased, so only heads that no
+    /// workspace has checked out can be pruned.
+    pub fn prune_commit(&mut self, commit: &Commit) -> Result<(), PruneCommitError> {
+        if !self.view().heads().contains(commit.id()) {
+            return Err(PruneCommitError::NotAHead(commit.id().clone()));
+        }
+        if let Some(workspace_id) =
+            self.view()
+                .wc_commit_ids()
+                .iter()
+                .find_map(|(workspace_id, wc_commit_id)| {
+                    (wc_commit_id == commit.id()).then(|| workspace_id.clone())
+                })
+        {
+            return Err(PruneCommitError::CheckedOut(
+                commit.id().clone(),
+                workspace_id,
+            ));
+        }
+        let parents = commit
+            .parent_ids()
+            .iter()
+            .map(|parent_id| self.store().get_commit(parent_id))
+            .collect::<BackendResult<Vec<_>>>()?;
+        self.remove_head(commit.id());
+        for parent in &parents {
+            self.add_head(parent);
+        }
+        Ok(())
+    }
+
+    /// Makes a hidden commit, such as an abandoned one, visible again by
+    /// adding it back as a head. Parents that have been hidden since are
+    /// replaced by their successors, in which case the commit is rebased onto
+    /// them and the rebased commit is returned.
+    pub fn unabandon_commit(
+        &mut self,
+        settings: &UserSettings,
+        commit: &Commit,
+    ) -> Result<Commit, TreeMergeError> {
+        // The commit may have been abandoned earlier in this transaction
+        self.abandoned_commits.remove(commit.id());
+        self.all_abandoned_commits.remove(commit.id());
+        let new_parent_ids = commit
+            .parents()
+            .iter()
+            .flat_map(|parent| self.visible_successors(parent))
+            .unique()
+            .collect_vec();
+        if new_parent_ids == commit.parent_ids() {
+            self.add_head(commit);
+            return Ok(commit.clone());
+        }
+        let new_parents: Vec<_> = new_parent_ids
+            .iter()
+            .map(|id| self.store().get_commit(id))
+            .try_collect()?;
+        let old_rewrites = self.rewritten_commits.get(commit.id()).cloned();
+        let new_commit = rebase_commit(settings, self, commit, &new_parents)?;
+        // The old commit is hidden, so it has no visible descendants to rebase.
+        // Recording it as rewritten would make `rebase_descendants()` add its
+        // parents back as heads. Any earlier record is kept.
+        match old_rewrites {
+            Some(new_ids) => self.rewritten_commits.insert(commit.id().clone(), new_ids),
+            None => self.rewritten_commits.remove(commit.id()),
+        };
+        Ok(new_commit)
+    }
+
+    /// Returns the commit itself if it's visible, otherwise the visible commit
+    /// with the same change id if there's exactly one, otherwise the visible
+    /// successors of its parents.
+    fn visible_successors(&self, commit: &Commit) -> Vec<CommitId> {
+        let index = self.index();
+        let is_visible = index.has_id(commit.id())
+            && self
+                .view()
+                .heads()
+                .iter()
+                .any(|head_id| index.is_ancestor(commit.id(), head_id));
+        if is_visible {
+            return vec![commit.id().clone()];
+        }
+        if let Some(ids) = self.resolve_change_id(commit.change_id()) {
+            if let [id] = ids.as_slice() {
+                return vec![id.clone()];
+            }
+        }
+        commit
+            .parents()
+            .iter()
+            .flat_map(|parent| self.visible_successors(parent))
+            .collect()
+    }
+
     pub fn add_public_head(&mut self, head: &Commit) {
         self.view_mut().add_public_head(head.id());
         self.view.mark_dirty();
@@ -978,6 +2679,68 @@ impl MutableRepo {
         self.view_mut().remove_local_branch(name);
     }
 
+    /// Sets or removes (if the target is `None`) several local branches at
+    /// once. Each update is merged against the branch's current target in this
+    /// transaction, so changes made earlier in the transaction are built upon
+    /// rather than reported as conflicts.
+    pub fn set_branches(&mut self, updates: Vec<(String, Option<RefTarget>)>) {
+        let view = self.view.get_mut();
+        for (name, new_target) in updates {
+            let base_target = view.get_local_branch(&name);
+            view.merge_single_ref(
+                self.index.as_index(),
+                &RefName::LocalBranch(name),
+                base_target.as_ref(),
+                new_target.as_ref(),
+            );
+        }
+    }
+
+    /// Sets the local branch `name` back to where it pointed in `from_op`.
+    pub fn restore_branch(&mut self, name: &str, from_op: &Operation) -> Result<(), BranchError> {
+        let target = from_op
+            .view()
+            .store_view()
+            .branches
+            .get(name)
+            .and_then(|branch_target| branch_target.local_target.clone())
+            .ok_or_else(|| BranchError::NotFoundInOperation {
+                name: name.to_owned(),
+                operation_id: from_op.id().clone(),
+            })?;
+        self.set_local_branch(name.to_owned(), target);
+        Ok(())
+    }
+
+    /// Resolves the conflicted local branch `name` by pointing it to `target`.
+    /// Unless `force` is set, `target` must be one of the commits the branch
+    /// was conflicted between.
+    pub fn resolve_branch_conflict(
+        &mut self,
+        name: &str,
+        target: CommitId,
+        force: bool,
+    ) -> Result<(), BranchError> {
+        let current_target = self
+            .get_local_branch(name)
+            .ok_or_else(|| BranchError::NotFound {
+                name: name.to_owned(),
+            })?;
+        if !current_target.is_conflict() {
+            return Err(BranchError::NotConflicted {
+                name: name.to_owned(),
+            });
+        }
+        if !force && !current_target.adds().contains(&target) {
+            return Err(BranchError::NotAConflictSide {
+                name: name.to_owned(),
+                commit_id: target,
+            });
+        }
+        self.set_local_branch(name.to_owned(), RefTarget::Normal(target));
+        Ok(())
+    }
+
     pub fn get_remote_branch(&self, name: &str, remote_name: &str) -> Option<RefTarget> {
         self.view
             .with_ref(|v| v.get_remote_branch(name, remote_name))
@@ -1049,6 +2812,44 @@ impl MutableRepo {
         self.view.mark_dirty();
     }
 
+    /// Merges the changes from `base` to `other` into this repo, like
+    /// `merge()` but for views that don't come from this repo's operation
+    /// log. The commits the views refer to must be readable from this repo's
+    /// store; any that aren't in the index yet are added to it.
+    pub fn merge_foreign_view(
+        &mut self,
+        base: &op_store::View,
+        other: &op_store::View,
+    ) -> BackendResult<()> {
+        let mut referenced_ids = referenced_commit_ids(base);
+        referenced_ids.extend(referenced_commit_ids(other));
+        let mut missing_commits = vec![];
+        for commit_id in referenced_ids.into_iter().unique() {
+            if !self.index().has_id(&commit_id) {
+                missing_commits.push(self.store().get_commit(&commit_id)?);
+            }
+        }
+        let missing_commits = dag_walk::topo_order_forward(
+            missing_commits,
+            |commit: &Commit| commit.id().clone(),
+            |commit: &Commit| -> Vec<Commit> {
+                commit
+                    .parents()
+                    .into_iter()
+                    .filter(|parent| !self.index().has_id(parent.id()))
+                    .collect()
+            },
+        );
+        for missing_commit in &missing_commits {
+            self.index.add_commit(missing_commit);
+        }
+
+        self.view.ensure_clean(|v| self.enforce_view_invariants(v));
+        self.merge_view(&View::new(base.clone()), &View::new(other.clone()));
+        self.view.mark_dirty();
+        Ok(())
+    }
+
     fn merge_view(&mut self, base: &View, other: &View) {
         // Merge working-copy commits. If there's a conflict, we keep the self side.
         for (workspace_id, base_wc_commit) in base.wc_commit_ids() {
@@ -1284,15 +3085,266 @@ pub enum EditCommitError {
     RewriteRootCommit,
 }
 
+/// Error from `MutableRepo::amend_checkout_tree()`
+#[derive(Debug, Error)]
+pub enum AmendCheckoutError {
+    #[error("Workspace {} has no working-copy commit", .0.as_str())]
+    NoCheckout(WorkspaceId),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Error from `MutableRepo::add_parent()`
+#[derive(Debug, Error)]
+pub enum AddParentError {
+    #[error("Cannot rewrite the root commit")]
+    RootCommit,
+    #[error("Commit {} is already a parent", .0.hex())]
+    AlreadyParent(CommitId),
+    #[error("Commit {} is not in the repo", .0.hex())]
+    NotFound(CommitId),
+    #[error("Commit {} is a descendant of the commit, so it can't become its parent", .0.hex())]
+    Cycle(CommitId),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Error from `MutableRepo::prune_commit()`
+#[derive(Debug, Error)]
+pub enum PruneCommitError {
+    #[error("Commit {} is not a head", .0.hex())]
+    NotAHead(CommitId),
+    #[error("Commit {} is checked out in workspace {}", .0.hex(), .1.as_str())]
+    CheckedOut(CommitId, WorkspaceId),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Error from `MutableRepo::apply_rebase_plan()`
+#[derive(Debug, Error)]
+pub enum RebasePlanError {
+    #[error("Cannot rewrite the root commit")]
+    RootCommit,
+    #[error("Commit {} appears more than once in the plan", .0.hex())]
+    DuplicateCommit(CommitId),
+    #[error("Commit {} must have at least one parent", .0.hex())]
+    NoParents(CommitId),
+    #[error(
+        "Commit {} can't be rebased onto {}, which is dropped by the plan",
+        .commit.hex(),
+        .parent.hex()
+    )]
+    DroppedParent { commit: CommitId, parent: CommitId },
+    #[error(
+        "Commit {} can't be rebased onto {}, which appears later in the plan",
+        .commit.hex(),
+        .parent.hex()
+    )]
+    ParentNotYetPicked { commit: CommitId, parent: CommitId },
+    #[error(
+        "Commit {} can't be rebased onto {}, which is a descendant of a commit in the plan",
+        .commit.hex(),
+        .parent.hex()
+    )]
+    ParentRewritten { commit: CommitId, parent: CommitId },
+    #[error("Commit {} has no preceding commit to be squashed into", .0.hex())]
+    NothingToSquashInto(CommitId),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Error from `MutableRepo::squash_range()`
+#[derive(Debug, Error)]
+pub enum SquashError {
+    #[error("No commits to squash")]
+    EmptyRange,
+    #[error("Cannot rewrite the root commit")]
+    RootCommit,
+    #[error(
+        "Commit {} doesn't have the previous commit in the range as its only parent",
+        .0.hex()
+    )]
+    NotLinear(CommitId),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Error from `MutableRepo::create_merge()`
+#[derive(Debug, Error)]
+pub enum CreateMergeError {
+    #[error(
+        "Merge would leave conflicts in {}",
+        .0.iter().map(|path| path.to_internal_file_string()).join(", ")
+    )]
+    Conflicts(Vec<RepoPath>),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Error from `MutableRepo::reconcile_divergence()`
+#[derive(Debug, Error)]
+pub enum DivergenceError {
+    #[error("Change {} is not divergent", .0.hex())]
+    NotDivergent(ChangeId),
+    #[error("Commit {} is not one of the divergent commits", .0.hex())]
+    NotInChange(CommitId),
+    #[error(
+        "Commit {} is an ancestor of the commit to keep, so it can't be replaced by it",
+        .0.hex()
+    )]
+    AncestorOfKept(CommitId),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Error from `ReadonlyRepo::resolve_operation()` and `resolve_op()`
+#[derive(Debug, Error)]
+pub enum OpResolveError {
+    #[error(r#"Operation ID "{0}" is not a valid hexadecimal prefix"#)]
+    InvalidPrefix(String),
+    #[error(r#"No operation ID matching "{0}""#)]
+    NotFound(String),
+    #[error(
+        r#"Operation ID prefix "{prefix}" is ambiguous: {}"#,
+        .candidates.iter().map(|id| id.hex()).join(", ")
+    )]
+    Ambiguous {
+        prefix: String,
+        candidates: Vec<OperationId>,
+    },
+    #[error(r#"The "{0}" expression resolved to no operations"#)]
+    NoParent(String),
+    #[error(r#"The "{0}" expression resolved to more than one operation"#)]
+    MultipleParents(String),
+    #[error(transparent)]
+    OpStore(#[from] OpStoreError),
+}
+
+/// Resolves `op_str` to an operation. `@` is the operation returned by
+/// `current_op`, which is only called if needed, and anything else is a unique
+/// prefix of the id of an operation reachable from `head_ids`. Each trailing
+/// `-` moves to the single parent of the operation.
+pub fn resolve_op<E: From<OpResolveError>>(
+    op_store: &Arc<dyn OpStore>,
+    head_ids: Vec<OperationId>,
+    op_str: &str,
+    current_op: impl FnOnce() -> Result<Operation, E>,
+) -> Result<Operation, E> {
+    let op_symbol = op_str.trim_end_matches('-');
+    let op_postfix = &op_str[op_symbol.len()..];
+    let mut operation = if op_symbol == "@" {
+        current_op()?
+    } else {
+        resolve_op_id_prefix(op_store, head_ids, op_symbol)?
+    };
+    for _ in op_postfix.chars() {
+        operation = match operation.parents().as_slice() {
+            [op] => op.clone(),
+            [] => return Err(OpResolveError::NoParent(op_str.to_owned()).into()),
+            [_, _, ..] => return Err(OpResolveError::MultipleParents(op_str.to_owned()).into()),
+        };
+    }
+    Ok(operation)
+}
+
+fn resolve_op_id_prefix(
+    op_store: &Arc<dyn OpStore>,
+    head_ids: Vec<OperationId>,
+    prefix: &str,
+) -> Result<Operation, OpResolveError> {
+    if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(OpResolveError::InvalidPrefix(prefix.to_owned()));
+    }
+    // A full-length id can be read directly without walking the operation log
+    if let Ok(binary_op_id) = hex::decode(prefix) {
+        let op_id = OperationId::new(binary_op_id);
+        match op_store.read_operation(&op_id) {
+            Ok(store_operation) => {
+                return Ok(Operation::new(op_store.clone(), op_id, store_operation));
+            }
+            Err(OpStoreError::NotFound) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let mut visited = HashSet::new();
+    let mut work: VecDeque<_> = head_ids.into();
+    let mut matches = vec![];
+    while let Some(op_id) = work.pop_front() {
+        if visited.insert(op_id.clone()) {
+            let store_operation = op_store.read_operation(&op_id)?;
+            work.extend(store_operation.parents.iter().cloned());
+            if op_id.hex().starts_with(prefix) {
+                matches.push(Operation::new(op_store.clone(), op_id, store_operation));
+            }
+        }
+    }
+    match matches.len() {
+        0 => Err(OpResolveError::NotFound(prefix.to_owned())),
+        1 => Ok(matches.pop().unwrap()),
+        _ => Err(OpResolveError::Ambiguous {
+            prefix: prefix.to_owned(),
+            candidates: matches.iter().map(|op| op.id().clone()).sorted().collect(),
+        }),
+    }
+}
+
 /// Error from attempts to check out a commit
 #[derive(Debug, Error)]
 pub enum CheckOutCommitError {
+    #[error("Invalid checkout policy: {0}")]
+    Config(#[from] config::ConfigError),
     #[error("Failed to create new working-copy commit: {0}")]
     CreateCommit(#[from] BackendError),
     #[error("Failed to edit commit: {0}")]
     EditCommit(#[from] EditCommitError),
 }
 
+/// Error from `Repo::check_commit_arg()`
+#[derive(Debug, Error)]
+pub enum CommitArgError {
+    #[error("Commit {} doesn't exist", .0.hex())]
+    NotFound(CommitId),
+    #[error("Commit {} is not visible", .0.hex())]
+    NotVisible(CommitId),
+    #[error(transparent)]
+    Backend(BackendError),
+}
+
+#[derive(Debug, Error)]
+pub enum FlushError {
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    OpStore(#[from] OpStoreError),
+    #[error(transparent)]
+    IndexStore(#[from] IndexWriteError),
+    #[error("Failed to sync the operation heads: {0}")]
+    OpHeadsStore(#[source] io::Error),
+}
+
+/// Error from attempts to restore a branch from another operation or to resolve
+/// a conflicted branch
+#[derive(Debug, Error)]
+pub enum BranchError {
+    #[error("Branch {name} doesn't exist in operation {}", operation_id.hex())]
+    NotFoundInOperation {
+        name: String,
+        operation_id: OperationId,
+    },
+    #[error("Branch {name} doesn't exist")]
+    NotFound { name: String },
+    #[error("Branch {name} is not conflicted")]
+    NotConflicted { name: String },
+    #[error("Commit {} is not one of the sides of branch {name}'s conflict", commit_id.hex())]
+    NotAConflictSide { name: String, commit_id: CommitId },
+}
+
 mod dirty_cell {
     use std::cell::{Cell, RefCell};
 
@@ -34,7 +34,7 @@ use thiserror::Error;
 use crate::backend::{BackendError, BackendResult, ChangeId, CommitId, ObjectId};
 use crate::commit::Commit;
 use crate::git::{self, get_local_git_tracking_branch};
-use crate::hex_util::to_forward_hex;
+use crate::hex_util::{to_forward_hex, to_reverse_hex};
 use crate::index::{HexPrefix, PrefixResolution};
 use crate::op_store::WorkspaceId;
 use crate::repo::Repo;
@@ -53,8 +53,14 @@ pub enum RevsetResolutionError {
     EmptyString,
     #[error("Commit ID prefix \"{0}\" is ambiguous")]
     AmbiguousCommitIdPrefix(String),
-    #[error("Change ID prefix \"{0}\" is ambiguous")]
-    AmbiguousChangeIdPrefix(String),
+    #[error("Change ID prefix \"{prefix}\" is ambiguous")]
+    AmbiguousChangeIdPrefix {
+        prefix: String,
+        /// The first few matching change ids.
+        candidates: Vec<String>,
+        /// The number of matching change ids not included in `candidates`.
+        num_omitted: usize,
+    },
     #[error("Unexpected error from store: {0}")]
     StoreError(#[source] BackendError),
 }
@@ -1684,6 +1690,34 @@ fn collect_branch_symbols(repo: &dyn Repo, include_synced_remotes: bool) -> Vec<
         .collect()
 }
 
+const MAX_CHANGE_ID_CANDIDATES: usize = 10;
+
+/// Lists up to `MAX_CHANGE_ID_CANDIDATES` visible change ids matching
+/// `prefix`, in the reverse-hex form users type them in, along with the number
+/// of matches left out.
+fn collect_change_id_candidates(
+    repo: &dyn Repo,
+    prefix: &HexPrefix,
+) -> Result<(Vec<String>, usize), RevsetResolutionError> {
+    let change_ids = repo
+        .change_ids_with_prefix(prefix)
+        .map_err(|err| match err {
+            RevsetEvaluationError::StoreError(err) => RevsetResolutionError::StoreError(err),
+            RevsetEvaluationError::Other(message) => {
+                RevsetResolutionError::StoreError(BackendError::Other(message.into()))
+            }
+        })?;
+    // Reversing the hex digits reverses the order
+    let candidates = change_ids
+        .iter()
+        .rev()
+        .take(MAX_CHANGE_ID_CANDIDATES)
+        .map(|change_id| to_reverse_hex(&change_id.hex()).unwrap())
+        .collect_vec();
+    let num_omitted = change_ids.len() - candidates.len();
+    Ok((candidates, num_omitted))
+}
+
 fn resolve_full_commit_id(
     repo: &dyn Repo,
     symbol: &str,
@@ -1830,9 +1864,13 @@ impl SymbolResolver for DefaultSymbolResolver<'_> {
             if let Some(prefix) = to_forward_hex(symbol).as_deref().and_then(HexPrefix::new) {
                 match (self.change_id_resolver)(self.repo, &prefix) {
                     PrefixResolution::AmbiguousMatch => {
-                        return Err(RevsetResolutionError::AmbiguousChangeIdPrefix(
-                            symbol.to_owned(),
-                        ));
+                        let (candidates, num_omitted) =
+                            collect_change_id_candidates(self.repo, &prefix)?;
+                        return Err(RevsetResolutionError::AmbiguousChangeIdPrefix {
+                            prefix: symbol.to_owned(),
+                            candidates,
+                            num_omitted,
+                        });
                     }
                     PrefixResolution::SingleMatch(ids) => {
                         return Ok(ids);
@@ -1934,7 +1972,7 @@ fn resolve_symbols(
                         }
                         RevsetResolutionError::EmptyString
                         | RevsetResolutionError::AmbiguousCommitIdPrefix(_)
-                        | RevsetResolutionError::AmbiguousChangeIdPrefix(_)
+                        | RevsetResolutionError::AmbiguousChangeIdPrefix { .. }
                         | RevsetResolutionError::StoreError(_) => Err(err),
                     })
                     .map(Some) // Always rewrite subtree
@@ -2145,6 +2183,14 @@ pub trait ChangeIdIndex: Send + Sync {
     /// Resolve an unambiguous change ID prefix to the commit IDs in the revset.
     fn resolve_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<Vec<CommitId>>;
 
+    /// Returns the distinct change IDs in the revset matching `prefix`, in
+    /// ascending order. They're only used to list candidates for an ambiguous
+    /// prefix, so the default implementation, for indexes that can't
+    /// enumerate their keys, returns none.
+    fn change_ids_with_prefix(&self, _prefix: &HexPrefix) -> Vec<ChangeId> {
+        vec![]
+    }
+
     /// This function returns the shortest length of a prefix of `key` that
     /// disambiguates it from every other key in the index.
     ///
@@ -20,6 +20,7 @@ use std::sync::Arc;
 use itertools::{process_results, Itertools};
 
 use crate::backend::{BackendError, CommitId, ObjectId};
+use crate::cancellation::CancellationToken;
 use crate::commit::Commit;
 use crate::dag_walk;
 use crate::index::Index;
@@ -29,7 +30,7 @@ use crate::repo_path::RepoPath;
 use crate::revset::{RevsetExpression, RevsetIteratorExt};
 use crate::settings::UserSettings;
 use crate::store::Store;
-use crate::tree::{merge_trees, Tree, TreeMergeError};
+use crate::tree::{merge_trees, merge_trees_with_options, Tree, TreeMergeError};
 use crate::view::RefName;
 
 pub fn merge_commit_trees(repo: &dyn Repo, commits: &[Commit]) -> Result<Tree, TreeMergeError> {
@@ -83,7 +84,12 @@ pub fn rebase_commit(
     } else {
         let old_base_tree = merge_commit_trees(mut_repo, &old_parents)?;
         let new_base_tree = merge_commit_trees(mut_repo, new_parents)?;
-        let merged_tree = merge_trees(&new_base_tree, &old_base_tree, &old_commit.tree())?;
+        let merged_tree = merge_trees_with_options(
+            &new_base_tree,
+            &old_base_tree,
+            &old_commit.tree(),
+            &settings.merge_options()?,
+        )?;
         merged_tree.id().clone()
     };
     let new_parent_ids = new_parents
@@ -250,6 +256,14 @@ impl<'settings, 'repo> DescendantRebaser<'settings, 'repo> {
         }
     }
 
+    /// Returns statistics about the rebase so far.
+    pub fn stats(&self) -> RebaseStats {
+        RebaseStats {
+            num_rebased: self.rebased.len(),
+            num_skipped: self.divergent.len(),
+        }
+    }
+
     /// Returns a map from `CommitId` of old commit to new commit. Includes the
     /// commits rebase so far. Does not include the inputs passed to
     /// `rebase_descendants`.
@@ -427,6 +441,11 @@ impl<'settings, 'repo> DescendantRebaser<'settings, 'repo> {
         // TODO: As the TODO above says, we should probably change the API. Even if we
         // don't, we should at least make this code not do any work if you call
         // rebase_next() after we've returned None.
+        self.update_heads();
+        Ok(None)
+    }
+
+    fn update_heads(&mut self) {
         let mut view = self.mut_repo.view().store_view().clone();
         for commit_id in &self.heads_to_remove {
             view.head_ids.remove(commit_id);
@@ -439,13 +458,113 @@ impl<'settings, 'repo> DescendantRebaser<'settings, 'repo> {
         self.mut_repo.set_view(view);
         self.mut_repo.clear_rewritten_commits();
         self.mut_repo.clear_abandoned_commits();
-        Ok(None)
     }
 
     pub fn rebase_all(&mut self) -> Result<(), TreeMergeError> {
         while self.rebase_next()?.is_some() {}
         Ok(())
     }
+
+    /// Like `rebase_all()`, but stops before rebasing the next commit once
+    /// `cancel` has been cancelled. The commits rebased so far are kept, and
+    /// the remaining descendants are left on their old parents, so the repo
+    /// is still consistent. Returns false if the rebase was cancelled.
+    pub fn rebase_all_cancellable(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<bool, TreeMergeError> {
+        loop {
+            if cancel.is_cancelled() {
+                self.to_visit.clear();
+                self.update_heads();
+                return Ok(false);
+            }
+            if self.rebase_next()?.is_none() {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// Summary of what `MutableRepo::rebase_all_descendants()` did.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RebaseStats {
+    /// Number of descendants that were rebased.
+    pub num_rebased: usize,
+    /// Number of divergent rewritten commits whose descendants were left in
+    /// place because there's no single commit to rebase them onto.
+    pub num_skipped: usize,
+}
+
+/// A step of a `RebasePlan`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RebaseAction {
+    /// Rebases the commit onto `parents`. Parents that appear earlier in the
+    /// plan refer to their rewritten versions.
+    Pick {
+        commit: CommitId,
+        parents: Vec<CommitId>,
+    },
+    /// Abandons the commit. Its descendants outside the plan are rebased onto
+    /// its parents.
+    Drop(CommitId),
+    /// Folds the commit's changes into the commit produced by the previous
+    /// `Pick` or `Squash` action.
+    Squash(CommitId),
+}
+
+impl RebaseAction {
+    /// The commit the action applies to.
+    pub fn commit_id(&self) -> &CommitId {
+        match self {
+            RebaseAction::Pick { commit, .. }
+            | RebaseAction::Drop(commit)
+            | RebaseAction::Squash(commit) => commit,
+        }
+    }
+}
+
+/// A list of actions for an interactive rebase, applied in order by
+/// `MutableRepo::apply_rebase_plan()`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RebasePlan {
+    actions: Vec<RebaseAction>,
+}
+
+impl RebasePlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pick(mut self, commit: CommitId, parents: Vec<CommitId>) -> Self {
+        self.actions.push(RebaseAction::Pick { commit, parents });
+        self
+    }
+
+    pub fn drop_commit(mut self, commit: CommitId) -> Self {
+        self.actions.push(RebaseAction::Drop(commit));
+        self
+    }
+
+    pub fn squash(mut self, commit: CommitId) -> Self {
+        self.actions.push(RebaseAction::Squash(commit));
+        self
+    }
+
+    pub fn actions(&self) -> &[RebaseAction] {
+        &self.actions
+    }
+}
+
+/// Summary of what `MutableRepo::apply_rebase_plan()` did.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RebaseResult {
+    /// Maps each picked or squashed commit to the commit that replaced it.
+    pub rewritten: HashMap<CommitId, CommitId>,
+    /// The dropped commits.
+    pub abandoned: Vec<CommitId>,
+    /// Number of descendants outside the plan that were rebased.
+    pub num_rebased_descendants: usize,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -14,15 +14,23 @@
 
 #![allow(missing_docs)]
 
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 use chrono::DateTime;
+use itertools::Itertools;
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
 
-use crate::backend::{ChangeId, ObjectId, Signature, Timestamp};
+use crate::backend::{BackendResult, ChangeId, CommitId, ObjectId, Signature, Timestamp};
+use crate::commit::Commit;
+use crate::files::{MergeOptions, WhitespaceHandling};
 use crate::fsmonitor::FsmonitorKind;
+use crate::revset::RevsetGraphEdge;
+use crate::store::Store;
 
 #[derive(Debug, Clone)]
 pub struct UserSettings {
@@ -34,6 +42,7 @@ pub struct UserSettings {
 #[derive(Debug, Clone)]
 pub struct RepoSettings {
     _config: config::Config,
+    durability_mode: DurabilityMode,
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +66,184 @@ impl Default for GitSettings {
     }
 }
 
+/// The order in which commits are listed by `Repo::log_order()` and by
+/// `jj log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOrder {
+    /// Children before parents, newest indexed commits first.
+    Topological,
+    /// Newest committer timestamp first, falling back to topological order
+    /// for commits with the same timestamp.
+    CommitterTimestamp,
+}
+
+impl LogOrder {
+    /// Sorts commits that are in topological order (children first) into this
+    /// order.
+    pub fn sort_commits(&self, commits: &mut [Commit]) {
+        match self {
+            LogOrder::Topological => {}
+            LogOrder::CommitterTimestamp => {
+                // The sort is stable, so commits with the same timestamp stay in
+                // topological order.
+                commits.sort_by(|commit1, commit2| {
+                    let timestamp1 = &commit1.committer().timestamp.timestamp;
+                    let timestamp2 = &commit2.committer().timestamp.timestamp;
+                    timestamp2.cmp(timestamp1)
+                });
+            }
+        }
+    }
+
+    /// Sorts graph nodes that are in topological order (children first) into
+    /// this order as far as the graph allows. A node is never moved before any
+    /// of its children, so the result can still be rendered as a graph.
+    pub fn sort_graph_nodes(
+        &self,
+        store: &Arc<Store>,
+        nodes: Vec<(CommitId, Vec<RevsetGraphEdge>)>,
+    ) -> BackendResult<Vec<(CommitId, Vec<RevsetGraphEdge>)>> {
+        match self {
+            LogOrder::Topological => Ok(nodes),
+            LogOrder::CommitterTimestamp => {
+                let positions: HashMap<&CommitId, usize> = nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, (commit_id, _))| (commit_id, pos))
+                    .collect();
+                let mut timestamps = Vec::with_capacity(nodes.len());
+                let mut num_children = vec![0; nodes.len()];
+                for (commit_id, edges) in &nodes {
+                    let commit = store.get_commit(commit_id)?;
+                    timestamps.push(commit.committer().timestamp.timestamp.clone());
+                    for edge in edges {
+                        if let Some(&pos) = positions.get(&edge.target) {
+                            num_children[pos] += 1;
+                        }
+                    }
+                }
+                // Among the nodes whose children have all been listed, pick the
+                // newest one, and the earliest in topological order on ties.
+                let mut ready: BinaryHeap<_> = (0..nodes.len())
+                    .filter(|&pos| num_children[pos] == 0)
+                    .map(|pos| (timestamps[pos].clone(), Reverse(pos)))
+                    .collect();
+                let mut order = Vec::with_capacity(nodes.len());
+                while let Some((_, Reverse(pos))) = ready.pop() {
+                    order.push(pos);
+                    for edge in &nodes[pos].1 {
+                        if let Some(&parent_pos) = positions.get(&edge.target) {
+                            num_children[parent_pos] -= 1;
+                            if num_children[parent_pos] == 0 {
+                                ready.push((timestamps[parent_pos].clone(), Reverse(parent_pos)));
+                            }
+                        }
+                    }
+                }
+                let mut nodes = nodes.into_iter().map(Some).collect_vec();
+                Ok(order
+                    .into_iter()
+                    .filter_map(|pos| nodes[pos].take())
+                    .collect())
+            }
+        }
+    }
+}
+
+impl FromStr for LogOrder {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "topological" => Ok(LogOrder::Topological),
+            "committer-timestamp" => Ok(LogOrder::CommitterTimestamp),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown log order: {other}"
+            ))),
+        }
+    }
+}
+
+/// Whether `Transaction::commit()` syncs the objects and operations it wrote
+/// to disk before returning. The mode is picked when the repo is loaded; see
+/// `RepoSettings::durability_mode()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurabilityMode {
+    /// Sync before publishing each operation.
+    Sync,
+    /// Leave syncing to the OS. Faster, but operations committed shortly
+    /// before a crash may be lost.
+    #[default]
+    Async,
+}
+
+impl FromStr for DurabilityMode {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(DurabilityMode::Sync),
+            "async" => Ok(DurabilityMode::Async),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown durability mode: {other}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for WhitespaceHandling {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(WhitespaceHandling::Strict),
+            "ignore-trailing" => Ok(WhitespaceHandling::IgnoreTrailing),
+            "ignore-all" => Ok(WhitespaceHandling::IgnoreAll),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown whitespace handling: {other}"
+            ))),
+        }
+    }
+}
+
+/// Where `MutableRepo::check_out()` creates the new working-copy commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckoutPolicy {
+    /// On top of the checked-out commit.
+    Child,
+    /// On top of the checked-out commit's parents, with the same tree as the
+    /// checked-out commit.
+    Sibling,
+}
+
+impl CheckoutPolicy {
+    /// Returns the parents of a new working-copy commit for checking out
+    /// `commit`. The root commit has no parents, so a sibling of it can't be
+    /// created; its child is used instead.
+    pub fn parent_ids(&self, commit: &Commit) -> Vec<CommitId> {
+        match self {
+            CheckoutPolicy::Sibling if !commit.parent_ids().is_empty() => {
+                commit.parent_ids().to_vec()
+            }
+            _ => vec![commit.id().clone()],
+        }
+    }
+}
+
+impl FromStr for CheckoutPolicy {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "child" => Ok(CheckoutPolicy::Child),
+            "sibling" => Ok(CheckoutPolicy::Sibling),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown checkout policy: {other}"
+            ))),
+        }
+    }
+}
+
 fn get_timestamp_config(config: &config::Config, key: &str) -> Option<Timestamp> {
     match config.get_string(key) {
         Ok(timestamp_str) => match DateTime::parse_from_rfc3339(&timestamp_str) {
@@ -89,7 +276,11 @@ impl UserSettings {
     // https://github.com/martinvonz/jj/issues/616#issuecomment-1345170699
     pub fn with_repo(&self, _repo_path: &Path) -> Result<RepoSettings, config::ConfigError> {
         let config = self.config.clone();
-        Ok(RepoSettings { _config: config })
+        let durability_mode = self.durability_mode()?;
+        Ok(RepoSettings {
+            _config: config,
+            durability_mode,
+        })
     }
 
     pub fn get_rng(&self) -> Arc<JJRng> {
@@ -140,6 +331,13 @@ impl UserSettings {
             .unwrap_or_else(|_| whoami::username())
     }
 
+    /// Whether `Transaction::write()` should check the resulting view for
+    /// inconsistencies. Meant for development; off by default since it walks
+    /// the index.
+    pub fn validate_view(&self) -> bool {
+        self.config.get_bool("debug.validate-view").unwrap_or(false)
+    }
+
     pub fn push_branch_prefix(&self) -> String {
         self.config
             .get_string("push.branch-prefix")
@@ -158,6 +356,44 @@ impl UserSettings {
         })
     }
 
+    pub fn log_order(&self) -> Result<LogOrder, config::ConfigError> {
+        match self.config.get_string("ui.log-order") {
+            Ok(log_order) => log_order.parse(),
+            Err(config::ConfigError::NotFound(_)) => Ok(LogOrder::Topological),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn checkout_policy(&self) -> Result<CheckoutPolicy, config::ConfigError> {
+        match self.config.get_string("ui.checkout-policy") {
+            Ok(policy) => policy.parse(),
+            Err(config::ConfigError::NotFound(_)) => Ok(CheckoutPolicy::Child),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn durability_mode(&self) -> Result<DurabilityMode, config::ConfigError> {
+        match self.config.get_string("storage.durability") {
+            Ok(mode) => mode.parse(),
+            Err(config::ConfigError::NotFound(_)) => Ok(DurabilityMode::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Options for merging the contents of files changed on both sides when
+    /// commits are rebased.
+    pub fn merge_options(&self) -> Result<MergeOptions, config::ConfigError> {
+        let whitespace = match self.config.get_string("merge.whitespace") {
+            Ok(whitespace) => whitespace.parse()?,
+            Err(config::ConfigError::NotFound(_)) => WhitespaceHandling::default(),
+            Err(err) => return Err(err),
+        };
+        Ok(MergeOptions {
+            whitespace,
+            ..Default::default()
+        })
+    }
+
     pub fn signature(&self) -> Signature {
         let timestamp = self.timestamp.clone().unwrap_or_else(Timestamp::now);
         Signature {
@@ -173,6 +409,32 @@ impl UserSettings {
             .unwrap_or(false)
     }
 
+    pub fn auto_snapshot(&self) -> bool {
+        self.config.get_bool("ui.auto-snapshot").unwrap_or(false)
+    }
+
+    /// Whether a commit that doesn't change anything compared to its parents
+    /// may be committed with `jj commit`.
+    pub fn allow_empty_commits(&self) -> bool {
+        self.config
+            .get_bool("ui.allow-empty-commits")
+            .unwrap_or(true)
+    }
+
+    /// Whether `jj commit` should refuse to commit a working copy with
+    /// conflicts or leftover conflict markers.
+    pub fn check_conflict_markers(&self) -> bool {
+        self.config
+            .get_bool("ui.check-conflict-markers")
+            .unwrap_or(false)
+    }
+
+    /// Whether `jj commit` should move the current branch (see
+    /// `Repo::current_branch()`) to the committed change.
+    pub fn advance_branches(&self) -> bool {
+        self.config.get_bool("ui.advance-branches").unwrap_or(false)
+    }
+
     pub fn diff_instructions(&self) -> bool {
         self.config.get_bool("ui.diff-instructions").unwrap_or(true)
     }
@@ -192,6 +454,13 @@ impl UserSettings {
     }
 }
 
+impl RepoSettings {
+    /// The `storage.durability` setting the repo was loaded with.
+    pub fn durability_mode(&self) -> DurabilityMode {
+        self.durability_mode
+    }
+}
+
 /// This Rng uses interior mutability to allow generating random values using an
 /// immutable reference. It also fixes a specific seedable RNG for
 /// reproducibility.
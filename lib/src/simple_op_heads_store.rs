@@ -15,9 +15,10 @@
 #![allow(missing_docs)]
 
 use std::fmt::{Debug, Formatter};
-use std::fs;
 use std::path::{Path, PathBuf};
+use std::{fs, io};
 
+use crate::file_util::sync_dir;
 use crate::lock::FileLock;
 use crate::op_heads_store::{OpHeadsStore, OpHeadsStoreLock};
 use crate::op_store::OperationId;
@@ -117,6 +118,11 @@ impl OpHeadsStore for SimpleOpHeadsStore {
             _lock: FileLock::lock(self.dir.join("lock")),
         })
     }
+
+    fn flush(&self) -> io::Result<()> {
+        // The head files are empty, so only the directory entries need syncing
+        sync_dir(&self.dir)
+    }
 }
 
 #[cfg(test)]
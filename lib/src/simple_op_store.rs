@@ -26,11 +26,12 @@ use tempfile::{NamedTempFile, PersistError};
 
 use crate::backend::{CommitId, MillisSinceEpoch, ObjectId, Timestamp};
 use crate::content_hash::blake2b_hash;
-use crate::file_util::persist_content_addressed_temp_file;
+use crate::file_util::{persist_content_addressed_temp_file, PendingSyncs};
 use crate::op_store::{
     BranchTarget, OpStore, OpStoreError, OpStoreResult, Operation, OperationId, OperationMetadata,
     RefTarget, View, ViewId, WorkspaceId,
 };
+use crate::settings::DurabilityMode;
 
 impl From<std::io::Error> for OpStoreError {
     fn from(err: std::io::Error) -> Self {
@@ -53,6 +54,7 @@ impl From<prost::DecodeError> for OpStoreError {
 #[derive(Debug)]
 pub struct SimpleOpStore {
     path: PathBuf,
+    pending_syncs: PendingSyncs,
 }
 
 impl SimpleOpStore {
@@ -62,6 +64,7 @@ impl SimpleOpStore {
         fs::create_dir(store_path.join("operations")).unwrap();
         SimpleOpStore {
             path: store_path.to_owned(),
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
@@ -69,6 +72,7 @@ impl SimpleOpStore {
     pub fn load(store_path: &Path) -> Self {
         SimpleOpStore {
             path: store_path.to_path_buf(),
+            pending_syncs: PendingSyncs::default(),
         }
     }
 
@@ -102,7 +106,9 @@ impl OpStore for SimpleOpStore {
 
         let id = ViewId::new(blake2b_hash(view).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.view_path(&id))?;
+        let path = self.view_path(&id);
+        persist_content_addressed_temp_file(temp_file, &path)?;
+        self.pending_syncs.push(path);
         Ok(id)
     }
 
@@ -122,9 +128,19 @@ impl OpStore for SimpleOpStore {
 
         let id = OperationId::new(blake2b_hash(operation).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.operation_path(&id))?;
+        let path = self.operation_path(&id);
+        persist_content_addressed_temp_file(temp_file, &path)?;
+        self.pending_syncs.push(path);
         Ok(id)
     }
+
+    fn set_durability_mode(&self, mode: DurabilityMode) {
+        self.pending_syncs.set_enabled(mode == DurabilityMode::Sync);
+    }
+
+    fn flush(&self) -> OpStoreResult<()> {
+        Ok(self.pending_syncs.sync_all()?)
+    }
 }
 
 fn not_found_to_store_error(err: std::io::Error) -> OpStoreError {
@@ -33,7 +33,7 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
-use crate::file_util::persist_content_addressed_temp_file;
+use crate::file_util::{persist_content_addressed_temp_file, PendingSyncs};
 use crate::lock::FileLock;
 
 pub trait TableSegment {
@@ -337,7 +337,8 @@ impl MutableTable {
         let mut temp_file = NamedTempFile::new_in(&store.dir)?;
         let file = temp_file.as_file_mut();
         file.write_all(&buf)?;
-        persist_content_addressed_temp_file(temp_file, file_path)?;
+        persist_content_addressed_temp_file(temp_file, &file_path)?;
+        store.pending_syncs.push(file_path);
 
         ReadonlyTable::load_from(&mut buf.as_slice(), store, file_id_hex, store.key_size)
     }
@@ -376,6 +377,7 @@ pub struct TableStore {
     dir: PathBuf,
     key_size: usize,
     cached_tables: RwLock<HashMap<String, Arc<ReadonlyTable>>>,
+    pending_syncs: PendingSyncs,
 }
 
 impl TableStore {
@@ -385,6 +387,7 @@ impl TableStore {
             dir,
             key_size,
             cached_tables: Default::default(),
+            pending_syncs: Default::default(),
         }
     }
 
@@ -397,11 +400,23 @@ impl TableStore {
         self.key_size
     }
 
+    /// Starts or stops keeping track of the table files written, so they can
+    /// be synced to disk by `sync()`.
+    pub fn set_sync_enabled(&self, enabled: bool) {
+        self.pending_syncs.set_enabled(enabled);
+    }
+
+    /// Syncs the table files written since `set_sync_enabled(true)` to disk.
+    pub fn sync(&self) -> io::Result<()> {
+        self.pending_syncs.sync_all()
+    }
+
     pub fn load(dir: PathBuf, key_size: usize) -> Self {
         TableStore {
             dir,
             key_size,
             cached_tables: Default::default(),
+            pending_syncs: Default::default(),
         }
     }
 
@@ -422,7 +437,10 @@ impl TableStore {
     }
 
     fn add_head(&self, table: &Arc<ReadonlyTable>) -> std::io::Result<()> {
-        std::fs::write(self.dir.join("heads").join(&table.name), "")
+        let head_path = self.dir.join("heads").join(&table.name);
+        std::fs::write(&head_path, "")?;
+        self.pending_syncs.push(head_path);
+        Ok(())
     }
 
     fn remove_head(&self, table: &Arc<ReadonlyTable>) {
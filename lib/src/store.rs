@@ -19,15 +19,25 @@ use std::collections::HashMap;
 use std::io::Read;
 use std::sync::{Arc, RwLock};
 
+use itertools::Itertools;
+
 use crate::backend::{
-    Backend, BackendResult, ChangeId, CommitId, ConflictId, FileId, SymlinkId, TreeId, TreeValue,
+    AnyObjectId, Backend, BackendResult, ChangeId, CommitId, ConflictId, FileId, SymlinkId, TreeId,
+    TreeValue,
 };
 use crate::commit::Commit;
 use crate::repo_path::RepoPath;
+use crate::settings::DurabilityMode;
 use crate::tree::Tree;
 use crate::tree_builder::TreeBuilder;
 use crate::{backend, conflicts};
 
+/// Returns true if the two trees have the same contents. Trees are
+/// content-addressed, so this is just an id comparison.
+pub fn trees_equal(a: &TreeId, b: &TreeId) -> bool {
+    a == b
+}
+
 /// Wraps the low-level backend and makes it return more convenient types. Also
 /// adds caching.
 #[derive(Debug)]
@@ -93,11 +103,49 @@ impl Store {
         Ok(data)
     }
 
+    /// Loads several commits, reading the ones that aren't cached from the
+    /// backend in a single batch. The results are in the same order as `ids`.
+    pub fn get_commits(self: &Arc<Self>, ids: &[CommitId]) -> Vec<BackendResult<Commit>> {
+        let mut results: Vec<Option<BackendResult<Commit>>> = {
+            let read_locked_cache = self.commit_cache.read().unwrap();
+            ids.iter()
+                .map(|id| {
+                    read_locked_cache
+                        .get(id)
+                        .map(|data| Ok(Commit::new(self.clone(), id.clone(), data.clone())))
+                })
+                .collect()
+        };
+        let missing_ids = ids
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_none())
+            .map(|(id, _)| id.clone())
+            .collect_vec();
+        if !missing_ids.is_empty() {
+            let mut read_results = self.backend.read_commits(&missing_ids).into_iter();
+            let mut write_locked_cache = self.commit_cache.write().unwrap();
+            for (id, result) in ids.iter().zip(&mut results) {
+                if result.is_none() {
+                    *result = Some(read_results.next().unwrap().map(|commit| {
+                        let data = Arc::new(commit);
+                        write_locked_cache.insert(id.clone(), data.clone());
+                        Commit::new(self.clone(), id.clone(), data)
+                    }));
+                }
+            }
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
     pub fn write_commit(self: &Arc<Self>, commit: backend::Commit) -> BackendResult<Commit> {
         assert!(!commit.parents.is_empty());
         let (commit_id, commit) = self.backend.write_commit(commit)?;
         let data = Arc::new(commit);
         {
+            // Cache what the backend returned, which may differ from what we passed in
+            // (e.g. an adjusted committer timestamp). Commits are content-addressed, so
+            // replacing an existing entry for the same id never changes its data.
             let mut write_locked_cache = self.commit_cache.write().unwrap();
             write_locked_cache.insert(commit_id.clone(), data.clone());
         }
@@ -139,10 +187,33 @@ impl Store {
         Ok(Tree::new(self.clone(), path.clone(), tree_id, data))
     }
 
+    /// Iterates over the ids of all objects in the backend, including ones
+    /// that aren't reachable from any commit. See `Backend::all_object_ids()`.
+    pub fn iter_all_objects(&self) -> BackendResult<impl Iterator<Item = AnyObjectId>> {
+        Ok(self.backend.all_object_ids()?.into_iter())
+    }
+
+    /// See `Backend::set_durability_mode()`.
+    pub fn set_durability_mode(&self, mode: DurabilityMode) {
+        self.backend.set_durability_mode(mode);
+    }
+
+    /// Makes sure all objects written so far are on disk. See
+    /// `Backend::flush()`.
+    pub fn flush(&self) -> BackendResult<()> {
+        self.backend.flush()
+    }
+
     pub fn read_file(&self, path: &RepoPath, id: &FileId) -> BackendResult<Box<dyn Read>> {
         self.backend.read_file(path, id)
     }
 
+    /// Returns the size in bytes of the contents of the file with the given id.
+    /// See `Backend::file_size()`.
+    pub fn object_size(&self, path: &RepoPath, id: &FileId) -> BackendResult<u64> {
+        self.backend.file_size(path, id)
+    }
+
     pub fn write_file(&self, path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
         self.backend.write_file(path, contents)
     }
@@ -14,16 +14,20 @@
 
 #![allow(missing_docs)]
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use crate::backend::Timestamp;
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::backend::{BackendResult, CommitId, MillisSinceEpoch, ObjectId, Timestamp};
 use crate::dag_walk::closest_common_node;
-use crate::index::ReadonlyIndex;
+use crate::index::{Index, ReadonlyIndex};
 use crate::op_store;
-use crate::op_store::OperationMetadata;
+use crate::op_store::{OperationMetadata, WorkspaceId};
 use crate::operation::Operation;
-use crate::repo::{MutableRepo, ReadonlyRepo, Repo, RepoLoader};
-use crate::settings::UserSettings;
+use crate::repo::{FlushError, MutableRepo, ReadonlyRepo, Repo, RepoLoader};
+use crate::settings::{DurabilityMode, UserSettings};
 use crate::view::View;
 
 pub struct Transaction {
@@ -31,6 +35,7 @@ pub struct Transaction {
     parent_ops: Vec<Operation>,
     op_metadata: OperationMetadata,
     end_time: Option<Timestamp>,
+    validate_view: bool,
 }
 
 impl Transaction {
@@ -42,14 +47,35 @@ impl Transaction {
         let parent_ops = vec![mut_repo.base_repo().operation().clone()];
         let op_metadata = create_op_metadata(user_settings, description.to_string());
         let end_time = user_settings.operation_timestamp();
+        let validate_view = user_settings.validate_view();
         Transaction {
             mut_repo,
             parent_ops,
             op_metadata,
             end_time,
+            validate_view,
         }
     }
 
+    /// Starts a transaction on top of `base_op`, which may be any operation in
+    /// `repo`'s history rather than the latest one. The resulting operation
+    /// will be a child of `base_op`, creating a branch in the operation log
+    /// that is merged the next time the repo is loaded at head.
+    pub fn new_with_base(
+        repo: &ReadonlyRepo,
+        base_op: &Operation,
+        user_settings: &UserSettings,
+        description: &str,
+    ) -> Transaction {
+        let base_repo = repo.reload_at(base_op);
+        let mut_repo = MutableRepo::new(
+            base_repo.clone(),
+            base_repo.readonly_index(),
+            base_repo.view(),
+        );
+        Transaction::new(mut_repo, user_settings, description)
+    }
+
     pub fn base_repo(&self) -> &Arc<ReadonlyRepo> {
         self.mut_repo.base_repo()
     }
@@ -86,15 +112,167 @@ impl Transaction {
         merged_repo.merge(&base_repo, &other_repo);
     }
 
-    /// Writes the transaction to the operation store and publishes it.
-    pub fn commit(self) -> Arc<ReadonlyRepo> {
-        self.write().publish()
+    /// Applies the changes a foreign operation made, from `base_view` to
+    /// `foreign_op_view`, on top of this transaction, e.g. to mirror an
+    /// operation from another repo that shares the same backend. Conflicting
+    /// changes are merged the same way as in `merge_operation()`.
+    pub fn apply_foreign_operation(
+        &mut self,
+        foreign_op_view: &op_store::View,
+        base_view: &op_store::View,
+    ) -> BackendResult<()> {
+        self.mut_repo.merge_foreign_view(base_view, foreign_op_view)
+    }
+
+    /// Generates a description of the changes made so far in this
+    /// transaction, such as "check out commit abc123" or "create 2 commits".
+    /// Used by `write()` when no description was given. Only the commits
+    /// written in this transaction are read, plus those recorded as abandoned.
+    pub fn auto_description(&self) -> BackendResult<String> {
+        let base_view = self.base_repo().view();
+        let view = self.mut_repo.view();
+        let checked_out: Vec<_> = view
+            .wc_commit_ids()
+            .iter()
+            .filter(|(workspace_id, commit_id)| {
+                base_view.get_wc_commit_id(workspace_id) != Some(*commit_id)
+            })
+            .map(|(_, commit_id)| self.checkout_target(commit_id))
+            .try_collect()?;
+        if !checked_out.is_empty() {
+            return Ok(checked_out
+                .iter()
+                .unique()
+                .sorted()
+                .map(|commit_id| format!("check out commit {}", &commit_id.hex()[0..12]))
+                .join(", "));
+        }
+
+        // Walk the visible commits that didn't exist before the transaction
+        let base_index = self.base_repo().index();
+        let store = self.mut_repo.store();
+        let mut num_created = 0;
+        let mut num_rewritten = 0;
+        let mut visited = HashSet::new();
+        let mut to_visit = view
+            .heads()
+            .iter()
+            .filter(|id| !base_index.has_id(id))
+            .cloned()
+            .collect_vec();
+        while let Some(commit_id) = to_visit.pop() {
+            if !visited.insert(commit_id.clone()) {
+                continue;
+            }
+            let commit = store.get_commit(&commit_id)?;
+            if commit.predecessor_ids().is_empty() {
+                num_created += 1;
+            } else {
+                num_rewritten += 1;
+            }
+            to_visit.extend(
+                commit
+                    .parent_ids()
+                    .iter()
+                    .filter(|id| !base_index.has_id(id))
+                    .cloned(),
+            );
+        }
+        let num_abandoned = self
+            .mut_repo
+            .all_abandoned_commits()
+            .iter()
+            .filter(|id| base_index.has_id(id))
+            .count();
+        let parts = [
+            ("create", num_created),
+            ("rewrite", num_rewritten),
+            ("abandon", num_abandoned),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(verb, count)| match count {
+            1 => format!("{verb} commit"),
+            _ => format!("{verb} {count} commits"),
+        })
+        .collect_vec();
+        if !parts.is_empty() {
+            Ok(parts.join(", "))
+        } else if view != base_view {
+            Ok("update refs".to_string())
+        } else {
+            Ok("no changes".to_string())
+        }
+    }
+
+    /// Returns the commit the user checked out to get `wc_commit_id`: the
+    /// parent if the working-copy commit was created in this transaction on
+    /// top of a single commit, otherwise the working-copy commit itself.
+    fn checkout_target(&self, wc_commit_id: &CommitId) -> BackendResult<CommitId> {
+        if !self.base_repo().index().has_id(wc_commit_id) {
+            let wc_commit = self.mut_repo.store().get_commit(wc_commit_id)?;
+            if let [parent_id] = wc_commit.parent_ids() {
+                return Ok(parent_id.clone());
+            }
+        }
+        Ok(wc_commit_id.clone())
+    }
+
+    /// Starts a transaction on `repo`, runs `f` on it, and commits it if `f`
+    /// succeeds. If `f` fails, the transaction is discarded and no operation
+    /// is written. Returns the repo at the new operation along with the value
+    /// returned by `f`.
+    pub fn run<T, E: From<FlushError>>(
+        repo: &Arc<ReadonlyRepo>,
+        user_settings: &UserSettings,
+        description: &str,
+        f: impl FnOnce(&mut Transaction) -> Result<T, E>,
+    ) -> Result<(Arc<ReadonlyRepo>, T), E> {
+        let mut tx = repo.start_transaction(user_settings, description);
+        let value = f(&mut tx)?;
+        Ok((tx.commit()?, value))
+    }
+
+    /// Writes the transaction to the operation store and publishes it. With
+    /// the `Sync` durability mode, the objects, operation and index written
+    /// are synced to disk before the operation is published, and the
+    /// operation heads are synced after. If syncing fails, the operation is
+    /// not published.
+    pub fn commit(self) -> Result<Arc<ReadonlyRepo>, FlushError> {
+        let base_repo = self.base_repo().clone();
+        let sync = base_repo.settings().durability_mode() == DurabilityMode::Sync;
+        let unpublished_op = self.write();
+        if sync {
+            base_repo.flush()?;
+        }
+        let repo = unpublished_op.publish();
+        if sync {
+            repo.op_heads_store()
+                .flush()
+                .map_err(FlushError::OpHeadsStore)?;
+        }
+        Ok(repo)
     }
 
     /// Writes the transaction to the operation store, but does not publish it.
     /// That means that a repo can be loaded at the operation, but the
-    /// operation will not be seen when loading the repo at head.
+    /// operation will not be seen when loading the repo at head. Nothing is
+    /// synced to disk; see `commit()`. If the description is empty, one is
+    /// generated by `auto_description()`, or "unknown changes" is used if that
+    /// fails.
+    ///
+    /// Panics if the `debug.validate-view` setting is enabled and the
+    /// resulting view fails `validate_view()`.
     pub fn write(mut self) -> UnpublishedOperation {
+        if self.op_metadata.description.is_empty() {
+            self.op_metadata.description = self
+                .auto_description()
+                .unwrap_or_else(|_| "unknown changes".to_string());
+        }
+        self.op_metadata.end_time = match self.end_time.take() {
+            Some(end_time) => end_time,
+            None => self.monotonic_now(),
+        };
         let mut_repo = self.mut_repo;
         // TODO: Should we instead just do the rebasing here if necessary?
         assert!(
@@ -103,9 +281,13 @@ impl Transaction {
         );
         let base_repo = mut_repo.base_repo().clone();
         let (mut_index, view) = mut_repo.consume();
+        if self.validate_view {
+            if let Err(err) = validate_view(mut_index.as_index(), &view) {
+                panic!("BUG: Transaction produced an inconsistent view: {err}");
+            }
+        }
 
         let view_id = base_repo.op_store().write_view(view.store_view()).unwrap();
-        self.op_metadata.end_time = self.end_time.unwrap_or_else(Timestamp::now);
         let parents = self.parent_ops.iter().map(|op| op.id().clone()).collect();
         let store_operation = op_store::Operation {
             view_id,
@@ -124,6 +306,81 @@ impl Transaction {
             .unwrap();
         UnpublishedOperation::new(base_repo.loader(), operation, view, index)
     }
+
+    /// Returns the current time, bumped to just after the latest parent
+    /// operation if the system clock is behind it, so the operation log stays
+    /// chronological. Adjustments are recorded in the operation's tags.
+    fn monotonic_now(&mut self) -> Timestamp {
+        let now = Timestamp::now();
+        let latest_parent_millis = self
+            .parent_ops
+            .iter()
+            .map(|op| op.store_operation().metadata.end_time.timestamp.0)
+            .max();
+        match latest_parent_millis {
+            Some(parent_millis) if now.timestamp.0 <= parent_millis => {
+                let adjusted_millis = parent_millis + 1;
+                self.op_metadata.tags.insert(
+                    "clock_adjustment_ms".to_string(),
+                    (adjusted_millis - now.timestamp.0).to_string(),
+                );
+                Timestamp {
+                    timestamp: MillisSinceEpoch(adjusted_millis),
+                    tz_offset: now.tz_offset,
+                }
+            }
+            _ => now,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ViewValidationError {
+    #[error("Head {} is not in the index", .0.hex())]
+    HeadNotIndexed(CommitId),
+    #[error("Head {} is an ancestor of another head", .0.hex())]
+    RedundantHead(CommitId),
+    #[error("Public head {} is not visible", .0.hex())]
+    HiddenPublicHead(CommitId),
+    #[error("Working-copy commit {} of workspace {} is not visible", .1.hex(), .0.as_str())]
+    DanglingCheckout(WorkspaceId, CommitId),
+}
+
+/// Checks that the view's heads are indexed and minimal, and that its public
+/// heads and working-copy commits are ancestors of its heads.
+pub fn validate_view(index: &dyn Index, view: &View) -> Result<(), ViewValidationError> {
+    let heads = view.heads();
+    for head_id in heads.iter().sorted() {
+        if !index.has_id(head_id) {
+            return Err(ViewValidationError::HeadNotIndexed(head_id.clone()));
+        }
+    }
+    let minimal_heads: HashSet<CommitId> = index.heads(&mut heads.iter()).into_iter().collect();
+    if let Some(head_id) = heads.difference(&minimal_heads).sorted().next() {
+        return Err(ViewValidationError::RedundantHead(head_id.clone()));
+    }
+    let is_visible = |commit_id: &CommitId| {
+        index.has_id(commit_id)
+            && heads
+                .iter()
+                .any(|head_id| index.is_ancestor(commit_id, head_id))
+    };
+    for public_head_id in view.public_heads().iter().sorted() {
+        if !is_visible(public_head_id) {
+            return Err(ViewValidationError::HiddenPublicHead(
+                public_head_id.clone(),
+            ));
+        }
+    }
+    for (workspace_id, wc_commit_id) in view.wc_commit_ids().iter().sorted() {
+        if !is_visible(wc_commit_id) {
+            return Err(ViewValidationError::DanglingCheckout(
+                workspace_id.clone(),
+                wc_commit_id.clone(),
+            ));
+        }
+    }
+    Ok(())
 }
 
 pub fn create_op_metadata(user_settings: &UserSettings, description: String) -> OperationMetadata {
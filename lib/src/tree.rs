@@ -27,7 +27,7 @@ use crate::backend::{
     TreeValue,
 };
 use crate::conflicts::Conflict;
-use crate::files::MergeResult;
+use crate::files::{MergeOptions, MergeResult};
 use crate::matchers::{EverythingMatcher, Matcher};
 use crate::merge::trivial_merge;
 use crate::repo_path::{RepoPath, RepoPathComponent, RepoPathJoin};
@@ -43,6 +43,8 @@ pub enum TreeMergeError {
     },
     #[error("Backend error: {0}")]
     BackendError(#[from] BackendError),
+    #[error("Invalid merge configuration: {0}")]
+    Config(#[from] config::ConfigError),
 }
 
 #[derive(Clone)]
@@ -158,6 +160,15 @@ impl Tree {
         }
     }
 
+    /// Returns the id of the conflict at `path`, or `None` if the path isn't
+    /// conflicted.
+    pub fn conflict_id_at(&self, path: &RepoPath) -> Option<ConflictId> {
+        match self.path_value(path) {
+            Some(TreeValue::Conflict(id)) => Some(id),
+            _ => None,
+        }
+    }
+
     pub fn sub_tree(&self, name: &RepoPathComponent) -> Option<Tree> {
         self.data.value(name).and_then(|sub_tree| match sub_tree {
             TreeValue::Tree(sub_tree_id) => {
@@ -322,6 +333,30 @@ impl<T> Diff<T> {
     }
 }
 
+/// The kind of change to a path between two trees.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Modified,
+    /// The path is conflicted in either tree.
+    Conflicted,
+}
+
+impl DiffKind {
+    pub fn from_diff(diff: &Diff<TreeValue>) -> Self {
+        match diff {
+            Diff::Added(TreeValue::Conflict(_))
+            | Diff::Removed(TreeValue::Conflict(_))
+            | Diff::Modified(TreeValue::Conflict(_), _)
+            | Diff::Modified(_, TreeValue::Conflict(_)) => DiffKind::Conflicted,
+            Diff::Added(_) => DiffKind::Added,
+            Diff::Removed(_) => DiffKind::Removed,
+            Diff::Modified(_, _) => DiffKind::Modified,
+        }
+    }
+}
+
 struct TreeEntryDiffIterator<'trees> {
     tree1: &'trees Tree,
     tree2: &'trees Tree,
@@ -484,6 +519,17 @@ pub fn merge_trees(
     side1_tree: &Tree,
     base_tree: &Tree,
     side2_tree: &Tree,
+) -> Result<Tree, TreeMergeError> {
+    merge_trees_with_options(side1_tree, base_tree, side2_tree, &MergeOptions::default())
+}
+
+/// Like `merge_trees()`, but merges the contents of files changed on both
+/// sides according to `options`.
+pub fn merge_trees_with_options(
+    side1_tree: &Tree,
+    base_tree: &Tree,
+    side2_tree: &Tree,
+    options: &MergeOptions,
 ) -> Result<Tree, TreeMergeError> {
     let store = base_tree.store();
     let dir = base_tree.dir();
@@ -507,8 +553,15 @@ pub fn merge_trees(
             // value
         } else {
             // The two sides changed in different ways
-            let new_value =
-                merge_tree_value(store, dir, basename, maybe_base, maybe_side1, maybe_side2)?;
+            let new_value = merge_tree_value(
+                store,
+                dir,
+                basename,
+                maybe_base,
+                maybe_side1,
+                maybe_side2,
+                options,
+            )?;
             new_tree.set_or_remove(basename, new_value);
         }
     }
@@ -535,6 +588,7 @@ fn merge_tree_value(
     maybe_base: Option<&TreeValue>,
     maybe_side1: Option<&TreeValue>,
     maybe_side2: Option<&TreeValue>,
+    options: &MergeOptions,
 ) -> Result<Option<TreeValue>, TreeMergeError> {
     // Resolve non-trivial conflicts:
     //   * resolve tree conflicts by recursing
@@ -552,7 +606,8 @@ fn merge_tree_value(
             let base_tree = store.get_tree(&subdir, base_id)?;
             let side1_tree = store.get_tree(&subdir, side1_id)?;
             let side2_tree = store.get_tree(&subdir, side2_id)?;
-            let merged_tree = merge_trees(&side1_tree, &base_tree, &side2_tree)?;
+            let merged_tree =
+                merge_trees_with_options(&side1_tree, &base_tree, &side2_tree, options)?;
             if merged_tree.id() == empty_tree_id {
                 None
             } else {
@@ -571,7 +626,9 @@ fn merge_tree_value(
             if let Some(value) = conflict.as_resolved() {
                 return Ok(value.clone());
             }
-            if let Some(tree_value) = try_resolve_file_conflict(store, &filename, &conflict)? {
+            if let Some(tree_value) =
+                try_resolve_file_conflict(store, &filename, &conflict, options)?
+            {
                 Some(tree_value)
             } else {
                 let conflict_id = store.write_conflict(&filename, &conflict)?;
@@ -585,6 +642,7 @@ fn try_resolve_file_conflict(
     store: &Store,
     filename: &RepoPath,
     conflict: &Conflict<Option<TreeValue>>,
+    options: &MergeOptions,
 ) -> Result<Option<TreeValue>, TreeMergeError> {
     // If there are any non-file or any missing parts in the conflict, we can't
     // merge it. We check early so we don't waste time reading file contents if
@@ -646,9 +704,10 @@ fn try_resolve_file_conflict(
             })?;
         added_contents.push(content);
     }
-    let merge_result = files::merge(
+    let merge_result = files::merge_with_options(
         &removed_contents.iter().map(Vec::as_slice).collect_vec(),
         &added_contents.iter().map(Vec::as_slice).collect_vec(),
+        options,
     );
     match merge_result {
         MergeResult::Resolved(merged_content) => {
@@ -82,6 +82,10 @@ impl View {
         &self.data.head_ids
     }
 
+    pub fn is_head(&self, id: &CommitId) -> bool {
+        self.data.head_ids.contains(id)
+    }
+
     pub fn public_heads(&self) -> &HashSet<CommitId> {
         &self.data.public_head_ids
     }
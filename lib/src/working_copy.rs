@@ -37,6 +37,7 @@ use thiserror::Error;
 use crate::backend::{
     BackendError, ConflictId, FileId, MillisSinceEpoch, ObjectId, SymlinkId, TreeId, TreeValue,
 };
+use crate::conflicts::has_conflict_markers;
 #[cfg(feature = "watchman")]
 use crate::fsmonitor::watchman;
 use crate::fsmonitor::FsmonitorKind;
@@ -97,6 +98,15 @@ impl FileState {
         }
     }
 
+    /// A state for a file whose metadata hasn't been read.
+    fn placeholder(executable: bool) -> Self {
+        FileState {
+            file_type: FileType::Normal { executable },
+            mtime: MillisSinceEpoch(0),
+            size: 0,
+        }
+    }
+
     fn for_gitsubmodule() -> Self {
         FileState {
             file_type: FileType::GitSubmodule,
@@ -916,8 +926,19 @@ impl TreeState {
         path: &RepoPath,
         id: &ConflictId,
     ) -> Result<FileState, CheckoutError> {
-        create_parent_dirs(&self.working_copy_path, path)?;
         let conflict = self.store.read_conflict(path, id)?;
+        // If the conflict is actually resolved, write the resolved file instead of
+        // conflict markers.
+        match conflict.clone().simplify().as_resolved() {
+            Some(Some(TreeValue::File { id, executable })) => {
+                return self.write_file(disk_path, path, id, *executable);
+            }
+            Some(Some(TreeValue::Symlink(id))) => {
+                return self.write_symlink(disk_path, path, id);
+            }
+            _ => {}
+        }
+        create_parent_dirs(&self.working_copy_path, path)?;
         let mut file = OpenOptions::new()
             .write(true)
             .create_new(true) // Don't overwrite un-ignored file. Don't follow symlink.
@@ -1141,6 +1162,103 @@ impl TreeState {
     }
 }
 
+/// Statistics about the filesystem changes made by `reset_to()`.
+pub type ResetStats = CheckoutStats;
+
+/// Updates the files in `working_copy_path` from `previous_tree` to
+/// `target_tree`, touching only the paths that differ between the two trees.
+/// Files not tracked by either tree are left alone, and conflicts in
+/// `target_tree` are materialized. No tree state is read or written.
+pub fn reset_to(
+    store: &Arc<Store>,
+    working_copy_path: &Path,
+    target_tree: &Tree,
+    previous_tree: &Tree,
+) -> Result<ResetStats, CheckoutError> {
+    let mut tree_state = TreeState::empty(
+        store.clone(),
+        working_copy_path.to_path_buf(),
+        PathBuf::new(),
+    );
+    // `update()` expects a state for each file it modifies in place, such as
+    // when only the executable bit changes
+    for (path, diff) in previous_tree.diff(target_tree, &EverythingMatcher) {
+        if let (Some(TreeValue::File { executable, .. }), _) = diff.into_options() {
+            tree_state
+                .file_states
+                .insert(path, FileState::placeholder(executable));
+        }
+    }
+    tree_state.update(previous_tree, target_tree, &EverythingMatcher, Err)
+}
+
+#[derive(Debug, Error)]
+#[error("Unresolved conflicts in {}", paths.iter().map(|path| path.to_internal_file_string()).join(", "))]
+pub struct UnresolvedConflicts {
+    pub paths: Vec<RepoPath>,
+}
+
+/// Error from `assert_resolved()`
+#[derive(Debug, Error)]
+pub enum AssertResolvedError {
+    #[error(transparent)]
+    Unresolved(#[from] UnresolvedConflicts),
+    #[error("Failed to read {}: {source}", path.to_internal_file_string())]
+    ReadFile {
+        path: RepoPath,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Checks that no file that changed between `base_tree` and `tree` is
+/// conflicted or still contains conflict markers in `working_copy_path`.
+/// Markers of conflicts with any number of sides are detected. Files missing
+/// from the working copy, such as ones outside the sparse patterns, are checked
+/// in the store instead.
+pub fn assert_resolved(
+    store: &Arc<Store>,
+    working_copy_path: &Path,
+    base_tree: &Tree,
+    tree: &Tree,
+) -> Result<(), AssertResolvedError> {
+    let mut paths = vec![];
+    for (path, diff) in base_tree.diff(tree, &EverythingMatcher) {
+        match diff.into_options().1 {
+            Some(TreeValue::Conflict(_)) => paths.push(path),
+            Some(TreeValue::File { id, .. }) => {
+                let content = match fs::read(path.to_fs_path(working_copy_path)) {
+                    Ok(content) => content,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                        let mut content = vec![];
+                        store
+                            .read_file(&path, &id)?
+                            .read_to_end(&mut content)
+                            .map_err(|source| AssertResolvedError::ReadFile {
+                                path: path.clone(),
+                                source,
+                            })?;
+                        content
+                    }
+                    Err(source) => {
+                        return Err(AssertResolvedError::ReadFile { path, source });
+                    }
+                };
+                if has_conflict_markers(&content) {
+                    paths.push(path);
+                }
+            }
+            _ => {}
+        }
+    }
+    if paths.is_empty() {
+        Ok(())
+    } else {
+        Err(UnresolvedConflicts { paths }.into())
+    }
+}
+
 /// Working copy state stored in "checkout" file.
 #[derive(Clone, Debug)]
 struct CheckoutState {
@@ -21,7 +21,7 @@ use std::sync::Arc;
 
 use thiserror::Error;
 
-use crate::backend::{Backend, BackendInitError};
+use crate::backend::{Backend, BackendError, BackendInitError};
 use crate::file_util::{IoResultExt as _, PathError};
 use crate::git_backend::GitBackend;
 use crate::index::IndexStore;
@@ -29,12 +29,14 @@ use crate::local_backend::LocalBackend;
 use crate::op_heads_store::OpHeadsStore;
 use crate::op_store::{OpStore, WorkspaceId};
 use crate::repo::{
-    CheckOutCommitError, ReadonlyRepo, Repo, RepoInitError, RepoLoader, StoreFactories,
-    StoreLoadError,
+    CheckOutCommitError, FlushError, ReadonlyRepo, Repo, RepoInitError, RepoLoader,
+    RewriteRootCommit, StoreFactories, StoreLoadError,
 };
 use crate::settings::UserSettings;
 use crate::submodule_store::SubmoduleStore;
-use crate::working_copy::WorkingCopy;
+use crate::transaction::Transaction;
+use crate::tree::TreeMergeError;
+use crate::working_copy::{SnapshotError, SnapshotOptions, WorkingCopy};
 
 #[derive(Error, Debug)]
 pub enum WorkspaceInitError {
@@ -48,6 +50,10 @@ pub enum WorkspaceInitError {
     Path(#[from] PathError),
     #[error(transparent)]
     Backend(#[from] BackendInitError),
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
+    #[error(transparent)]
+    Flush(#[from] FlushError),
 }
 
 #[derive(Error, Debug)]
@@ -64,6 +70,23 @@ pub enum WorkspaceLoadError {
     Path(#[from] PathError),
 }
 
+/// Error from `Workspace::snapshot_working_copy()`
+#[derive(Error, Debug)]
+pub enum WorkspaceSnapshotError {
+    #[error("The working copy is stale (its tree doesn't match the working-copy commit's)")]
+    WorkingCopyStale,
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+    #[error(transparent)]
+    RewriteRootCommit(#[from] RewriteRootCommit),
+    #[error(transparent)]
+    Flush(#[from] FlushError),
+}
+
 /// Represents a workspace, i.e. what's typically the .jj/ directory and its
 /// parent.
 pub struct Workspace {
@@ -104,7 +127,7 @@ fn init_working_copy(
         user_settings,
         &repo.store().root_commit(),
     )?;
-    let repo = tx.commit();
+    let repo = tx.commit()?;
 
     let working_copy = WorkingCopy::init(
         repo.store().clone(),
@@ -190,6 +213,7 @@ impl Workspace {
             .map_err(|repo_init_err| match repo_init_err {
                 RepoInitError::Backend(err) => WorkspaceInitError::Backend(err),
                 RepoInitError::Path(err) => WorkspaceInitError::Path(err),
+                RepoInitError::Config(err) => WorkspaceInitError::Config(err),
             })?;
             let (working_copy, repo) = init_working_copy(
                 user_settings,
@@ -283,6 +307,72 @@ impl Workspace {
     pub fn working_copy_mut(&mut self) -> &mut WorkingCopy {
         &mut self.working_copy
     }
+
+    /// Snapshots the working copy and, if it has changed, records the new tree
+    /// in the workspace's working-copy commit in a separate operation.
+    /// Descendants of the working-copy commit are rebased. Returns the repo
+    /// after that operation, or `repo` itself if the working copy was clean.
+    /// `options` should include the user's base ignores (such as git's
+    /// `core.excludesFile`) so ignored files aren't snapshotted.
+    pub fn snapshot_working_copy(
+        &mut self,
+        repo: &Arc<ReadonlyRepo>,
+        settings: &UserSettings,
+        options: SnapshotOptions,
+    ) -> Result<Arc<ReadonlyRepo>, WorkspaceSnapshotError> {
+        let workspace_id = self.workspace_id().clone();
+        let wc_commit = match repo.view().get_wc_commit_id(&workspace_id) {
+            Some(wc_commit_id) => repo.store().get_commit(wc_commit_id)?,
+            // The workspace has been deleted
+            None => return Ok(repo.clone()),
+        };
+        let mut locked_wc = self.working_copy.start_mutation();
+        if locked_wc.old_tree_id() != wc_commit.tree_id() {
+            locked_wc.discard();
+            return Err(WorkspaceSnapshotError::WorkingCopyStale);
+        }
+        let new_tree_id = locked_wc.snapshot(options)?;
+        if new_tree_id == *wc_commit.tree_id() {
+            locked_wc.discard();
+            return Ok(repo.clone());
+        }
+        let mut tx = repo.start_transaction(settings, "snapshot working copy");
+        let mut_repo = tx.mut_repo();
+        let commit = mut_repo
+            .rewrite_commit(settings, &wc_commit)
+            .set_tree(new_tree_id)
+            .write()?;
+        mut_repo.set_wc_commit(workspace_id, commit.id().clone())?;
+        mut_repo.rebase_descendants(settings)?;
+        let repo = match tx.commit() {
+            Ok(repo) => repo,
+            Err(err) => {
+                locked_wc.discard();
+                return Err(err.into());
+            }
+        };
+        locked_wc.finish(repo.op_id().clone());
+        Ok(repo)
+    }
+
+    /// Starts a transaction on `repo`. If `ui.auto-snapshot` is enabled, the
+    /// working copy is first snapshotted by `snapshot_working_copy()` with
+    /// `options`, so the transaction starts from the resulting operation and
+    /// sees any uncommitted changes.
+    pub fn start_transaction(
+        &mut self,
+        repo: &Arc<ReadonlyRepo>,
+        settings: &UserSettings,
+        options: SnapshotOptions,
+        description: &str,
+    ) -> Result<Transaction, WorkspaceSnapshotError> {
+        let repo = if settings.auto_snapshot() {
+            self.snapshot_working_copy(repo, settings, options)?
+        } else {
+            repo.clone()
+        };
+        Ok(repo.start_transaction(settings, description))
+    }
 }
 
 #[derive(Clone)]
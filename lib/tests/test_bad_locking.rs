@@ -103,7 +103,7 @@ fn test_bad_locking_children(use_git: bool) {
         .set_parents(vec![repo.store().root_commit_id().clone()])
         .write()
         .unwrap();
-    tx.commit();
+    tx.commit().unwrap();
 
     // Simulate a write of a commit that happens on one machine
     let machine1_root = testutils::new_temp_dir();
@@ -119,7 +119,7 @@ fn test_bad_locking_children(use_git: bool) {
         .set_parents(vec![initial.id().clone()])
         .write()
         .unwrap();
-    machine1_tx.commit();
+    machine1_tx.commit().unwrap();
 
     // Simulate a write of a commit that happens on another machine
     let machine2_root = testutils::new_temp_dir();
@@ -135,7 +135,7 @@ fn test_bad_locking_children(use_git: bool) {
         .set_parents(vec![initial.id().clone()])
         .write()
         .unwrap();
-    machine2_tx.commit();
+    machine2_tx.commit().unwrap();
 
     // Simulate that the distributed file system now has received the changes from
     // both machines
@@ -174,7 +174,7 @@ fn test_bad_locking_interrupted(use_git: bool) {
         .set_parents(vec![repo.store().root_commit_id().clone()])
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Simulate a crash that resulted in the old op-head left in place. We simulate
     // it somewhat hackily by copying the .jj/op_heads/ directory before the
@@ -188,7 +188,7 @@ fn test_bad_locking_interrupted(use_git: bool) {
         .set_parents(vec![initial.id().clone()])
         .write()
         .unwrap();
-    let op_id = tx.commit().operation().id().clone();
+    let op_id = tx.commit().unwrap().operation().id().clone();
 
     copy_directory(backup_path.path(), &op_heads_dir);
     // Reload the repo and check that only the new head is present.
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use itertools::Itertools;
 use jj_lib::backend::{ChangeId, MillisSinceEpoch, ObjectId, Signature, Timestamp};
 use jj_lib::matchers::EverythingMatcher;
 use jj_lib::repo::Repo;
@@ -75,7 +76,7 @@ fn test_initial(use_git: bool) {
     assert_eq!(builder.author(), &author_signature);
     assert_eq!(builder.committer(), &committer_signature);
     let commit = builder.write().unwrap();
-    tx.commit();
+    tx.commit().unwrap();
 
     assert_eq!(commit.parents(), vec![store.root_commit()]);
     assert_eq!(commit.predecessors(), vec![]);
@@ -95,6 +96,67 @@ fn test_initial(use_git: bool) {
     );
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_author_committer_round_trip(use_git: bool) {
+    // Test that distinct author and committer signatures survive a round trip
+    // through the backend, and that a rewrite keeps the author but replaces the
+    // committer
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let author_signature = Signature {
+        name: "Author Name".to_string(),
+        email: "author@example.com".to_string(),
+        timestamp: Timestamp {
+            timestamp: MillisSinceEpoch(1000),
+            tz_offset: 60,
+        },
+    };
+    let committer_signature = Signature {
+        name: "Committer Name".to_string(),
+        email: "committer@example.com".to_string(),
+        timestamp: Timestamp {
+            timestamp: MillisSinceEpoch(2000),
+            tz_offset: -60,
+        },
+    };
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            repo.store().empty_tree_id().clone(),
+        )
+        .set_author(author_signature.clone())
+        .set_committer(committer_signature.clone())
+        .write()
+        .unwrap();
+    tx.commit().unwrap();
+
+    // Read the commit back through a fresh store so it isn't served from cache
+    let reloaded_repo = testutils::load_repo_at_head(&settings, repo.repo_path());
+    let reloaded_commit = reloaded_repo.store().get_commit(commit.id()).unwrap();
+    assert_eq!(reloaded_commit.author(), &author_signature);
+    assert_eq!(reloaded_commit.committer(), &committer_signature);
+
+    let mut tx = reloaded_repo.start_transaction(&settings, "test");
+    let rewritten_commit = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &reloaded_commit)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(rewritten_commit.author(), &author_signature);
+    assert_eq!(rewritten_commit.committer().name, settings.user_name());
+    assert_eq!(rewritten_commit.committer().email, settings.user_email());
+    assert_ne!(rewritten_commit.committer(), &committer_signature);
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_rewrite(use_git: bool) {
@@ -123,7 +185,7 @@ fn test_rewrite(use_git: bool) {
         )
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let rewritten_tree = testutils::create_tree(
         &repo,
@@ -149,7 +211,7 @@ fn test_rewrite(use_git: bool) {
         .write()
         .unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    tx.commit();
+    tx.commit().unwrap();
     assert_eq!(rewritten_commit.parents(), vec![store.root_commit()]);
     assert_eq!(
         rewritten_commit.predecessors(),
@@ -252,7 +314,7 @@ fn test_commit_builder_descendants(use_git: bool) {
     let commit1 = graph_builder.initial_commit();
     let commit2 = graph_builder.commit_with_parents(&[&commit1]);
     let commit3 = graph_builder.commit_with_parents(&[&commit2]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Test with for_new_commit()
     let mut tx = repo.start_transaction(&settings, "test");
@@ -288,3 +350,215 @@ fn test_commit_builder_descendants(use_git: bool) {
     let mut rebaser = tx.mut_repo().create_descendant_rebaser(&settings);
     assert!(rebaser.rebase_next().unwrap().is_none());
 }
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_multiple_predecessors(use_git: bool) {
+    // Test that a squash commit can list all its inputs as predecessors, and
+    // that they're all replaced by it once recorded as rewritten.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit1 = graph_builder.initial_commit();
+    let commit2 = graph_builder.commit_with_parents(&[&commit1]);
+    let commit3 = graph_builder.commit_with_parents(&[&commit2]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let squashed = mut_repo
+        .rewrite_commit(&settings, &commit2)
+        .set_parents(commit1.parent_ids().to_vec())
+        .set_predecessors(vec![commit1.id().clone(), commit2.id().clone()])
+        .write()
+        .unwrap();
+    mut_repo.record_rewritten_commit(commit1.id().clone(), squashed.id().clone());
+    mut_repo.rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+
+    // The predecessors survive a round trip through the backend
+    let repo = testutils::load_repo_at_head(&settings, repo.repo_path());
+    let squashed = repo.store().get_commit(squashed.id()).unwrap();
+    assert_eq!(
+        squashed.predecessor_ids(),
+        [commit1.id().clone(), commit2.id().clone()]
+    );
+    let squashed_predecessors = squashed.predecessors();
+    assert_eq!(
+        squashed_predecessors,
+        vec![commit1.clone(), commit2.clone()]
+    );
+    // Both inputs are replaced by the squash commit
+    let heads = repo.view().heads().iter().cloned().collect_vec();
+    assert_eq!(heads.len(), 1);
+    let new_commit3 = repo.store().get_commit(&heads[0]).unwrap();
+    assert_eq!(new_commit3.predecessor_ids(), [commit3.id().clone()]);
+    assert_eq!(new_commit3.parent_ids(), [squashed.id().clone()]);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_commit_cache_coherent(use_git: bool) {
+    // Test that commits read back through the store's cache match what was
+    // written, including when writing an identical commit again and when
+    // writing a commit that differs only in change id.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let store = repo.store();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = mut_repo
+        .new_commit(
+            &settings,
+            vec![store.root_commit_id().clone()],
+            store.empty_tree_id().clone(),
+        )
+        .set_description("initial")
+        .write()
+        .unwrap();
+    assert_eq!(store.get_commit(commit1.id()).unwrap(), commit1);
+
+    // Writing the same data again results in the same commit
+    let rewritten = store.write_commit(commit1.store_commit().clone()).unwrap();
+    assert_eq!(rewritten.id(), commit1.id());
+    assert_eq!(rewritten.store_commit(), commit1.store_commit());
+    assert_eq!(
+        store.get_commit(commit1.id()).unwrap().store_commit(),
+        commit1.store_commit()
+    );
+
+    // Writing the same data with a different change id must not return the
+    // first commit's data
+    let mut data = commit1.store_commit().clone();
+    data.change_id = ChangeId::from_hex("abcdef");
+    let commit2 = store.write_commit(data).unwrap();
+    assert_ne!(commit2.id(), commit1.id());
+    assert_eq!(commit2.change_id(), &ChangeId::from_hex("abcdef"));
+    assert_eq!(
+        store.get_commit(commit2.id()).unwrap().change_id(),
+        &ChangeId::from_hex("abcdef")
+    );
+    assert_eq!(
+        store.get_commit(commit1.id()).unwrap().change_id(),
+        commit1.change_id()
+    );
+
+    // Rewriting creates a new commit and leaves the old one readable
+    let commit3 = mut_repo
+        .rewrite_commit(&settings, &commit1)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    assert_ne!(commit3.id(), commit1.id());
+    assert_eq!(
+        store.get_commit(commit3.id()).unwrap().description(),
+        "rewritten"
+    );
+    assert_eq!(
+        store.get_commit(commit1.id()).unwrap().description(),
+        "initial"
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_reproducible_commit_ids(use_git: bool) {
+    // Test that two repos with the same fixed commit timestamp (and randomness
+    // seed) produce identical commits, including when rewriting
+    let build = || {
+        let config = config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .set_override("debug.commit-timestamp", "2001-02-03T04:05:06+07:00")
+            .unwrap()
+            .build()
+            .unwrap();
+        let settings = UserSettings::from_config(config);
+        let test_repo = TestRepo::init(use_git);
+        let repo = &test_repo.repo;
+        let mut tx = repo.start_transaction(&settings, "test");
+        let mut_repo = tx.mut_repo();
+        let commit = mut_repo
+            .new_commit(
+                &settings,
+                vec![repo.store().root_commit_id().clone()],
+                repo.store().empty_tree_id().clone(),
+            )
+            .set_description("initial")
+            .write()
+            .unwrap();
+        let rewritten = mut_repo
+            .rewrite_commit(&settings, &commit)
+            .set_description("rewritten")
+            .write()
+            .unwrap();
+        (commit, rewritten)
+    };
+    let (commit1, rewritten1) = build();
+    let (commit2, rewritten2) = build();
+    assert_eq!(commit1.id(), commit2.id());
+    assert_eq!(rewritten1.id(), rewritten2.id());
+    assert_eq!(rewritten1.committer().timestamp, commit1.author().timestamp);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_set_both_timestamps(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let timestamp = Timestamp {
+        timestamp: MillisSinceEpoch(1_000_000_000_000),
+        tz_offset: 60,
+    };
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            repo.store().empty_tree_id().clone(),
+        )
+        .set_both_timestamps(timestamp.clone())
+        .write()
+        .unwrap();
+    assert_eq!(commit.author().timestamp, timestamp);
+    assert_eq!(commit.committer().timestamp, timestamp);
+}
+
+#[test]
+fn test_change_id_seed() {
+    // Test that seeding the RNG makes the generated change ids reproducible,
+    // and that different seeds give different ids
+    let settings_with_seed = |seed: &str| {
+        UserSettings::from_config(
+            config::Config::builder()
+                .set_override("debug.randomness-seed", seed)
+                .unwrap()
+                .build()
+                .unwrap(),
+        )
+    };
+    let generate = |settings: &UserSettings| {
+        let rng = settings.get_rng();
+        (0..3).map(|_| rng.new_change_id(16).hex()).collect_vec()
+    };
+    // The sequence for a given seed must not change between runs or versions,
+    // or tests that depend on specific change ids would break
+    let change_ids = generate(&settings_with_seed("42"));
+    assert_eq!(
+        change_ids,
+        vec![
+            "781199f9d55d18e855a7aa84c5e4b40d",
+            "a2c96fc88f32e487328f04927f20c4b1",
+            "4399e4f3123763dfe7d68a2809ecc01b",
+        ]
+    );
+    assert_eq!(generate(&settings_with_seed("42")), change_ids);
+    assert_ne!(generate(&settings_with_seed("43")), change_ids);
+}
@@ -56,7 +56,7 @@ fn test_commit_parallel(use_git: bool) {
             s.spawn(move || {
                 let mut tx = repo.start_transaction(&settings, "test");
                 write_random_commit(tx.mut_repo(), &settings);
-                tx.commit();
+                tx.commit().unwrap();
             });
         }
     });
@@ -87,7 +87,7 @@ fn test_commit_parallel_instances(use_git: bool) {
             s.spawn(move || {
                 let mut tx = repo.start_transaction(&settings, "test");
                 write_random_commit(tx.mut_repo(), &settings);
-                tx.commit();
+                tx.commit().unwrap();
             });
         }
     });
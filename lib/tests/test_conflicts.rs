@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use jj_lib::backend::{FileId, TreeValue};
-use jj_lib::conflicts::{parse_conflict, Conflict};
+use jj_lib::conflicts::{has_conflict_markers, parse_conflict, Conflict};
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
 use jj_lib::store::Store;
@@ -349,6 +349,120 @@ line 5 right
     "###);
 }
 
+#[test]
+fn test_materialize_conflict_with_labels() {
+    let test_repo = TestRepo::init(false);
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("file");
+    let base_id = testutils::write_file(
+        store,
+        &path,
+        "line 1
+line 2
+line 3
+line 4
+line 5
+",
+    );
+    let left_id = testutils::write_file(
+        store,
+        &path,
+        "line 1 left
+line 2 left
+line 3
+line 4
+line 5 left
+",
+    );
+    let right_id = testutils::write_file(
+        store,
+        &path,
+        "line 1 right
+line 2
+line 3
+line 4 right
+line 5 right
+",
+    );
+
+    let conflict = Conflict::new(
+        vec![Some(file_value(&base_id))],
+        vec![Some(file_value(&left_id)), Some(file_value(&right_id))],
+    );
+    let labels = vec!["main".to_string(), "feature".to_string()];
+    let mut result: Vec<u8> = vec![];
+    conflict
+        .materialize_with_labels(store, &path, &labels, &mut result)
+        .unwrap();
+    insta::assert_snapshot!(
+        String::from_utf8(result.clone()).unwrap(),
+        @r###"
+    <<<<<<<
+    +++++++ main
+    line 1 left
+    line 2 left
+    %%%%%%% feature
+    -line 1
+    +line 1 right
+     line 2
+    >>>>>>>
+    line 3
+    <<<<<<<
+    %%%%%%% main
+     line 4
+    -line 5
+    +line 5 left
+    +++++++ feature
+    line 4 right
+    line 5 right
+    >>>>>>>
+    "###
+    );
+
+    // The labels are ignored when parsing
+    let mut unlabeled: Vec<u8> = vec![];
+    conflict.materialize(store, &path, &mut unlabeled).unwrap();
+    assert_eq!(
+        parse_conflict(&result, 1, 2),
+        parse_conflict(&unlabeled, 1, 2)
+    );
+    assert_eq!(
+        conflict.update_from_content(store, &path, &result).unwrap(),
+        Some(conflict.clone())
+    );
+
+    // Missing labels result in bare markers
+    let mut result: Vec<u8> = vec![];
+    conflict
+        .materialize_with_labels(store, &path, &labels[..1], &mut result)
+        .unwrap();
+    insta::assert_snapshot!(
+        String::from_utf8(result).unwrap(),
+        @r###"
+    <<<<<<<
+    +++++++ main
+    line 1 left
+    line 2 left
+    %%%%%%%
+    -line 1
+    +line 1 right
+     line 2
+    >>>>>>>
+    line 3
+    <<<<<<<
+    %%%%%%% main
+     line 4
+    -line 5
+    +line 5 left
+    +++++++
+    line 4 right
+    line 5 right
+    >>>>>>>
+    "###
+    );
+}
+
 #[test]
 fn test_materialize_conflict_modify_delete() {
     let test_repo = TestRepo::init(false);
@@ -641,6 +755,60 @@ line 5
     )
 }
 
+#[test]
+fn test_has_conflict_markers() {
+    assert!(!has_conflict_markers(b""));
+    assert!(!has_conflict_markers(b"line 1\nline 2\n"));
+    // 2-sided
+    assert!(has_conflict_markers(
+        b"line 1
+<<<<<<<
+%%%%%%%
+-line 2
++left
++++++++
+right
+>>>>>>>
+line 3
+"
+    ));
+    // 3-sided
+    assert!(has_conflict_markers(
+        b"<<<<<<<
+%%%%%%%
+-line 2
++left
++++++++
+right
+%%%%%%%
+ line 2
++forward
+>>>>>>>
+"
+    ));
+    // Malformed markers, as in test_parse_conflict_malformed_marker()
+    assert!(!has_conflict_markers(
+        b"<<<<<<<
+ line 2
+-line 3
++left
++++++++
+right
+>>>>>>>
+"
+    ));
+    // Unterminated
+    assert!(!has_conflict_markers(
+        b"<<<<<<<
+%%%%%%%
+-line 2
++left
++++++++
+right
+"
+    ));
+}
+
 #[test]
 fn test_update_conflict_from_content() {
     let test_repo = TestRepo::init(false);
@@ -97,7 +97,7 @@ fn test_import_refs() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     let view = repo.view();
 
     let expected_heads = hashset! {
@@ -199,7 +199,7 @@ fn test_import_refs_reimport() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let expected_heads = hashset! {
             jj_id(&commit3),
@@ -223,12 +223,12 @@ fn test_import_refs_reimport() {
         "feature2".to_string(),
         RefTarget::Normal(commit6.id().clone()),
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let view = repo.view();
     let expected_heads = hashset! {
@@ -433,7 +433,7 @@ fn test_import_refs_reimport_with_deleted_remote_ref() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let expected_heads = hashset! {
             jj_id(&commit_main),
@@ -474,7 +474,7 @@ fn test_import_refs_reimport_with_deleted_remote_ref() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let view = repo.view();
     // The local branches were indeed deleted
@@ -522,7 +522,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let expected_heads = hashset! {
             jj_id(&commit_main),
@@ -573,7 +573,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let view = repo.view();
     assert_eq!(view.branches().len(), 3);
@@ -704,7 +704,7 @@ fn test_import_some_refs() {
     })
     .unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // There are two heads, feature2 and feature4.
     let view = repo.view();
@@ -769,7 +769,7 @@ fn test_import_some_refs() {
     })
     .unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // feature2 and feature4 will still be heads, and all four branches should be
     // present.
@@ -786,7 +786,7 @@ fn test_import_some_refs() {
     .unwrap();
     // No descendant should be rewritten.
     assert_eq!(tx.mut_repo().rebase_descendants(&settings).unwrap(), 0);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // feature2 and feature4 should still be the heads, and all three branches
     // feature2, feature3, and feature3 should exist.
@@ -803,7 +803,7 @@ fn test_import_some_refs() {
     .unwrap();
     // No descendant should be rewritten
     assert_eq!(tx.mut_repo().rebase_descendants(&settings).unwrap(), 0);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // feature2 and feature4 should still be the heads, and both branches
     // should exist.
@@ -819,7 +819,7 @@ fn test_import_some_refs() {
     .unwrap();
     // No descendant should be rewritten
     assert_eq!(tx.mut_repo().rebase_descendants(&settings).unwrap(), 0);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // feature2 should now be the only head and only branch.
     let view = repo.view();
@@ -894,7 +894,7 @@ fn test_import_refs_empty_git_repo() {
     tx.mut_repo()
         .rebase_descendants(&test_data.settings)
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert_eq!(*repo.view().heads(), heads_before);
     assert_eq!(repo.view().branches().len(), 0);
     assert_eq!(repo.view().tags().len(), 0);
@@ -924,7 +924,7 @@ fn test_import_refs_detached_head() {
     tx.mut_repo()
         .rebase_descendants(&test_data.settings)
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let expected_heads = hashset! { jj_id(&commit1) };
     assert_eq!(*repo.view().heads(), expected_heads);
@@ -1503,7 +1503,7 @@ fn test_fetch_initial_commit() {
     .unwrap();
     // No default branch because the origin repo's HEAD wasn't set
     assert_eq!(default_branch, None);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     // The initial commit is visible after git::fetch().
     let view = repo.view();
     assert!(view.heads().contains(&jj_id(&initial_git_commit)));
@@ -1543,7 +1543,7 @@ fn test_fetch_success() {
         &git_settings,
     )
     .unwrap();
-    test_data.repo = tx.commit();
+    test_data.repo = tx.commit().unwrap();
 
     test_data.origin_repo.set_head("refs/heads/main").unwrap();
     let new_git_commit = empty_git_commit(
@@ -1566,7 +1566,7 @@ fn test_fetch_success() {
     .unwrap();
     // The default branch is "main"
     assert_eq!(default_branch, Some("main".to_string()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     // The new commit is visible after we fetch again
     let view = repo.view();
     assert!(view.heads().contains(&jj_id(&new_git_commit)));
@@ -1725,7 +1725,7 @@ fn set_up_push_repos(settings: &UserSettings, temp_dir: &TempDir) -> PushTestSet
         .set_parents(vec![jj_id(&initial_git_commit)])
         .write()
         .unwrap();
-    let jj_repo = tx.commit();
+    let jj_repo = tx.commit().unwrap();
     PushTestSetup {
         source_repo_dir,
         jj_repo,
@@ -1848,7 +1848,7 @@ fn test_push_updates_not_fast_forward() {
     let mut setup = set_up_push_repos(&settings, &temp_dir);
     let mut tx = setup.jj_repo.start_transaction(&settings, "test");
     let new_commit = write_random_commit(tx.mut_repo(), &settings);
-    setup.jj_repo = tx.commit();
+    setup.jj_repo = tx.commit().unwrap();
     let result = git::push_updates(
         &get_git_repo(&setup.jj_repo),
         "origin",
@@ -1869,7 +1869,7 @@ fn test_push_updates_not_fast_forward_with_force() {
     let mut setup = set_up_push_repos(&settings, &temp_dir);
     let mut tx = setup.jj_repo.start_transaction(&settings, "test");
     let new_commit = write_random_commit(tx.mut_repo(), &settings);
-    setup.jj_repo = tx.commit();
+    setup.jj_repo = tx.commit().unwrap();
     let result = git::push_updates(
         &get_git_repo(&setup.jj_repo),
         "origin",
@@ -1947,7 +1947,7 @@ fn test_bulk_update_extra_on_import_refs() {
         let mut tx = repo.start_transaction(&settings, "test");
         git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
         tx.mut_repo().rebase_descendants(&settings).unwrap();
-        tx.commit()
+        tx.commit().unwrap()
     };
 
     // Extra metadata table shouldn't be created per read_commit() call. The number
@@ -1986,7 +1986,7 @@ fn test_rewrite_imported_commit() {
     let mut tx = repo.start_transaction(&settings, "test");
     git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     let imported_commit = repo.store().get_commit(&jj_id(&git_commit)).unwrap();
 
     // Try to create identical commit with different change id.
@@ -2003,7 +2003,7 @@ fn test_rewrite_imported_commit() {
         .set_description(imported_commit.description())
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Imported commit shouldn't be reused, and the timestamp of the authored
     // commit should be adjusted to create new commit.
@@ -2047,7 +2047,7 @@ fn test_concurrent_write_commit() {
                     .set_description("racy commit")
                     .write()
                     .unwrap();
-                tx.commit();
+                tx.commit().unwrap();
                 sender
                     .send((commit.id().clone(), commit.change_id().clone()))
                     .unwrap();
@@ -2132,7 +2132,7 @@ fn test_concurrent_read_write_commit() {
                     .set_description(format!("commit {i}"))
                     .write()
                     .unwrap();
-                tx.commit();
+                tx.commit().unwrap();
                 assert_eq!(commit.id(), commit_id);
             });
         }
@@ -2163,7 +2163,7 @@ fn test_concurrent_read_write_commit() {
                         })
                         .collect_vec();
                     if tx.mut_repo().has_changes() {
-                        tx.commit();
+                        tx.commit().unwrap();
                     }
                     thread::yield_now();
                 }
@@ -94,7 +94,7 @@ fn test_index_commits_standard_cases(use_git: bool) {
     let commit_f = graph_builder.commit_with_parents(&[&commit_b, &commit_e]);
     let commit_g = graph_builder.commit_with_parents(&[&commit_f]);
     let commit_h = graph_builder.commit_with_parents(&[&commit_e]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let index = as_readonly_composite(&repo);
     // There should be the root commit, plus 8 more
@@ -154,7 +154,7 @@ fn test_index_commits_criss_cross(use_git: bool) {
         left_commits.push(new_left);
         right_commits.push(new_right);
     }
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let index = as_readonly_composite(&repo);
     // There should the root commit, plus 2 for each generation
@@ -285,11 +285,11 @@ fn test_index_commits_previous_operations(use_git: bool) {
     let commit_a = graph_builder.initial_commit();
     let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
     let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     tx.mut_repo().remove_head(commit_c.id());
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Delete index from disk
     let index_operations_dir = repo.repo_path().join("index").join("operations");
@@ -333,7 +333,7 @@ fn test_index_commits_incremental(use_git: bool) {
     let commit_a = child_commit(tx.mut_repo(), &settings, &root_commit)
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let index = as_readonly_composite(&repo);
     // There should be the root commit, plus 1 more
@@ -346,7 +346,7 @@ fn test_index_commits_incremental(use_git: bool) {
     let commit_c = child_commit(tx.mut_repo(), &settings, &commit_b)
         .write()
         .unwrap();
-    tx.commit();
+    tx.commit().unwrap();
 
     let repo = load_repo_at_head(&settings, repo.repo_path());
     let index = as_readonly_composite(&repo);
@@ -385,13 +385,13 @@ fn test_index_commits_incremental_empty_transaction(use_git: bool) {
     let commit_a = child_commit(tx.mut_repo(), &settings, &root_commit)
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let index = as_readonly_composite(&repo);
     // There should be the root commit, plus 1 more
     assert_eq!(index.num_commits(), 1 + 1);
 
-    repo.start_transaction(&settings, "test").commit();
+    repo.start_transaction(&settings, "test").commit().unwrap();
 
     let repo = load_repo_at_head(&settings, repo.repo_path());
     let index = as_readonly_composite(&repo);
@@ -428,7 +428,7 @@ fn test_index_commits_incremental_already_indexed(use_git: bool) {
     let commit_a = child_commit(tx.mut_repo(), &settings, &root_commit)
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     assert!(repo.index().has_id(commit_a.id()));
     assert_eq!(as_readonly_composite(&repo).num_commits(), 1 + 1);
@@ -448,7 +448,7 @@ fn create_n_commits(
     for _ in 0..num_commits {
         write_random_commit(tx.mut_repo(), settings);
     }
-    tx.commit()
+    tx.commit().unwrap()
 }
 
 fn as_readonly_wrapper(repo: &Arc<ReadonlyRepo>) -> &ReadonlyIndexWrapper {
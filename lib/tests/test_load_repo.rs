@@ -12,7 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use jj_lib::repo::{RepoLoader, StoreFactories};
+use std::any::Any;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use jj_lib::backend::TreeValue;
+use jj_lib::default_index_store::DefaultIndexStore;
+use jj_lib::index::{IndexStore, IndexWriteError, MutableIndex, ReadonlyIndex};
+use jj_lib::op_store::OperationId;
+use jj_lib::operation::Operation;
+use jj_lib::repo::{Repo, RepoLoader, StoreFactories};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::store::Store;
 use test_case::test_case;
 use testutils::{write_random_commit, TestRepo};
 
@@ -25,11 +36,11 @@ fn test_load_at_operation(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "add commit");
     let commit = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "remove commit");
     tx.mut_repo().remove_head(commit.id());
-    tx.commit();
+    tx.commit().unwrap();
 
     // If we load the repo at head, we should not see the commit since it was
     // removed
@@ -43,3 +54,87 @@ fn test_load_at_operation(use_git: bool) {
     let old_repo = loader.load_at(repo.operation());
     assert!(old_repo.view().heads().contains(commit.id()));
 }
+
+/// Index store that counts how many times an index has been loaded.
+#[derive(Debug)]
+struct CountingIndexStore {
+    inner: DefaultIndexStore,
+    loads: Arc<AtomicUsize>,
+}
+
+impl IndexStore for CountingIndexStore {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn get_index_at_op(&self, op: &Operation, store: &Arc<Store>) -> Box<dyn ReadonlyIndex> {
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        self.inner.get_index_at_op(op, store)
+    }
+
+    fn write_index(
+        &self,
+        index: Box<dyn MutableIndex>,
+        op_id: &OperationId,
+    ) -> Result<Box<dyn ReadonlyIndex>, IndexWriteError> {
+        self.inner.write_index(index, op_id)
+    }
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_load_is_lazy(use_git: bool) {
+    // Test that loading a repo doesn't load the index until a query needs it, so
+    // commands that only read files stay cheap.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let tree = testutils::create_tree(repo, &[(&file_path, "contents")]);
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut store_factories = StoreFactories::default();
+    store_factories.add_index_store("default", {
+        let loads = loads.clone();
+        Box::new(move |store_path| {
+            Box::new(CountingIndexStore {
+                inner: DefaultIndexStore::load(store_path),
+                loads: loads.clone(),
+            })
+        })
+    });
+    let loader = RepoLoader::init(&settings, repo.repo_path(), &store_factories).unwrap();
+    let repo = loader.load_at_head(&settings).unwrap();
+
+    let commit = repo.store().get_commit(commit.id()).unwrap();
+    let file_id = match commit.tree().path_value(&file_path) {
+        Some(TreeValue::File { id, .. }) => id,
+        value => panic!("unexpected value {value:?}"),
+    };
+    assert_eq!(
+        testutils::read_file(repo.store(), &file_path, &file_id),
+        b"contents"
+    );
+    assert_eq!(loads.load(Ordering::SeqCst), 0);
+
+    // The index is loaded once on first use
+    assert!(repo.index().has_id(commit.id()));
+    assert!(repo.is_head(commit.id()));
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+}
@@ -15,10 +15,12 @@
 use assert_matches::assert_matches;
 use itertools::Itertools;
 use jj_lib::backend::TreeValue;
+use jj_lib::files::{MergeOptions, WhitespaceHandling};
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::{RepoPath, RepoPathComponent};
 use jj_lib::rewrite::rebase_commit;
-use jj_lib::tree::{merge_trees, Tree};
+use jj_lib::settings::UserSettings;
+use jj_lib::tree::{merge_trees, merge_trees_with_options, Tree, TreeMergeError};
 use test_case::test_case;
 use testutils::TestRepo;
 
@@ -625,7 +627,7 @@ fn test_simplify_conflict_after_resolving_parent(use_git: bool) {
         .unwrap();
     let commit_c3 = rebase_commit(&settings, tx.mut_repo(), &commit_c2, &[commit_b3]).unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // The conflict should now be resolved.
     let resolved_value = commit_c3.tree().path_value(&path);
@@ -647,3 +649,127 @@ fn test_simplify_conflict_after_resolving_parent(use_git: bool) {
 
 // TODO: Add tests for simplification of multi-way conflicts. Both the content
 // and the executable bit need testing.
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_identical_conflicts_share_id(use_git: bool) {
+    // Test that the same conflict produced by merging different trees is stored
+    // once, with a single conflict id
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string("dir/file");
+    let other_path = RepoPath::from_internal_string("other");
+    let merge = |other_content: &str| {
+        let base = testutils::create_tree(repo, &[(&path, "base"), (&other_path, other_content)]);
+        let side1 = testutils::create_tree(repo, &[(&path, "side1"), (&other_path, other_content)]);
+        let side2 = testutils::create_tree(repo, &[(&path, "side2"), (&other_path, other_content)]);
+        merge_trees(&side1, &base, &side2).unwrap()
+    };
+    let merged_tree1 = merge("1");
+    let merged_tree2 = merge("2");
+    assert_ne!(merged_tree1.id(), merged_tree2.id());
+    let conflict_id1 = merged_tree1.conflict_id_at(&path).unwrap();
+    let conflict_id2 = merged_tree2.conflict_id_at(&path).unwrap();
+    assert_eq!(conflict_id1, conflict_id2);
+    assert_eq!(
+        merged_tree1.path_value(&path),
+        Some(TreeValue::Conflict(conflict_id1))
+    );
+    assert_eq!(merged_tree1.conflict_id_at(&other_path), None);
+    assert_eq!(
+        merged_tree1.conflict_id_at(&RepoPath::from_internal_string("missing")),
+        None
+    );
+}
+
+#[test_case(WhitespaceHandling::Strict ; "strict")]
+#[test_case(WhitespaceHandling::IgnoreTrailing ; "ignore trailing")]
+fn test_merge_trailing_whitespace(whitespace: WhitespaceHandling) {
+    // Test that a file where one side only adds trailing whitespace to a line
+    // the other side changes is resolved when trailing whitespace is ignored
+    let test_repo = TestRepo::init(false);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string("file");
+    let base = testutils::create_tree(repo, &[(&path, "a\nb\n")]);
+    let side1 = testutils::create_tree(repo, &[(&path, "a \nb\n")]);
+    let side2 = testutils::create_tree(repo, &[(&path, "A\nb\n")]);
+    let options = MergeOptions {
+        whitespace,
+        ..Default::default()
+    };
+    let merged_tree = merge_trees_with_options(&side1, &base, &side2, &options).unwrap();
+    match whitespace {
+        WhitespaceHandling::Strict => {
+            assert_matches!(merged_tree.path_value(&path), Some(TreeValue::Conflict(_)));
+        }
+        _ => {
+            let expected = testutils::create_tree(repo, &[(&path, "A\nb\n")]);
+            assert_eq!(merged_tree.id(), expected.id());
+        }
+    }
+}
+
+#[test]
+fn test_rebase_with_merge_whitespace_setting() {
+    // Test that rebasing merges file contents according to `merge.whitespace`
+    let settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .set_override("merge.whitespace", "ignore-trailing")
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let test_repo = TestRepo::init(false);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string("file");
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut new_commit = |parent_id, content| {
+        let tree = testutils::create_tree(repo, &[(&path, content)]);
+        tx.mut_repo()
+            .new_commit(&settings, vec![parent_id], tree.id().clone())
+            .write()
+            .unwrap()
+    };
+    let commit_a = new_commit(repo.store().root_commit_id().clone(), "a\nb\n");
+    let commit_b = new_commit(commit_a.id().clone(), "a \nb\n");
+    let commit_c = new_commit(commit_a.id().clone(), "A\nb\n");
+    let commit_b2 = rebase_commit(&settings, tx.mut_repo(), &commit_b, &[commit_c]).unwrap();
+    let expected = testutils::create_tree(repo, &[(&path, "A\nb\n")]);
+    assert_eq!(commit_b2.tree_id(), expected.id());
+}
+
+#[test]
+fn test_rebase_with_invalid_merge_whitespace_setting() {
+    // Test that an invalid `merge.whitespace` is reported instead of ignored
+    let settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .set_override("merge.whitespace", "bogus")
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let test_repo = TestRepo::init(false);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string("file");
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut new_commit = |parent_id, content| {
+        let tree = testutils::create_tree(repo, &[(&path, content)]);
+        tx.mut_repo()
+            .new_commit(&settings, vec![parent_id], tree.id().clone())
+            .write()
+            .unwrap()
+    };
+    let commit_a = new_commit(repo.store().root_commit_id().clone(), "a\nb\n");
+    let commit_b = new_commit(commit_a.id().clone(), "a \nb\n");
+    let commit_c = new_commit(commit_a.id().clone(), "A\nb\n");
+    assert_matches!(
+        rebase_commit(&settings, tx.mut_repo(), &commit_b, &[commit_c]),
+        Err(TreeMergeError::Config(_))
+    );
+}
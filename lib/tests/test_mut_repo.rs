@@ -12,12 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use assert_matches::assert_matches;
+use itertools::Itertools;
+use jj_lib::backend::{CommitId, ObjectId, TreeValue};
+use jj_lib::matchers::EverythingMatcher;
 use jj_lib::op_store::{RefTarget, WorkspaceId};
-use jj_lib::repo::Repo;
+use jj_lib::repo::{
+    AddParentError, AmendCheckoutError, BranchError, CheckOutCommitError, CreateMergeError,
+    DivergenceError, PruneCommitError, RebasePlanError, Repo, SquashError,
+};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::rewrite::RebasePlan;
+use jj_lib::settings::UserSettings;
 use maplit::hashset;
 use test_case::test_case;
 use testutils::{
-    assert_rebased, create_random_commit, write_random_commit, CommitGraphBuilder, TestRepo,
+    assert_rebased, create_random_commit, create_tree, write_random_commit, CommitGraphBuilder,
+    TestRepo,
 };
 
 #[test_case(false ; "local backend")]
@@ -30,12 +41,12 @@ fn test_edit(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "test");
     let wc_commit = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let ws_id = WorkspaceId::default();
     tx.mut_repo().edit(ws_id.clone(), &wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert_eq!(repo.view().get_wc_commit_id(&ws_id), Some(wc_commit.id()));
 }
 
@@ -49,7 +60,7 @@ fn test_checkout(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "test");
     let wc_commit_parent = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let ws_id = WorkspaceId::default();
@@ -60,10 +71,72 @@ fn test_checkout(use_git: bool) {
     assert_eq!(wc_commit.tree_id(), wc_commit_parent.tree_id());
     assert_eq!(wc_commit.parents().len(), 1);
     assert_eq!(wc_commit.parents()[0].id(), wc_commit_parent.id());
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert_eq!(repo.view().get_wc_commit_id(&ws_id), Some(wc_commit.id()));
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_checkout_policy(use_git: bool) {
+    // Test that MutableRepo::check_out() creates a child or a sibling depending on
+    // the checkout policy
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit1 = graph_builder.initial_commit();
+    let commit2 = graph_builder.commit_with_parents(&[&commit1]);
+    let repo = tx.commit().unwrap();
+
+    let settings_with_policy = |policy: &str| {
+        let config = config::Config::builder()
+            .add_source(settings.config().clone())
+            .set_override("ui.checkout-policy", policy)
+            .unwrap()
+            .build()
+            .unwrap();
+        UserSettings::from_config(config)
+    };
+    let ws_id = WorkspaceId::default();
+
+    let child_settings = settings_with_policy("child");
+    let mut tx = repo.start_transaction(&settings, "test");
+    let wc_commit = tx
+        .mut_repo()
+        .check_out(ws_id.clone(), &child_settings, &commit2)
+        .unwrap();
+    assert_eq!(wc_commit.parent_ids(), &[commit2.id().clone()]);
+    assert_eq!(wc_commit.tree_id(), commit2.tree_id());
+
+    let sibling_settings = settings_with_policy("sibling");
+    let mut tx = repo.start_transaction(&settings, "test");
+    let wc_commit = tx
+        .mut_repo()
+        .check_out(ws_id.clone(), &sibling_settings, &commit2)
+        .unwrap();
+    assert_eq!(wc_commit.parent_ids(), &[commit1.id().clone()]);
+    assert_eq!(wc_commit.tree_id(), commit2.tree_id());
+    assert!(tx.mut_repo().view().heads().contains(commit2.id()));
+
+    // The root commit has no parents, so we create a child of it
+    let root_commit = repo.store().root_commit();
+    let mut tx = repo.start_transaction(&settings, "test");
+    let wc_commit = tx
+        .mut_repo()
+        .check_out(ws_id.clone(), &sibling_settings, &root_commit)
+        .unwrap();
+    assert_eq!(wc_commit.parent_ids(), &[root_commit.id().clone()]);
+
+    let invalid_settings = settings_with_policy("bad");
+    let mut tx = repo.start_transaction(&settings, "test");
+    assert_matches!(
+        tx.mut_repo().check_out(ws_id, &invalid_settings, &commit2),
+        Err(CheckOutCommitError::Config(_))
+    );
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_checkout_previous_not_empty(use_git: bool) {
@@ -78,7 +151,7 @@ fn test_checkout_previous_not_empty(use_git: bool) {
     let old_wc_commit = write_random_commit(mut_repo, &settings);
     let ws_id = WorkspaceId::default();
     mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -109,7 +182,7 @@ fn test_checkout_previous_empty(use_git: bool) {
         .unwrap();
     let ws_id = WorkspaceId::default();
     mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -141,7 +214,7 @@ fn test_checkout_previous_empty_with_description(use_git: bool) {
         .unwrap();
     let ws_id = WorkspaceId::default();
     mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -176,7 +249,7 @@ fn test_checkout_previous_empty_with_local_branch(use_git: bool) {
     );
     let ws_id = WorkspaceId::default();
     mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -215,7 +288,7 @@ fn test_checkout_previous_empty_non_head(use_git: bool) {
         .unwrap();
     let ws_id = WorkspaceId::default();
     mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -239,20 +312,65 @@ fn test_edit_initial(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "test");
     let wc_commit = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let workspace_id = WorkspaceId::new("new-workspace".to_string());
     tx.mut_repo()
         .edit(workspace_id.clone(), &wc_commit)
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert_eq!(
         repo.view().get_wc_commit_id(&workspace_id),
         Some(wc_commit.id())
     );
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_set_working_copy_commit(use_git: bool) {
+    // Test that MutableRepo::set_working_copy_commit() only records the
+    // working-copy commit: it doesn't create a new commit or abandon the previous
+    // empty working-copy commit, and it makes a hidden commit visible
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let old_wc_commit = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            repo.store().empty_tree_id().clone(),
+        )
+        .write()
+        .unwrap();
+    let ws_id = WorkspaceId::default();
+    mut_repo.edit(ws_id.clone(), &old_wc_commit).unwrap();
+    let repo = tx.commit().unwrap();
+
+    // Create a commit that isn't visible
+    let mut tx = repo.start_transaction(&settings, "test");
+    let new_wc_commit = write_random_commit(tx.mut_repo(), &settings);
+    drop(tx);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo()
+        .set_working_copy_commit(ws_id.clone(), &new_wc_commit)
+        .unwrap();
+    assert!(!tx.mut_repo().has_rewrites());
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.view().get_wc_commit_id(&ws_id),
+        Some(new_wc_commit.id())
+    );
+    assert_eq!(
+        *repo.view().heads(),
+        hashset! {old_wc_commit.id().clone(), new_wc_commit.id().clone()}
+    );
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_add_head_success(use_git: bool) {
@@ -275,7 +393,7 @@ fn test_add_head_success(use_git: bool) {
     mut_repo.add_head(&new_commit);
     assert!(mut_repo.view().heads().contains(new_commit.id()));
     assert!(mut_repo.index().has_id(new_commit.id()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert!(repo.view().heads().contains(new_commit.id()));
     assert!(repo.index().has_id(new_commit.id()));
 }
@@ -294,7 +412,7 @@ fn test_add_head_ancestor(use_git: bool) {
     let commit1 = graph_builder.initial_commit();
     let commit2 = graph_builder.commit_with_parents(&[&commit1]);
     let commit3 = graph_builder.commit_with_parents(&[&commit2]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     assert_eq!(repo.view().heads(), &hashset! {commit3.id().clone()});
     let mut tx = repo.start_transaction(&settings, "test");
@@ -303,6 +421,63 @@ fn test_add_head_ancestor(use_git: bool) {
     assert_eq!(repo.view().heads(), &hashset! {commit3.id().clone()});
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_add_head_merge(use_git: bool) {
+    // Test that MutableRepo::add_head() removes the parents of a merge commit from
+    // the heads, both when all parents are heads and when only some are.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // D
+    // | C
+    // |/
+    // | B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.initial_commit();
+    let commit_c = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.view().heads(),
+        &hashset! {commit_b.id().clone(), commit_c.id().clone(), commit_d.id().clone()}
+    );
+
+    // Create merge commits outside of the repo by using a temporary transaction
+    let mut tx = repo.start_transaction(&settings, "test");
+    let merge_heads = create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit_b.id().clone(), commit_c.id().clone()])
+        .write()
+        .unwrap();
+    let merge_with_non_head = create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit_a.id().clone(), commit_d.id().clone()])
+        .write()
+        .unwrap();
+    drop(tx);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    mut_repo.add_head(&merge_heads);
+    assert_eq!(
+        mut_repo.view().heads(),
+        &hashset! {merge_heads.id().clone(), commit_d.id().clone()}
+    );
+    mut_repo.add_head(&merge_with_non_head);
+    assert_eq!(
+        mut_repo.view().heads(),
+        &hashset! {merge_heads.id().clone(), merge_with_non_head.id().clone()}
+    );
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.view().heads(),
+        &hashset! {merge_heads.id().clone(), merge_with_non_head.id().clone()}
+    );
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_add_head_not_immediate_child(use_git: bool) {
@@ -314,7 +489,7 @@ fn test_add_head_not_immediate_child(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "test");
     let initial = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Create some commits outside of the repo by using a temporary transaction.
     // Then add one of them as a head.
@@ -358,7 +533,7 @@ fn test_remove_head(use_git: bool) {
     let commit1 = graph_builder.initial_commit();
     let commit2 = graph_builder.commit_with_parents(&[&commit1]);
     let commit3 = graph_builder.commit_with_parents(&[&commit2]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -371,7 +546,7 @@ fn test_remove_head(use_git: bool) {
     assert!(mut_repo.index().has_id(commit1.id()));
     assert!(mut_repo.index().has_id(commit2.id()));
     assert!(mut_repo.index().has_id(commit3.id()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     let heads = repo.view().heads().clone();
     assert!(!heads.contains(commit3.id()));
     assert!(!heads.contains(commit2.id()));
@@ -381,6 +556,76 @@ fn test_remove_head(use_git: bool) {
     assert!(repo.index().has_id(commit3.id()));
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_is_head(use_git: bool) {
+    // Test that Repo::is_head() reflects heads added and removed in a transaction
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit1 = graph_builder.initial_commit();
+    let commit2 = graph_builder.commit_with_parents(&[&commit1]);
+    let repo = tx.commit().unwrap();
+    assert!(repo.is_head(commit2.id()));
+    assert!(!repo.is_head(commit1.id()));
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    mut_repo.remove_head(commit2.id());
+    assert!(!mut_repo.is_head(commit2.id()));
+    mut_repo.add_head(&commit2);
+    assert!(mut_repo.is_head(commit2.id()));
+    assert!(!mut_repo.is_head(commit1.id()));
+    mut_repo.prune_commit(&commit2).unwrap();
+    assert!(!mut_repo.is_head(commit2.id()));
+    assert!(mut_repo.is_head(commit1.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_prune_commit(use_git: bool) {
+    // Test that MutableRepo::prune_commit() replaces a pruned head by its parent,
+    // and that it refuses to prune non-heads and checked-out commits.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit1 = graph_builder.initial_commit();
+    let commit2 = graph_builder.commit_with_parents(&[&commit1]);
+    let commit3 = graph_builder.commit_with_parents(&[&commit2]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert_matches!(
+        mut_repo.prune_commit(&commit2),
+        Err(PruneCommitError::NotAHead(id)) if id == *commit2.id()
+    );
+    assert_eq!(*mut_repo.view().heads(), hashset! {commit3.id().clone()});
+    let ws_id = WorkspaceId::default();
+    mut_repo
+        .set_wc_commit(ws_id.clone(), commit3.id().clone())
+        .unwrap();
+    assert_matches!(
+        mut_repo.prune_commit(&commit3),
+        Err(PruneCommitError::CheckedOut(id, workspace_id))
+            if id == *commit3.id() && workspace_id == ws_id
+    );
+    assert_eq!(*mut_repo.view().heads(), hashset! {commit3.id().clone()});
+    mut_repo.remove_wc_commit(&ws_id);
+    mut_repo.prune_commit(&commit3).unwrap();
+    assert_eq!(*mut_repo.view().heads(), hashset! {commit2.id().clone()});
+    assert!(!mut_repo.has_rewrites());
+    let repo = tx.commit().unwrap();
+    assert_eq!(*repo.view().heads(), hashset! {commit2.id().clone()});
+    assert!(repo.index().has_id(commit3.id()));
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_add_public_head(use_git: bool) {
@@ -392,14 +637,14 @@ fn test_add_public_head(use_git: bool) {
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit1 = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
     assert!(!mut_repo.view().public_heads().contains(commit1.id()));
     mut_repo.add_public_head(&commit1);
     assert!(mut_repo.view().public_heads().contains(commit1.id()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert!(repo.view().public_heads().contains(commit1.id()));
 }
 
@@ -417,14 +662,14 @@ fn test_add_public_head_ancestor(use_git: bool) {
     let commit1 = graph_builder.initial_commit();
     let commit2 = graph_builder.commit_with_parents(&[&commit1]);
     tx.mut_repo().add_public_head(&commit2);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
     assert!(!mut_repo.view().public_heads().contains(commit1.id()));
     mut_repo.add_public_head(&commit1);
     assert!(!mut_repo.view().public_heads().contains(commit1.id()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert!(!repo.view().public_heads().contains(commit1.id()));
 }
 
@@ -441,14 +686,14 @@ fn test_remove_public_head(use_git: bool) {
     let mut_repo = tx.mut_repo();
     let commit1 = write_random_commit(mut_repo, &settings);
     mut_repo.add_public_head(&commit1);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
     assert!(mut_repo.view().public_heads().contains(commit1.id()));
     mut_repo.remove_public_head(commit1.id());
     assert!(!mut_repo.view().public_heads().contains(commit1.id()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     assert!(!repo.view().public_heads().contains(commit1.id()));
 }
 
@@ -478,7 +723,7 @@ fn test_has_changed(use_git: bool) {
         "origin".to_string(),
         RefTarget::Normal(commit1.id().clone()),
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
     // Test the setup
     assert_eq!(repo.view().heads(), &hashset! {commit1.id().clone()});
     assert_eq!(repo.view().public_heads(), &hashset! {commit1.id().clone()});
@@ -563,7 +808,7 @@ fn test_rebase_descendants_simple(use_git: bool) {
     let commit3 = graph_builder.commit_with_parents(&[&commit2]);
     let commit4 = graph_builder.commit_with_parents(&[&commit1]);
     let commit5 = graph_builder.commit_with_parents(&[&commit4]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -599,7 +844,7 @@ fn test_rebase_descendants_conflicting_rewrite(use_git: bool) {
     let commit1 = graph_builder.initial_commit();
     let commit2 = graph_builder.commit_with_parents(&[&commit1]);
     let _commit3 = graph_builder.commit_with_parents(&[&commit2]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let mut_repo = tx.mut_repo();
@@ -635,3 +880,1040 @@ fn test_rename_remote(use_git: bool) {
     assert_eq!(mut_repo.get_remote_branch("main", "upstream"), Some(target));
     assert_eq!(mut_repo.get_remote_branch("main", "origin"), None);
 }
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_set_branches(use_git: bool) {
+    // Test that MutableRepo::set_branches() applies all updates, and that an
+    // update on top of an earlier change in the transaction is applied to it
+    // instead of creating a conflict.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = write_random_commit(mut_repo, &settings);
+    let commit3 = write_random_commit(mut_repo, &settings);
+    mut_repo.set_local_branch("moved".to_string(), RefTarget::Normal(commit1.id().clone()));
+    mut_repo.set_local_branch(
+        "deleted".to_string(),
+        RefTarget::Normal(commit1.id().clone()),
+    );
+    mut_repo.set_local_branch(
+        "updated".to_string(),
+        RefTarget::Normal(commit1.id().clone()),
+    );
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    mut_repo.set_local_branch(
+        "updated".to_string(),
+        RefTarget::Normal(commit2.id().clone()),
+    );
+    mut_repo.set_branches(vec![
+        (
+            "added1".to_string(),
+            Some(RefTarget::Normal(commit1.id().clone())),
+        ),
+        (
+            "added2".to_string(),
+            Some(RefTarget::Normal(commit2.id().clone())),
+        ),
+        (
+            "moved".to_string(),
+            Some(RefTarget::Normal(commit2.id().clone())),
+        ),
+        ("deleted".to_string(), None),
+        (
+            "updated".to_string(),
+            Some(RefTarget::Normal(commit3.id().clone())),
+        ),
+    ]);
+    assert_eq!(
+        mut_repo.get_local_branch("added1"),
+        Some(RefTarget::Normal(commit1.id().clone()))
+    );
+    assert_eq!(
+        mut_repo.get_local_branch("added2"),
+        Some(RefTarget::Normal(commit2.id().clone()))
+    );
+    assert_eq!(
+        mut_repo.get_local_branch("moved"),
+        Some(RefTarget::Normal(commit2.id().clone()))
+    );
+    assert_eq!(mut_repo.get_local_branch("deleted"), None);
+    assert_eq!(
+        mut_repo.get_local_branch("updated"),
+        Some(RefTarget::Normal(commit3.id().clone()))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_restore_branch(use_git: bool) {
+    // Test that MutableRepo::restore_branch() brings back a deleted branch from an
+    // earlier operation, and errors out if the branch didn't exist there.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = write_random_commit(mut_repo, &settings);
+    mut_repo.set_local_branch("main".to_string(), RefTarget::Normal(commit1.id().clone()));
+    let repo = tx.commit().unwrap();
+    let old_op = repo.operation().clone();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().remove_local_branch("main");
+    let repo = tx.commit().unwrap();
+    assert_eq!(repo.view().get_local_branch("main"), None);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    mut_repo.restore_branch("main", &old_op).unwrap();
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        Some(RefTarget::Normal(commit1.id().clone()))
+    );
+    assert_matches!(
+        mut_repo.restore_branch("missing", &old_op),
+        Err(BranchError::NotFoundInOperation { name, operation_id })
+            if name == "missing" && operation_id == *old_op.id()
+    );
+    assert_eq!(mut_repo.get_local_branch("missing"), None);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_resolve_branch_conflict(use_git: bool) {
+    // Test that MutableRepo::resolve_branch_conflict() replaces a conflicted
+    // branch with one of its sides, or with any commit if forced.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = write_random_commit(mut_repo, &settings);
+    let commit3 = write_random_commit(mut_repo, &settings);
+    let commit4 = write_random_commit(mut_repo, &settings);
+    let conflict = RefTarget::Conflict {
+        removes: vec![commit1.id().clone()],
+        adds: vec![commit2.id().clone(), commit3.id().clone()],
+    };
+    mut_repo.set_local_branch("main".to_string(), conflict.clone());
+    mut_repo.set_local_branch(
+        "stable".to_string(),
+        RefTarget::Normal(commit1.id().clone()),
+    );
+
+    assert_matches!(
+        mut_repo.resolve_branch_conflict("main", commit4.id().clone(), false),
+        Err(BranchError::NotAConflictSide { name, commit_id })
+            if name == "main" && commit_id == *commit4.id()
+    );
+    assert_eq!(mut_repo.get_local_branch("main"), Some(conflict));
+    assert_matches!(
+        mut_repo.resolve_branch_conflict("stable", commit2.id().clone(), false),
+        Err(BranchError::NotConflicted { name }) if name == "stable"
+    );
+    assert_matches!(
+        mut_repo.resolve_branch_conflict("missing", commit2.id().clone(), false),
+        Err(BranchError::NotFound { name }) if name == "missing"
+    );
+
+    mut_repo
+        .resolve_branch_conflict("main", commit3.id().clone(), false)
+        .unwrap();
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        Some(RefTarget::Normal(commit3.id().clone()))
+    );
+    let repo = tx.commit().unwrap();
+    assert_eq!(repo.conflicted_branches(), vec![]);
+
+    // A commit outside the conflict can be chosen with `force`
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    mut_repo.set_local_branch(
+        "main".to_string(),
+        RefTarget::Conflict {
+            removes: vec![commit1.id().clone()],
+            adds: vec![commit2.id().clone(), commit3.id().clone()],
+        },
+    );
+    mut_repo
+        .resolve_branch_conflict("main", commit4.id().clone(), true)
+        .unwrap();
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        Some(RefTarget::Normal(commit4.id().clone()))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_create_merge(use_git: bool) {
+    // Test that MutableRepo::create_merge() records conflicts between the parents
+    // in the merged tree.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let conflict_path = RepoPath::from_internal_string("conflict");
+    let clean_path = RepoPath::from_internal_string("clean");
+    let base_tree = create_tree(repo, &[(&conflict_path, "base"), (&clean_path, "base")]);
+    let left_tree = create_tree(repo, &[(&conflict_path, "left"), (&clean_path, "left")]);
+    let right_tree = create_tree(repo, &[(&conflict_path, "right"), (&clean_path, "base")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let base = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            base_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let left = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], left_tree.id().clone())
+        .write()
+        .unwrap();
+    let right = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], right_tree.id().clone())
+        .write()
+        .unwrap();
+    let merge = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), right.id().clone()],
+            "merge",
+            false,
+        )
+        .unwrap();
+    assert_eq!(merge.parent_ids(), &[left.id().clone(), right.id().clone()]);
+    assert_eq!(merge.description(), "merge");
+    let merged_tree = merge.tree();
+    assert_eq!(
+        merged_tree
+            .conflicts()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect_vec(),
+        vec![conflict_path]
+    );
+    assert_eq!(
+        merged_tree.path_value(&clean_path),
+        left_tree.path_value(&clean_path)
+    );
+    assert_eq!(*mut_repo.view().heads(), hashset! {merge.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_create_merge_unrelated(use_git: bool) {
+    // Test that merging two children of the root commit uses the root commit (and
+    // its empty tree) as the merge base, so files added on both sides become
+    // add/add conflicts.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+
+    let path = RepoPath::from_internal_string("file");
+    let left_tree = create_tree(repo, &[(&path, "left")]);
+    let right_tree = create_tree(repo, &[(&path, "right")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let left = mut_repo
+        .new_commit(
+            &settings,
+            vec![root_commit.id().clone()],
+            left_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let right = mut_repo
+        .new_commit(
+            &settings,
+            vec![root_commit.id().clone()],
+            right_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    assert_eq!(
+        mut_repo
+            .index()
+            .common_ancestors(&[left.id().clone()], &[right.id().clone()]),
+        vec![root_commit.id().clone()]
+    );
+    assert_eq!(root_commit.tree_id(), repo.store().empty_tree_id());
+
+    let merge = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), right.id().clone()],
+            "merge",
+            false,
+        )
+        .unwrap();
+    let conflict_id = match merge.tree().path_value(&path) {
+        Some(TreeValue::Conflict(id)) => id,
+        value => panic!("unexpected value at {path:?}: {value:?}"),
+    };
+    let conflict = repo.store().read_conflict(&path, &conflict_id).unwrap();
+    assert_eq!(conflict.removes(), &[None]);
+    assert_eq!(
+        conflict.adds(),
+        &[left_tree.path_value(&path), right_tree.path_value(&path)]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_create_merge_octopus(use_git: bool) {
+    // Test that MutableRepo::create_merge() cleanly merges three branches that
+    // change different files.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let path1 = RepoPath::from_internal_string("file1");
+    let path2 = RepoPath::from_internal_string("file2");
+    let path3 = RepoPath::from_internal_string("file3");
+    let base_tree = create_tree(
+        repo,
+        &[(&path1, "base"), (&path2, "base"), (&path3, "base")],
+    );
+    let tree1 = create_tree(repo, &[(&path1, "1"), (&path2, "base"), (&path3, "base")]);
+    let tree2 = create_tree(repo, &[(&path1, "base"), (&path2, "2"), (&path3, "base")]);
+    let tree3 = create_tree(repo, &[(&path1, "base"), (&path2, "base"), (&path3, "3")]);
+    let expected_tree = create_tree(repo, &[(&path1, "1"), (&path2, "2"), (&path3, "3")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let base = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            base_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let parent_ids = [tree1, tree2, tree3]
+        .iter()
+        .map(|tree| {
+            mut_repo
+                .new_commit(&settings, vec![base.id().clone()], tree.id().clone())
+                .write()
+                .unwrap()
+                .id()
+                .clone()
+        })
+        .collect_vec();
+    let merge = mut_repo
+        .create_merge(&settings, &parent_ids, "octopus", true)
+        .unwrap();
+    assert_eq!(merge.parent_ids(), parent_ids);
+    assert_eq!(merge.tree_id(), expected_tree.id());
+    assert_eq!(*mut_repo.view().heads(), hashset! {merge.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_create_merge_strict(use_git: bool) {
+    // Test that MutableRepo::create_merge() refuses to create a conflicted merge
+    // when `strict` is set.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string("file");
+    let base_tree = create_tree(repo, &[(&path, "base")]);
+    let trees = [
+        create_tree(repo, &[(&path, "1")]),
+        create_tree(repo, &[(&path, "base")]),
+        create_tree(repo, &[(&path, "3")]),
+    ];
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let base = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            base_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let parent_ids = trees
+        .iter()
+        .map(|tree| {
+            mut_repo
+                .new_commit(&settings, vec![base.id().clone()], tree.id().clone())
+                .write()
+                .unwrap()
+                .id()
+                .clone()
+        })
+        .collect_vec();
+    assert_matches!(
+        mut_repo.create_merge(&settings, &parent_ids, "octopus", true),
+        Err(CreateMergeError::Conflicts(paths)) if paths == vec![path.clone()]
+    );
+    let merge = mut_repo
+        .create_merge(&settings, &parent_ids, "octopus", false)
+        .unwrap();
+    assert!(merge.tree().has_conflict());
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_squash_range(use_git: bool) {
+    // Test that MutableRepo::squash_range() collapses a linear run of commits
+    // into one and rebases descendants onto it.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // E
+    // D
+    // C F
+    // |/
+    // B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_d]);
+    let commit_f = graph_builder.commit_with_parents(&[&commit_b]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert_matches!(
+        mut_repo.squash_range(&settings, &[], "squashed"),
+        Err(SquashError::EmptyRange)
+    );
+    assert_matches!(
+        mut_repo.squash_range(
+            &settings,
+            &[commit_b.id().clone(), commit_d.id().clone()],
+            "squashed"
+        ),
+        Err(SquashError::NotLinear(id)) if id == *commit_d.id()
+    );
+    assert_matches!(
+        mut_repo.squash_range(
+            &settings,
+            &[repo.store().root_commit_id().clone(), commit_a.id().clone()],
+            "squashed"
+        ),
+        Err(SquashError::RootCommit)
+    );
+
+    let squashed = mut_repo
+        .squash_range(
+            &settings,
+            &[
+                commit_b.id().clone(),
+                commit_c.id().clone(),
+                commit_d.id().clone(),
+            ],
+            "squashed",
+        )
+        .unwrap();
+    assert_eq!(squashed.tree_id(), commit_d.tree_id());
+    assert_eq!(squashed.parent_ids(), [commit_a.id().clone()]);
+    assert_eq!(squashed.description(), "squashed");
+    assert_eq!(
+        squashed.predecessor_ids(),
+        [
+            commit_b.id().clone(),
+            commit_c.id().clone(),
+            commit_d.id().clone()
+        ]
+    );
+    let heads = mut_repo.view().heads().clone();
+    assert_eq!(heads.len(), 2);
+    for head_id in heads {
+        let head = repo.store().get_commit(&head_id).unwrap();
+        let old_head = if head.change_id() == commit_e.change_id() {
+            &commit_e
+        } else {
+            &commit_f
+        };
+        assert_eq!(head.parent_ids(), [squashed.id().clone()]);
+        assert_eq!(head.predecessor_ids(), [old_head.id().clone()]);
+    }
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_apply_rebase_plan(use_git: bool) {
+    // Test that MutableRepo::apply_rebase_plan() can reorder commits and drop
+    // one, rebasing the dropped commit's children onto its (rewritten) parent.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // E
+    // D
+    // C
+    // B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_d]);
+    let repo = tx.commit().unwrap();
+
+    // B
+    // | E
+    // |/
+    // C
+    // A
+    let plan = RebasePlan::new()
+        .pick(commit_c.id().clone(), vec![commit_a.id().clone()])
+        .pick(commit_b.id().clone(), vec![commit_c.id().clone()])
+        .drop_commit(commit_d.id().clone());
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let result = mut_repo.apply_rebase_plan(&settings, &plan).unwrap();
+    assert_eq!(result.abandoned, vec![commit_d.id().clone()]);
+    assert_eq!(result.num_rebased_descendants, 1);
+    let new_commit_c = repo
+        .store()
+        .get_commit(&result.rewritten[commit_c.id()])
+        .unwrap();
+    let new_commit_b = repo
+        .store()
+        .get_commit(&result.rewritten[commit_b.id()])
+        .unwrap();
+    assert_eq!(new_commit_c.change_id(), commit_c.change_id());
+    assert_eq!(new_commit_c.parent_ids(), [commit_a.id().clone()]);
+    assert_eq!(new_commit_b.change_id(), commit_b.change_id());
+    assert_eq!(new_commit_b.parent_ids(), [new_commit_c.id().clone()]);
+    let heads = mut_repo.view().heads().clone();
+    assert_eq!(heads.len(), 2);
+    assert!(heads.contains(new_commit_b.id()));
+    let new_commit_e = heads
+        .iter()
+        .filter(|id| *id != new_commit_b.id())
+        .map(|id| repo.store().get_commit(id).unwrap())
+        .exactly_one()
+        .unwrap();
+    assert_eq!(new_commit_e.change_id(), commit_e.change_id());
+    assert_eq!(new_commit_e.parent_ids(), [new_commit_c.id().clone()]);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_apply_rebase_plan_squash(use_git: bool) {
+    // Test that a squash action in MutableRepo::apply_rebase_plan() folds the
+    // commit's changes into the previously picked commit.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let path1 = RepoPath::from_internal_string("file1");
+    let path2 = RepoPath::from_internal_string("file2");
+    let path3 = RepoPath::from_internal_string("file3");
+    let tree_a = create_tree(repo, &[(&path1, "a")]);
+    let tree_b = create_tree(repo, &[(&path1, "a"), (&path2, "b")]);
+    let tree_c = create_tree(repo, &[(&path1, "a"), (&path2, "b"), (&path3, "c")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree_a.id().clone(),
+        )
+        .set_description("A")
+        .write()
+        .unwrap();
+    let commit_b = mut_repo
+        .new_commit(&settings, vec![commit_a.id().clone()], tree_b.id().clone())
+        .set_description("B")
+        .write()
+        .unwrap();
+    let commit_c = mut_repo
+        .new_commit(&settings, vec![commit_b.id().clone()], tree_c.id().clone())
+        .set_description("C")
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    // Reorder C before B and squash B into it
+    let plan = RebasePlan::new()
+        .pick(commit_c.id().clone(), vec![commit_a.id().clone()])
+        .squash(commit_b.id().clone());
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let result = mut_repo.apply_rebase_plan(&settings, &plan).unwrap();
+    let new_commit = repo
+        .store()
+        .get_commit(&result.rewritten[commit_c.id()])
+        .unwrap();
+    assert_eq!(result.rewritten[commit_b.id()], *new_commit.id());
+    assert_eq!(new_commit.parent_ids(), [commit_a.id().clone()]);
+    assert_eq!(new_commit.tree_id(), tree_c.id());
+    assert_eq!(new_commit.description(), "C\nB");
+    assert_eq!(*mut_repo.view().heads(), hashset! {new_commit.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_apply_rebase_plan_invalid(use_git: bool) {
+    // Test that MutableRepo::apply_rebase_plan() rejects inconsistent plans
+    // without rewriting anything.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // C
+    // B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new().drop_commit(repo.store().root_commit_id().clone())
+        ),
+        Err(RebasePlanError::RootCommit)
+    );
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new()
+                .drop_commit(commit_b.id().clone())
+                .drop_commit(commit_b.id().clone())
+        ),
+        Err(RebasePlanError::DuplicateCommit(id)) if id == *commit_b.id()
+    );
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new()
+                .pick(commit_b.id().clone(), vec![commit_c.id().clone()])
+                .pick(commit_c.id().clone(), vec![commit_a.id().clone()])
+        ),
+        Err(RebasePlanError::ParentNotYetPicked { commit, parent })
+            if commit == *commit_b.id() && parent == *commit_c.id()
+    );
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new()
+                .drop_commit(commit_b.id().clone())
+                .pick(commit_c.id().clone(), vec![commit_b.id().clone()])
+        ),
+        Err(RebasePlanError::DroppedParent { commit, parent })
+            if commit == *commit_c.id() && parent == *commit_b.id()
+    );
+    // B would be moved onto its own descendant
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new().pick(commit_b.id().clone(), vec![commit_c.id().clone()])
+        ),
+        Err(RebasePlanError::ParentRewritten { commit, parent })
+            if commit == *commit_b.id() && parent == *commit_c.id()
+    );
+    assert_matches!(
+        mut_repo.apply_rebase_plan(
+            &settings,
+            &RebasePlan::new()
+                .drop_commit(commit_b.id().clone())
+                .squash(commit_c.id().clone())
+        ),
+        Err(RebasePlanError::NothingToSquashInto(id)) if id == *commit_c.id()
+    );
+    assert!(!mut_repo.has_changes());
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_reconcile_divergence(use_git: bool) {
+    // Test that MutableRepo::reconcile_divergence() hides the other divergent
+    // commits and rebases their descendants onto the kept one.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // C
+    // B2 B1
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_random_commit(mut_repo, &settings);
+    let commit_b1 = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![commit_a.id().clone()])
+        .write()
+        .unwrap();
+    let commit_b2 = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![commit_a.id().clone()])
+        .set_change_id(commit_b1.change_id().clone())
+        .write()
+        .unwrap();
+    let commit_c = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![commit_b2.id().clone()])
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.resolve_change_id(commit_b1.change_id()).unwrap().len(),
+        2
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert_matches!(
+        mut_repo.reconcile_divergence(&settings, commit_a.change_id(), commit_a.id()),
+        Err(DivergenceError::NotDivergent(_))
+    );
+    assert_matches!(
+        mut_repo.reconcile_divergence(&settings, commit_b1.change_id(), commit_c.id()),
+        Err(DivergenceError::NotInChange(id)) if id == *commit_c.id()
+    );
+
+    mut_repo
+        .reconcile_divergence(&settings, commit_b1.change_id(), commit_b1.id())
+        .unwrap();
+    assert_eq!(
+        mut_repo.resolve_change_id(commit_b1.change_id()),
+        Some(vec![commit_b1.id().clone()])
+    );
+    assert_eq!(mut_repo.view().heads().len(), 1);
+    let new_head = repo
+        .store()
+        .get_commit(mut_repo.view().heads().iter().next().unwrap())
+        .unwrap();
+    assert_eq!(new_head.change_id(), commit_c.change_id());
+    assert_eq!(new_head.parent_ids(), [commit_b1.id().clone()]);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_amend_checkout_tree(use_git: bool) {
+    // Test that MutableRepo::amend_checkout_tree() replaces the working-copy
+    // commit with a rewritten one that has the new tree.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let parent = write_random_commit(mut_repo, &settings);
+    let wc_commit = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![parent.id().clone()])
+        .set_description("work in progress")
+        .write()
+        .unwrap();
+    mut_repo.edit(ws_id.clone(), &wc_commit).unwrap();
+    let repo = tx.commit().unwrap();
+
+    let new_tree = create_tree(
+        &repo,
+        &[(&RepoPath::from_internal_string("file"), "edited")],
+    );
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let new_wc_commit = mut_repo
+        .amend_checkout_tree(&ws_id, &settings, new_tree.id().clone())
+        .unwrap();
+    assert_eq!(new_wc_commit.tree_id(), new_tree.id());
+    assert_eq!(new_wc_commit.parent_ids(), wc_commit.parent_ids());
+    assert_eq!(new_wc_commit.change_id(), wc_commit.change_id());
+    assert_eq!(new_wc_commit.description(), "work in progress");
+    assert_eq!(new_wc_commit.predecessor_ids(), [wc_commit.id().clone()]);
+    assert_eq!(
+        mut_repo.view().get_wc_commit_id(&ws_id),
+        Some(new_wc_commit.id())
+    );
+    assert_matches!(
+        mut_repo.amend_checkout_tree(
+            &WorkspaceId::new("other".to_string()),
+            &settings,
+            new_tree.id().clone()
+        ),
+        Err(AmendCheckoutError::NoCheckout(_))
+    );
+    mut_repo.rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(*repo.view().heads(), hashset! {new_wc_commit.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_add_parent(use_git: bool) {
+    // Test that MutableRepo::add_parent() turns a commit into a merge without
+    // changing its tree, and rejects parents that would create a cycle.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // C
+    // B D
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert_matches!(
+        mut_repo.add_parent(&settings, &commit_b, commit_c.id().clone()),
+        Err(AddParentError::Cycle(id)) if id == *commit_c.id()
+    );
+    assert_matches!(
+        mut_repo.add_parent(&settings, &commit_b, commit_b.id().clone()),
+        Err(AddParentError::Cycle(_))
+    );
+    assert_matches!(
+        mut_repo.add_parent(&settings, &commit_b, commit_a.id().clone()),
+        Err(AddParentError::AlreadyParent(id)) if id == *commit_a.id()
+    );
+
+    let new_commit_b = mut_repo
+        .add_parent(&settings, &commit_b, commit_d.id().clone())
+        .unwrap();
+    assert_eq!(
+        new_commit_b.parent_ids(),
+        [commit_a.id().clone(), commit_d.id().clone()]
+    );
+    assert_eq!(new_commit_b.tree_id(), commit_b.tree_id());
+    assert_eq!(new_commit_b.predecessor_ids(), [commit_b.id().clone()]);
+    let heads = mut_repo.view().heads().clone();
+    assert_eq!(heads.len(), 1);
+    let new_commit_c = mut_repo
+        .store()
+        .get_commit(heads.iter().next().unwrap())
+        .unwrap();
+    assert_eq!(new_commit_c.change_id(), commit_c.change_id());
+    assert_eq!(new_commit_c.parent_ids(), [new_commit_b.id().clone()]);
+    drop(tx);
+
+    // Unknown parents are reported as errors
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_matches!(
+        mut_repo.add_parent(&settings, &commit_b, unknown_id.clone()),
+        Err(AddParentError::NotFound(id)) if id == unknown_id
+    );
+
+    // Rewrites pending elsewhere in the transaction are left alone
+    let commit_e = CommitGraphBuilder::new(&settings, mut_repo).commit_with_parents(&[&commit_a]);
+    mut_repo.record_abandoned_commit(commit_e.id().clone());
+    mut_repo
+        .add_parent(&settings, &commit_b, commit_d.id().clone())
+        .unwrap();
+    assert!(mut_repo.has_rewrites());
+    assert!(mut_repo.view().heads().contains(commit_e.id()));
+    mut_repo.rebase_descendants(&settings).unwrap();
+    assert!(!mut_repo.view().heads().contains(commit_e.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_unabandon_commit(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(*repo.view().heads(), hashset! {commit_a.id().clone()});
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let restored = tx
+        .mut_repo()
+        .unabandon_commit(&settings, &commit_b)
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(restored, commit_b);
+    assert_eq!(*repo.view().heads(), hashset! {commit_b.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_unabandon_commit_same_transaction(use_git: bool) {
+    // Test that a commit abandoned and then unabandoned in the same transaction
+    // stays visible, along with its descendants
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
+    let restored = tx
+        .mut_repo()
+        .unabandon_commit(&settings, &commit_b)
+        .unwrap();
+    assert_eq!(restored, commit_b);
+    assert!(!tx.mut_repo().has_rewrites());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(*repo.view().heads(), hashset! {commit_c.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_unabandon_commit_rewritten_parent(use_git: bool) {
+    // Test that a commit whose parent was rewritten after it was abandoned gets
+    // rebased onto the rewritten parent
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let rewritten_a = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &commit_a)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let restored = tx
+        .mut_repo()
+        .unabandon_commit(&settings, &commit_b)
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(restored.change_id(), commit_b.change_id());
+    assert_eq!(restored.parent_ids(), vec![rewritten_a.id().clone()]);
+    assert_eq!(restored.tree_id(), commit_b.tree_id());
+    assert_eq!(*repo.view().heads(), hashset! {restored.id().clone()});
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_apply_diff(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let added_path = RepoPath::from_internal_string("added");
+    let patch_before = create_tree(repo, &[(&file_path, "1\n2\n3\n4\n5\n")]);
+    let patch_after = create_tree(
+        repo,
+        &[
+            (&file_path, "1\n2\n3\n4 changed\n5\n"),
+            (&added_path, "added\n"),
+        ],
+    );
+    let diff = patch_before
+        .diff(&patch_after, &EverythingMatcher)
+        .collect_vec();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    // The base differs from the patch's "before" side, but not in the changed line
+    let clean_base = mut_repo
+        .new_commit(
+            &settings,
+            vec![root_commit_id.clone()],
+            create_tree(repo, &[(&file_path, "one\n2\n3\n4\n5\n")])
+                .id()
+                .clone(),
+        )
+        .write()
+        .unwrap();
+    let applied = mut_repo
+        .apply_diff(&settings, &clean_base, &diff, "apply")
+        .unwrap();
+    assert_eq!(applied.parent_ids(), vec![clean_base.id().clone()]);
+    assert_eq!(applied.description(), "apply");
+    assert_eq!(
+        *applied.tree_id(),
+        *create_tree(
+            repo,
+            &[
+                (&file_path, "one\n2\n3\n4 changed\n5\n"),
+                (&added_path, "added\n")
+            ]
+        )
+        .id()
+    );
+
+    // The base changed the same line as the patch
+    let conflicting_base = mut_repo
+        .new_commit(
+            &settings,
+            vec![root_commit_id],
+            create_tree(repo, &[(&file_path, "1\n2\n3\n4 other\n5\n")])
+                .id()
+                .clone(),
+        )
+        .write()
+        .unwrap();
+    let applied = mut_repo
+        .apply_diff(&settings, &conflicting_base, &diff, "apply")
+        .unwrap();
+    assert_matches!(
+        applied.tree().path_value(&file_path),
+        Some(TreeValue::Conflict(_))
+    );
+    assert_matches!(
+        applied.tree().path_value(&added_path),
+        Some(TreeValue::File { .. })
+    );
+}
@@ -12,12 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::Arc;
 
-use jj_lib::backend::CommitId;
-use jj_lib::repo::Repo;
+use assert_matches::assert_matches;
+use itertools::Itertools;
+use jj_lib::backend::{Backend, BackendInitError, CommitId, ObjectId};
+use jj_lib::local_backend::LocalBackend;
+use jj_lib::op_store::{OperationId, RefTarget, WorkspaceId};
+use jj_lib::operation;
+use jj_lib::repo::{
+    FlushError, OpResolveError, ReadonlyRepo, Repo, RepoInitError, RepoLoader, StoreFactories,
+    StoreLoadError,
+};
+use jj_lib::settings::{DurabilityMode, UserSettings};
+use jj_lib::transaction::{validate_view, Transaction, ViewValidationError};
+use maplit::{btreeset, hashset};
 use test_case::test_case;
-use testutils::{create_random_commit, write_random_commit, TestRepo};
+use testutils::{create_random_commit, write_random_commit, CountingBackend, TestRepo};
 
 fn list_dir(dir: &Path) -> Vec<String> {
     std::fs::read_dir(dir)
@@ -63,14 +76,14 @@ fn test_consecutive_operations(use_git: bool) {
 
     let mut tx1 = repo.start_transaction(&settings, "transaction 1");
     write_random_commit(tx1.mut_repo(), &settings);
-    let op_id1 = tx1.commit().operation().id().clone();
+    let op_id1 = tx1.commit().unwrap().operation().id().clone();
     assert_ne!(op_id1, op_id0);
     assert_eq!(list_dir(&op_heads_dir), vec![op_id1.hex()]);
 
     let repo = repo.reload_at_head(&settings).unwrap();
     let mut tx2 = repo.start_transaction(&settings, "transaction 2");
     write_random_commit(tx2.mut_repo(), &settings);
-    let op_id2 = tx2.commit().operation().id().clone();
+    let op_id2 = tx2.commit().unwrap().operation().id().clone();
     assert_ne!(op_id2, op_id0);
     assert_ne!(op_id2, op_id1);
     assert_eq!(list_dir(&op_heads_dir), vec![op_id2.hex()]);
@@ -96,7 +109,7 @@ fn test_concurrent_operations(use_git: bool) {
 
     let mut tx1 = repo.start_transaction(&settings, "transaction 1");
     write_random_commit(tx1.mut_repo(), &settings);
-    let op_id1 = tx1.commit().operation().id().clone();
+    let op_id1 = tx1.commit().unwrap().operation().id().clone();
     assert_ne!(op_id1, op_id0);
     assert_eq!(list_dir(&op_heads_dir), vec![op_id1.hex()]);
 
@@ -104,7 +117,7 @@ fn test_concurrent_operations(use_git: bool) {
     // since they were run in parallel.
     let mut tx2 = repo.start_transaction(&settings, "transaction 2");
     write_random_commit(tx2.mut_repo(), &settings);
-    let op_id2 = tx2.commit().operation().id().clone();
+    let op_id2 = tx2.commit().unwrap().operation().id().clone();
     assert_ne!(op_id2, op_id0);
     assert_ne!(op_id2, op_id1);
     let mut actual_heads_on_disk = list_dir(&op_heads_dir);
@@ -122,11 +135,118 @@ fn test_concurrent_operations(use_git: bool) {
     assert_eq!(list_dir(&op_heads_dir), vec![merged_op_id.hex()]);
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operation_view_matches_loaded_repo(use_git: bool) {
+    // Test that reading an operation's view directly gives the same result as
+    // loading the repo at the operation, including for merge operations
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let mut repo = test_repo.repo.clone();
+
+    for i in 0..3 {
+        let mut tx = repo.start_transaction(&settings, &format!("transaction {i}"));
+        let commit = write_random_commit(tx.mut_repo(), &settings);
+        tx.mut_repo()
+            .set_local_branch(format!("branch{i}"), RefTarget::Normal(commit.id().clone()));
+        tx.mut_repo()
+            .set_wc_commit(WorkspaceId::default(), commit.id().clone())
+            .unwrap();
+        repo = tx.commit().unwrap();
+    }
+    // Create concurrent operations and merge them
+    let mut tx1 = repo.start_transaction(&settings, "concurrent 1");
+    write_random_commit(tx1.mut_repo(), &settings);
+    tx1.commit().unwrap();
+    let mut tx2 = repo.start_transaction(&settings, "concurrent 2");
+    write_random_commit(tx2.mut_repo(), &settings);
+    tx2.commit().unwrap();
+    let repo = repo.reload_at_head(&settings).unwrap();
+    assert_eq!(repo.operation().parent_ids().len(), 2);
+
+    let loader = repo.loader();
+    let mut num_ops = 0;
+    for op in operation::walk_ancestors(repo.operation()) {
+        let loaded_repo = loader.load_at(&op);
+        assert_eq!(op.view().store_view(), loaded_repo.view().store_view());
+        num_ops += 1;
+    }
+    // The initial operation, 3 + 2 transactions, and the merge
+    assert_eq!(num_ops, 7);
+}
+
 fn assert_heads(repo: &dyn Repo, expected: Vec<&CommitId>) {
     let expected = expected.iter().cloned().cloned().collect();
     assert_eq!(*repo.view().heads(), expected);
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_transaction_with_base(use_git: bool) {
+    // Test that a transaction can be started from an operation other than the
+    // latest one
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "transaction 1");
+    let commit1 = write_random_commit(tx.mut_repo(), &settings);
+    let repo1 = tx.commit().unwrap();
+    let mut tx = repo1.start_transaction(&settings, "transaction 2");
+    let commit2 = write_random_commit(tx.mut_repo(), &settings);
+    let repo2 = tx.commit().unwrap();
+
+    let mut tx = Transaction::new_with_base(&repo2, repo1.operation(), &settings, "branched");
+    assert!(tx.mut_repo().view().heads().contains(commit1.id()));
+    assert!(!tx.mut_repo().view().heads().contains(commit2.id()));
+    let commit3 = write_random_commit(tx.mut_repo(), &settings);
+    let repo3 = tx.commit().unwrap();
+    assert_eq!(repo3.operation().parent_ids(), &vec![repo1.op_id().clone()]);
+    assert!(!repo3.view().heads().contains(commit2.id()));
+
+    // The branched operation gets merged with the latest one on reload
+    let repo = repo2.reload_at_head(&settings).unwrap();
+    assert_eq!(
+        repo.operation().parent_ids().iter().collect::<HashSet<_>>(),
+        hashset! {repo2.op_id(), repo3.op_id()}
+    );
+    assert!(repo.view().heads().contains(commit2.id()));
+    assert!(repo.view().heads().contains(commit3.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_transaction_run(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let op_heads_dir = repo.repo_path().join("op_heads").join("heads");
+
+    // An error discards the transaction
+    let result = Transaction::run(repo, &settings, "failing", |tx| {
+        write_random_commit(tx.mut_repo(), &settings);
+        Err::<(), Box<dyn std::error::Error>>("failed".into())
+    });
+    assert_eq!(result.unwrap_err().to_string(), "failed");
+    assert_eq!(list_dir(&op_heads_dir), vec![repo.op_id().hex()]);
+
+    // Success commits the transaction
+    let (new_repo, commit) = Transaction::run(repo, &settings, "succeeding", |tx| {
+        Ok::<_, FlushError>(write_random_commit(tx.mut_repo(), &settings))
+    })
+    .unwrap();
+    assert_eq!(
+        new_repo.operation().parent_ids(),
+        &vec![repo.op_id().clone()]
+    );
+    assert_eq!(
+        new_repo.operation().store_operation().metadata.description,
+        "succeeding"
+    );
+    assert!(new_repo.view().heads().contains(commit.id()));
+    assert_eq!(list_dir(&op_heads_dir), vec![new_repo.op_id().hex()]);
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_isolation(use_git: bool) {
@@ -140,7 +260,7 @@ fn test_isolation(use_git: bool) {
         .set_parents(vec![repo.store().root_commit_id().clone()])
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "transaction 1");
     let mut_repo1 = tx1.mut_repo();
@@ -171,14 +291,628 @@ fn test_isolation(use_git: bool) {
     assert_heads(mut_repo2, vec![rewrite2.id()]);
 
     // The base repo and tx2 don't see the commits from tx1.
-    tx1.commit();
+    tx1.commit().unwrap();
     assert_heads(repo.as_ref(), vec![initial.id()]);
     assert_heads(mut_repo2, vec![rewrite2.id()]);
 
     // The base repo still doesn't see the commits after both transactions commit.
-    tx2.commit();
+    tx2.commit().unwrap();
     assert_heads(repo.as_ref(), vec![initial.id()]);
     // After reload, the base repo sees both rewrites.
     let repo = repo.reload_at_head(&settings).unwrap();
     assert_heads(repo.as_ref(), vec![rewrite1.id(), rewrite2.id()]);
 }
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operation_timestamp_monotonic(use_git: bool) {
+    // Test that an operation's end time is after its parent's even if the clock
+    // went backwards.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // Pretend that the parent operation happened in the future.
+    let future_settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(settings.config().clone())
+            .set_override("debug.operation-timestamp", "2100-01-01T00:00:00+00:00")
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let mut tx = repo.start_transaction(&future_settings, "future");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    let parent_metadata = &repo.operation().store_operation().metadata;
+    assert!(!parent_metadata.tags.contains_key("clock_adjustment_ms"));
+
+    let mut tx = repo.start_transaction(&settings, "present");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo2 = tx.commit().unwrap();
+    let metadata = &repo2.operation().store_operation().metadata;
+    assert_eq!(
+        metadata.end_time.timestamp.0,
+        parent_metadata.end_time.timestamp.0 + 1
+    );
+    assert!(metadata.tags.contains_key("clock_adjustment_ms"));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operation_count_and_oldest(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let mut repo = test_repo.repo.clone();
+    let initial_count = repo.operation_count();
+    let oldest_op = repo.oldest_operation();
+    assert!(oldest_op.parents().is_empty());
+
+    for i in 0..5 {
+        let mut tx = repo.start_transaction(&settings, &format!("transaction {i}"));
+        write_random_commit(tx.mut_repo(), &settings);
+        repo = tx.commit().unwrap();
+    }
+    assert_eq!(repo.operation_count(), initial_count + 5);
+    assert_eq!(repo.oldest_operation().id(), oldest_op.id());
+    // Older repos only count their own history
+    assert_eq!(test_repo.repo.operation_count(), initial_count);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operation_graph(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let op_id0 = repo.op_id().clone();
+
+    let mut tx1 = repo.start_transaction(&settings, "transaction 1");
+    write_random_commit(tx1.mut_repo(), &settings);
+    let op_id1 = tx1.commit().unwrap().op_id().clone();
+    let mut tx2 = repo.start_transaction(&settings, "transaction 2");
+    write_random_commit(tx2.mut_repo(), &settings);
+    let op_id2 = tx2.commit().unwrap().op_id().clone();
+    // Reloading merges the concurrent operations
+    let repo = repo.reload_at_head(&settings).unwrap();
+
+    let graph = repo.operation_graph(10);
+    assert_eq!(graph.len(), repo.operation_count());
+    assert_eq!(graph[0].operation.id(), repo.op_id());
+    assert_eq!(
+        graph[0].edges.iter().collect::<HashSet<_>>(),
+        hashset! {&op_id1, &op_id2}
+    );
+    let position = |id: &OperationId| {
+        graph
+            .iter()
+            .position(|node| node.operation.id() == id)
+            .unwrap()
+    };
+    assert!(position(&op_id0) > position(&op_id1));
+    assert!(position(&op_id0) > position(&op_id2));
+    assert_eq!(graph[position(&op_id1)].edges, vec![op_id0.clone()]);
+    assert_eq!(graph[position(&op_id2)].edges, vec![op_id0]);
+    assert!(graph.last().unwrap().edges.is_empty());
+
+    // The graph can be truncated
+    let graph = repo.operation_graph(2);
+    assert_eq!(graph.len(), 2);
+    assert_eq!(graph[0].edges.len(), 2);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_resolve_operation(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let mut repo = test_repo.repo.clone();
+    let mut op_ids = vec![repo.op_id().clone()];
+    for i in 0..20 {
+        let mut tx = repo.start_transaction(&settings, &format!("transaction {i}"));
+        write_random_commit(tx.mut_repo(), &settings);
+        repo = tx.commit().unwrap();
+        op_ids.push(repo.op_id().clone());
+    }
+
+    // Unique prefix
+    let op_id = &op_ids[5];
+    let op_hex = op_id.hex();
+    assert_eq!(repo.resolve_operation(&op_hex[..10]).unwrap().id(), op_id);
+    // Full-length id
+    assert_eq!(repo.resolve_operation(&op_hex).unwrap().id(), op_id);
+    assert_eq!(
+        repo.resolve_operation(&format!("{}-", &op_hex[..10]))
+            .unwrap()
+            .id(),
+        &op_ids[4]
+    );
+
+    // Ambiguous prefix; with 21 operations, some must share the first digit
+    let (prefix, expected_candidates) = op_ids
+        .iter()
+        .into_group_map_by(|id| id.hex()[..1].to_string())
+        .into_iter()
+        .find(|(_, ids)| ids.len() > 1)
+        .unwrap();
+    assert_matches!(
+        repo.resolve_operation(&prefix),
+        Err(OpResolveError::Ambiguous { candidates, .. })
+            if candidates == expected_candidates.into_iter().cloned().sorted().collect_vec()
+    );
+
+    // Special tokens
+    assert_eq!(repo.resolve_operation("@").unwrap().id(), repo.op_id());
+    assert_eq!(repo.resolve_operation("@-").unwrap().id(), &op_ids[19]);
+    assert_eq!(repo.resolve_operation("@---").unwrap().id(), &op_ids[17]);
+    assert_matches!(
+        repo.resolve_operation(&format!("{}-", op_ids[0].hex())),
+        Err(OpResolveError::NoParent(_))
+    );
+
+    // Invalid and unknown prefixes
+    assert_matches!(
+        repo.resolve_operation("xyz"),
+        Err(OpResolveError::InvalidPrefix(_))
+    );
+    assert_matches!(
+        repo.resolve_operation(""),
+        Err(OpResolveError::InvalidPrefix(_))
+    );
+    let unknown_prefix = (0..16)
+        .map(|i| format!("{i:x}{:0>10}", ""))
+        .find(|prefix| op_ids.iter().all(|id| !id.hex().starts_with(prefix)))
+        .unwrap();
+    assert_matches!(
+        repo.resolve_operation(&unknown_prefix),
+        Err(OpResolveError::NotFound(_))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operations_introducing(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "create commit");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    let create_op_id = repo.op_id().clone();
+    let mut tx = repo.start_transaction(&settings, "unrelated");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operations_introducing(commit.id()),
+        vec![create_op_id.clone()]
+    );
+
+    // Abandoning and then restoring the commit introduces it again
+    let mut tx = repo.start_transaction(&settings, "abandon");
+    tx.mut_repo().record_abandoned_commit(commit.id().clone());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operations_introducing(commit.id()),
+        vec![create_op_id.clone()]
+    );
+    let mut tx = repo.start_transaction(&settings, "restore");
+    tx.mut_repo().add_head(&commit);
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operations_introducing(commit.id()),
+        vec![repo.op_id().clone(), create_op_id]
+    );
+
+    // The root commit was introduced by the root operation
+    assert_eq!(
+        repo.operations_introducing(repo.store().root_commit_id()),
+        vec![repo.oldest_operation().id().clone()]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_checkout_history(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+
+    let check_out = |repo: &Arc<ReadonlyRepo>| {
+        let mut tx = repo.start_transaction(&settings, "check out");
+        let wc_commit = tx
+            .mut_repo()
+            .check_out(ws_id.clone(), &settings, &commit)
+            .unwrap();
+        tx.mut_repo().rebase_descendants(&settings).unwrap();
+        let repo = tx.commit().unwrap();
+        (repo, wc_commit)
+    };
+    let (repo, wc_commit1) = check_out(&repo);
+    let op1 = repo.op_id().clone();
+    // An operation that doesn't change the working-copy commit
+    let mut tx = repo.start_transaction(&settings, "test");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    let (repo, wc_commit2) = check_out(&repo);
+    let op2 = repo.op_id().clone();
+    let (repo, wc_commit3) = check_out(&repo);
+    let op3 = repo.op_id().clone();
+
+    assert_eq!(
+        repo.checkout_history(&ws_id, 10),
+        vec![
+            (op3.clone(), wc_commit3.id().clone()),
+            (op2.clone(), wc_commit2.id().clone()),
+            (op1, wc_commit1.id().clone()),
+        ]
+    );
+    assert_eq!(
+        repo.checkout_history(&ws_id, 2),
+        vec![
+            (op3, wc_commit3.id().clone()),
+            (op2, wc_commit2.id().clone()),
+        ]
+    );
+    assert_eq!(
+        repo.checkout_history(&WorkspaceId::new("other".to_string()), 10),
+        vec![]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_auto_description(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+
+    let mut tx = repo.start_transaction(&settings, "");
+    assert_eq!(tx.auto_description().unwrap(), "no changes");
+    let commit1 = write_random_commit(tx.mut_repo(), &settings);
+    assert_eq!(tx.auto_description().unwrap(), "create commit");
+    write_random_commit(tx.mut_repo(), &settings);
+    write_random_commit(tx.mut_repo(), &settings);
+    assert_eq!(tx.auto_description().unwrap(), "create 3 commits");
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operation().store_operation().metadata.description,
+        "create 3 commits"
+    );
+
+    let mut tx = repo.start_transaction(&settings, "");
+    tx.mut_repo().check_out(ws_id, &settings, &commit1).unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operation().store_operation().metadata.description,
+        format!("check out commit {}", &commit1.id().hex()[0..12])
+    );
+
+    // An explicit description is kept
+    let mut tx = repo.start_transaction(&settings, "test");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.operation().store_operation().metadata.description,
+        "test"
+    );
+
+    // Abandoning a commit rebases its child
+    let mut tx = repo.start_transaction(&settings, "");
+    let commit2 = write_random_commit(tx.mut_repo(), &settings);
+    create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit2.id().clone()])
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    let mut tx = repo.start_transaction(&settings, "");
+    tx.mut_repo().record_abandoned_commit(commit2.id().clone());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    assert_eq!(
+        tx.auto_description().unwrap(),
+        "rewrite commit, abandon commit"
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_pinned_operations(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let repo_path = repo.repo_path().clone();
+    assert_eq!(repo.pinned_operations().unwrap(), btreeset! {});
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    let op1 = repo.op_id().clone();
+    let mut tx = repo.start_transaction(&settings, "test");
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    let op2 = repo.op_id().clone();
+
+    operation::pin(&repo_path, &op1).unwrap();
+    operation::pin(&repo_path, &op2).unwrap();
+    // Pinning is idempotent
+    operation::pin(&repo_path, &op1).unwrap();
+    assert_eq!(
+        repo.pinned_operations().unwrap(),
+        btreeset! {op1.clone(), op2.clone()}
+    );
+
+    operation::unpin(&repo_path, &op2).unwrap();
+    let repo = testutils::load_repo_at_head(&settings, &repo_path);
+    assert_eq!(repo.pinned_operations().unwrap(), btreeset! {op1});
+}
+
+fn validating_settings() -> UserSettings {
+    UserSettings::from_config(
+        config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .set_override("debug.validate-view", true)
+            .unwrap()
+            .build()
+            .unwrap(),
+    )
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_validate_view_consistent(use_git: bool) {
+    // Test that a regular transaction passes validation
+    let settings = validating_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.mut_repo()
+        .check_out(WorkspaceId::default(), &settings, &commit)
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(validate_view(repo.index(), repo.view()), Ok(()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_validate_view_dangling_checkout(use_git: bool) {
+    // Test that validation detects a working-copy commit that is no longer
+    // visible, and that it's only done when enabled
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.mut_repo()
+        .set_wc_commit(WorkspaceId::default(), commit.id().clone())
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    // Validation is off by default
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().remove_head(commit.id());
+    let dangling_repo = tx.commit().unwrap();
+    assert_eq!(
+        validate_view(dangling_repo.index(), dangling_repo.view()),
+        Err(ViewValidationError::DanglingCheckout(
+            WorkspaceId::default(),
+            commit.id().clone()
+        ))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+#[should_panic(expected = "inconsistent view")]
+fn test_validate_view_rejects_commit(use_git: bool) {
+    // Test that committing an inconsistent view panics when validation is enabled
+    let settings = validating_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.mut_repo()
+        .set_wc_commit(WorkspaceId::default(), commit.id().clone())
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo().remove_head(commit.id());
+    tx.commit().unwrap();
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_apply_foreign_operation(use_git: bool) {
+    // Test that a head added by a foreign operation shows up locally, even if
+    // the commit wasn't indexed before
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let local_commit = write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+
+    // Write a commit to the store without publishing it from this repo
+    let base_view = repo.view().store_view().clone();
+    let mut foreign_tx = repo.start_transaction(&settings, "foreign");
+    let foreign_commit = write_random_commit(foreign_tx.mut_repo(), &settings);
+    let mut foreign_view = base_view.clone();
+    foreign_view.head_ids.insert(foreign_commit.id().clone());
+    drop(foreign_tx);
+    assert!(!repo.index().has_id(foreign_commit.id()));
+
+    let mut tx = repo.start_transaction(&settings, "apply foreign operation");
+    tx.apply_foreign_operation(&foreign_view, &base_view)
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert!(repo.index().has_id(foreign_commit.id()));
+    assert_eq!(
+        *repo.view().heads(),
+        hashset! {local_commit.id().clone(), foreign_commit.id().clone()}
+    );
+}
+
+fn init_counting_repo(settings: &UserSettings, repo_dir: &Path) -> Arc<ReadonlyRepo> {
+    ReadonlyRepo::init(
+        settings,
+        repo_dir,
+        |store_path| -> Result<Box<dyn Backend>, BackendInitError> {
+            Ok(Box::new(CountingBackend::new(Box::new(
+                LocalBackend::init(store_path),
+            ))))
+        },
+        ReadonlyRepo::default_op_store_factory(),
+        ReadonlyRepo::default_op_heads_store_factory(),
+        ReadonlyRepo::default_index_store_factory(),
+        ReadonlyRepo::default_submodule_store_factory(),
+    )
+    .unwrap()
+}
+
+fn num_flushes(repo: &ReadonlyRepo) -> usize {
+    repo.store()
+        .backend_impl()
+        .downcast_ref::<CountingBackend>()
+        .unwrap()
+        .flushes()
+}
+
+#[test_case(DurabilityMode::Sync ; "sync mode")]
+#[test_case(DurabilityMode::Async ; "async mode")]
+fn test_durability_mode(mode: DurabilityMode) {
+    // Test that the sync mode flushes the store once per transaction, and that
+    // the async mode only flushes when asked to
+    let mode_name = match mode {
+        DurabilityMode::Sync => "sync",
+        DurabilityMode::Async => "async",
+    };
+    let settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .set_override("storage.durability", mode_name)
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    assert_eq!(settings.durability_mode().unwrap(), mode);
+    let temp_dir = testutils::new_temp_dir();
+    let mut repo = init_counting_repo(&settings, temp_dir.path());
+    let initial_flushes = num_flushes(&repo);
+    for _ in 0..2 {
+        let mut tx = repo.start_transaction(&settings, "test");
+        write_random_commit(tx.mut_repo(), &settings);
+        repo = tx.commit().unwrap();
+    }
+    let expected_flushes = match mode {
+        DurabilityMode::Sync => 2,
+        DurabilityMode::Async => 0,
+    };
+    assert_eq!(num_flushes(&repo), initial_flushes + expected_flushes);
+
+    repo.flush().unwrap();
+    assert_eq!(num_flushes(&repo), initial_flushes + expected_flushes + 1);
+}
+
+#[test]
+fn test_durability_mode_invalid() {
+    // Test that a bad storage.durability setting is reported when the repo is
+    // initialized or loaded, rather than quietly treated as "async"
+    let settings = testutils::user_settings();
+    let temp_dir = testutils::new_temp_dir();
+    let repo = init_counting_repo(&settings, temp_dir.path());
+
+    let bad_settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(settings.config().clone())
+            .set_override("storage.durability", "eventually")
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let other_dir = testutils::new_temp_dir();
+    assert_matches!(
+        ReadonlyRepo::init(
+            &bad_settings,
+            other_dir.path(),
+            |store_path| -> Result<Box<dyn Backend>, BackendInitError> {
+                Ok(Box::new(LocalBackend::init(store_path)))
+            },
+            ReadonlyRepo::default_op_store_factory(),
+            ReadonlyRepo::default_op_heads_store_factory(),
+            ReadonlyRepo::default_index_store_factory(),
+            ReadonlyRepo::default_submodule_store_factory(),
+        ),
+        Err(RepoInitError::Config(_))
+    );
+    assert_matches!(
+        RepoLoader::init(&bad_settings, repo.repo_path(), &StoreFactories::default()).map(|_| ()),
+        Err(StoreLoadError::Config(_))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_commits_introduced_by(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit1 = write_random_commit(tx.mut_repo(), &settings);
+    let commit2 = create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit1.id().clone()])
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.commits_introduced_by(repo.operation()).unwrap(),
+        vec![commit1.id().clone(), commit2.id().clone()]
+    );
+
+    // Checking out an existing commit doesn't introduce any commits
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo()
+        .set_wc_commit(WorkspaceId::default(), commit1.id().clone())
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.commits_introduced_by(repo.operation()).unwrap(),
+        vec![]
+    );
+
+    // A merge of concurrent operations doesn't introduce the commits added by
+    // either side
+    let mut tx1 = repo.start_transaction(&settings, "test");
+    let commit3 = write_random_commit(tx1.mut_repo(), &settings);
+    let mut tx2 = repo.start_transaction(&settings, "test");
+    let commit4 = write_random_commit(tx2.mut_repo(), &settings);
+    tx1.commit().unwrap();
+    let repo = tx2.commit().unwrap().reload_at_head(&settings).unwrap();
+    let parent_ops = repo.operation().parents();
+    assert_eq!(parent_ops.len(), 2);
+    assert_eq!(
+        repo.commits_introduced_by(repo.operation()).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        parent_ops
+            .iter()
+            .map(|op| repo.commits_introduced_by(op).unwrap())
+            .collect::<HashSet<_>>(),
+        hashset! {vec![commit3.id().clone()], vec![commit4.id().clone()]}
+    );
+}
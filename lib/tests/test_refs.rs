@@ -40,7 +40,7 @@ fn test_merge_ref_targets() {
     let commit5 = graph_builder.commit_with_parents(&[&commit1]);
     let commit6 = graph_builder.commit_with_parents(&[&commit5]);
     let commit7 = graph_builder.commit_with_parents(&[&commit5]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let target1 = RefTarget::Normal(commit1.id().clone());
     let target2 = RefTarget::Normal(commit2.id().clone());
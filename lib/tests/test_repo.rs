@@ -0,0 +1,2003 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use itertools::Itertools;
+use jj_lib::backend::{
+    AnyObjectId, BackendError, ChangeId, CommitId, MillisSinceEpoch, ObjectId, Signature,
+    Timestamp, TreeId, TreeValue,
+};
+use jj_lib::commit::Commit;
+use jj_lib::index::{HexPrefix, Index, PrefixResolution};
+use jj_lib::op_store::{RefTarget, WorkspaceId};
+use jj_lib::repo::{
+    CommitArgError, DirDiffStat, MergeConflictSummary, MutableRepo, PrefixStats, Repo,
+};
+use jj_lib::repo_path::{RepoPath, RepoPathComponent};
+use jj_lib::revset::{ResolvedExpression, Revset, RevsetEvaluationError, RevsetExpression};
+use jj_lib::settings::{LogOrder, UserSettings};
+use jj_lib::store::Store;
+use jj_lib::tree::DiffKind;
+use maplit::hashset;
+use test_case::test_case;
+use testutils::{
+    create_random_commit, create_tree, write_random_commit, CommitGraphBuilder, TestRepo,
+};
+
+fn write_commit_at(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    parents: &[&Commit],
+    millis: i64,
+) -> Commit {
+    let signature = Signature {
+        name: "Some One".to_string(),
+        email: "some.one@example.com".to_string(),
+        timestamp: Timestamp {
+            timestamp: MillisSinceEpoch(millis),
+            tz_offset: 0,
+        },
+    };
+    create_random_commit(mut_repo, settings)
+        .set_parents(parents.iter().map(|commit| commit.id().clone()).collect())
+        .set_committer(signature)
+        .write()
+        .unwrap()
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_log_order(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+
+    // D (t=2)
+    // | C (t=4)
+    // | B (t=3)
+    // |/
+    // A (t=1)
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_commit_at(mut_repo, &settings, &[&root_commit], 1000);
+    let commit_b = write_commit_at(mut_repo, &settings, &[&commit_a], 3000);
+    let commit_c = write_commit_at(mut_repo, &settings, &[&commit_b], 4000);
+    let commit_d = write_commit_at(mut_repo, &settings, &[&commit_a], 2000);
+    let repo = tx.commit().unwrap();
+
+    let ids = |order| {
+        repo.log_order(order)
+            .unwrap()
+            .iter()
+            .map(|commit| commit.id().clone())
+            .collect_vec()
+    };
+    assert_eq!(
+        ids(LogOrder::Topological),
+        vec![
+            commit_d.id().clone(),
+            commit_c.id().clone(),
+            commit_b.id().clone(),
+            commit_a.id().clone(),
+            root_commit.id().clone(),
+        ]
+    );
+    assert_eq!(
+        ids(LogOrder::CommitterTimestamp),
+        vec![
+            commit_c.id().clone(),
+            commit_b.id().clone(),
+            commit_d.id().clone(),
+            commit_a.id().clone(),
+            root_commit.id().clone(),
+        ]
+    );
+}
+
+#[test]
+fn test_log_order_setting() {
+    let config = |text: &str| {
+        UserSettings::from_config(
+            config::Config::builder()
+                .add_source(config::File::from_str(text, config::FileFormat::Toml))
+                .build()
+                .unwrap(),
+        )
+    };
+    assert_eq!(config("").log_order().unwrap(), LogOrder::Topological);
+    assert_eq!(
+        config("ui.log-order = 'committer-timestamp'")
+            .log_order()
+            .unwrap(),
+        LogOrder::CommitterTimestamp
+    );
+    assert!(config("ui.log-order = 'bogus'").log_order().is_err());
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_checkout_parents(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+    let other_ws_id = WorkspaceId::new("other".to_string());
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let parent1 = write_random_commit(mut_repo, &settings);
+    let parent2 = write_random_commit(mut_repo, &settings);
+    let wc_commit = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![parent1.id().clone(), parent2.id().clone()])
+        .write()
+        .unwrap();
+    mut_repo.edit(ws_id.clone(), &wc_commit).unwrap();
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(
+        repo.checkout_parents(&ws_id).unwrap(),
+        vec![parent1, parent2]
+    );
+    assert_eq!(repo.checkout_parents(&other_ws_id).unwrap(), vec![]);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_commit_is_empty(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit_id = repo.store().root_commit_id().clone();
+
+    let base_path = RepoPath::from_internal_string("base");
+    let left_path = RepoPath::from_internal_string("left");
+    let right_path = RepoPath::from_internal_string("right");
+    let base_tree = create_tree(repo, &[(&base_path, "base")]);
+    let left_tree = create_tree(repo, &[(&base_path, "base"), (&left_path, "left")]);
+    let right_tree = create_tree(repo, &[(&base_path, "base"), (&right_path, "right")]);
+    let merged_tree = create_tree(
+        repo,
+        &[
+            (&base_path, "base"),
+            (&left_path, "left"),
+            (&right_path, "right"),
+        ],
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let mut write_commit = |parents: Vec<CommitId>, tree_id: &TreeId| {
+        mut_repo
+            .new_commit(&settings, parents, tree_id.clone())
+            .write()
+            .unwrap()
+    };
+    let base = write_commit(vec![root_commit_id], base_tree.id());
+    let empty_linear = write_commit(vec![base.id().clone()], base_tree.id());
+    let left = write_commit(vec![base.id().clone()], left_tree.id());
+    let right = write_commit(vec![base.id().clone()], right_tree.id());
+    let empty_merge = write_commit(
+        vec![left.id().clone(), right.id().clone()],
+        merged_tree.id(),
+    );
+    let nonempty_merge = write_commit(vec![left.id().clone(), right.id().clone()], left_tree.id());
+
+    let mut_repo = tx.mut_repo();
+    assert!(!mut_repo.commit_is_empty(&base).unwrap());
+    assert!(mut_repo.commit_is_empty(&empty_linear).unwrap());
+    assert!(!mut_repo.commit_is_empty(&left).unwrap());
+    assert!(mut_repo.commit_is_empty(&empty_merge).unwrap());
+    assert!(!mut_repo.commit_is_empty(&nonempty_merge).unwrap());
+    assert!(mut_repo
+        .commit_is_empty(&repo.store().root_commit())
+        .unwrap());
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_readonly_repo_is_snapshot(use_git: bool) {
+    // Test that a ReadonlyRepo keeps seeing the same view after another
+    // transaction has been committed on top of its operation.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let heads_before = repo.view().heads().clone();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    let new_repo = tx.commit().unwrap();
+
+    assert_eq!(*repo.view().heads(), heads_before);
+    assert!(!repo.index().has_id(commit.id()));
+    assert!(new_repo.view().heads().contains(commit.id()));
+    let reloaded_repo = repo.reload_at_head(&settings).unwrap();
+    assert_eq!(reloaded_repo.op_id(), new_repo.op_id());
+    assert!(reloaded_repo.view().heads().contains(commit.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_changed_paths(use_git: bool) {
+    // Test diffing two sibling commits, including a path that is conflicted in
+    // one of them.
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit_id = repo.store().root_commit_id().clone();
+
+    let unchanged_path = RepoPath::from_internal_string("unchanged");
+    let modified_path = RepoPath::from_internal_string("dir/modified");
+    let removed_path = RepoPath::from_internal_string("dir/removed");
+    let added_path = RepoPath::from_internal_string("added");
+    let conflict_path = RepoPath::from_internal_string("conflict");
+    let base_tree = create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "base"),
+            (&removed_path, "base"),
+            (&conflict_path, "base"),
+        ],
+    );
+    let left_tree = create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "left"),
+            (&removed_path, "base"),
+            (&conflict_path, "left"),
+        ],
+    );
+    let right_tree = create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "right"),
+            (&added_path, "right"),
+            (&conflict_path, "right"),
+        ],
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let base = mut_repo
+        .new_commit(&settings, vec![root_commit_id], base_tree.id().clone())
+        .write()
+        .unwrap();
+    let left = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], left_tree.id().clone())
+        .write()
+        .unwrap();
+    let other_tree = create_tree(repo, &[(&conflict_path, "other")]);
+    let other = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], other_tree.id().clone())
+        .write()
+        .unwrap();
+    let conflicted = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), other.id().clone()],
+            "",
+            false,
+        )
+        .unwrap();
+    let mut tree_builder = repo.store().tree_builder(right_tree.id().clone());
+    tree_builder.set(
+        conflict_path.clone(),
+        conflicted.tree().path_value(&conflict_path).unwrap(),
+    );
+    let right = mut_repo
+        .new_commit(
+            &settings,
+            vec![base.id().clone()],
+            tree_builder.write_tree(),
+        )
+        .write()
+        .unwrap();
+
+    assert_eq!(
+        mut_repo.changed_paths(&left, &right),
+        vec![
+            (added_path.clone(), DiffKind::Added),
+            (conflict_path.clone(), DiffKind::Conflicted),
+            (modified_path.clone(), DiffKind::Modified),
+            (removed_path.clone(), DiffKind::Removed),
+        ]
+    );
+    assert_eq!(mut_repo.changed_paths(&left, &left), vec![]);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_find_equivalent(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let other_path = RepoPath::from_internal_string("other");
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let mut write_commit = |parent: &Commit, file_content: &str, other_content: &str| {
+        let tree = create_tree(
+            repo,
+            &[(&file_path, file_content), (&other_path, other_content)],
+        );
+        mut_repo
+            .new_commit(&settings, vec![parent.id().clone()], tree.id().clone())
+            .write()
+            .unwrap()
+    };
+    let root_commit = repo.store().root_commit();
+    let base = write_commit(&root_commit, "1\n2\n3\n", "a\n");
+    let commit = write_commit(&base, "1\n2 changed\n3\n", "a\n");
+    // A different base that changes other lines of the same file
+    let other_base = write_commit(&base, "one\n2\n3\n", "b\n");
+    let cherry_pick = write_commit(&other_base, "one\n2 changed\n3\n", "b\n");
+    let different = write_commit(&other_base, "one\n2 differently\n3\n", "b\n");
+    assert_ne!(cherry_pick.id(), commit.id());
+
+    assert_eq!(
+        mut_repo
+            .find_equivalent(
+                &commit,
+                &[
+                    commit.id().clone(),
+                    different.id().clone(),
+                    cherry_pick.id().clone()
+                ]
+            )
+            .unwrap(),
+        Some(cherry_pick.id().clone())
+    );
+    assert_eq!(
+        mut_repo
+            .find_equivalent(&cherry_pick, &[base.id().clone(), commit.id().clone()])
+            .unwrap(),
+        Some(commit.id().clone())
+    );
+    assert_eq!(
+        mut_repo
+            .find_equivalent(&commit, &[different.id().clone(), other_base.id().clone()])
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_missing_referenced_commits() {
+    // Test with the local backend only, since it stores one commit per file
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(false);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+    let mut tx = repo.start_transaction(&settings, "test");
+    let rewritten_b = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &commit_b)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(repo.missing_referenced_commits().unwrap(), vec![]);
+
+    // Remove the parent and the predecessor of the rewritten commit, and read
+    // through a fresh store so the commits aren't cached
+    let commits_dir = repo.repo_path().join("store").join("commits");
+    std::fs::remove_file(commits_dir.join(commit_a.id().hex())).unwrap();
+    std::fs::remove_file(commits_dir.join(commit_b.id().hex())).unwrap();
+    let repo = testutils::load_repo_at_head(&settings, repo.repo_path());
+    assert_eq!(
+        repo.missing_referenced_commits().unwrap(),
+        vec![commit_b.id().clone(), commit_a.id().clone()]
+    );
+    assert!(repo.store().get_commit(rewritten_b.id()).is_ok());
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_diff_by_directory(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let path = RepoPath::from_internal_string;
+    let base_tree = create_tree(
+        repo,
+        &[
+            (&path("top"), "base"),
+            (&path("a/modified"), "base"),
+            (&path("a/removed"), "base"),
+            (&path("b/modified"), "base"),
+        ],
+    );
+    let tree = create_tree(
+        repo,
+        &[
+            (&path("top"), "changed"),
+            (&path("a/modified"), "changed"),
+            (&path("a/added"), "added"),
+            (&path("b/modified"), "changed"),
+            (&path("b/sub/added"), "added"),
+        ],
+    );
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let base = mut_repo
+        .new_commit(&settings, vec![root_commit_id], base_tree.id().clone())
+        .write()
+        .unwrap();
+    let commit = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], tree.id().clone())
+        .write()
+        .unwrap();
+
+    let stat = |added, modified, removed| DirDiffStat {
+        added,
+        modified,
+        removed,
+        conflicted: 0,
+    };
+    assert_eq!(
+        mut_repo.diff_by_directory(&commit, &base, 1),
+        vec![
+            (RepoPath::root(), stat(0, 1, 0)),
+            (path("a"), stat(1, 1, 1)),
+            (path("b"), stat(1, 1, 0)),
+        ]
+    );
+    assert_eq!(
+        mut_repo.diff_by_directory(&commit, &base, 2),
+        vec![
+            (RepoPath::root(), stat(0, 1, 0)),
+            (path("a"), stat(1, 1, 1)),
+            (path("b"), stat(0, 1, 0)),
+            (path("b/sub"), stat(1, 0, 0)),
+        ]
+    );
+    assert_eq!(
+        mut_repo.diff_by_directory(&commit, &base, 0),
+        vec![(RepoPath::root(), stat(2, 3, 1))]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_ancestors_containing(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // E
+    // | D
+    // | C
+    // |/
+    // B F
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_f = graph_builder.commit_with_parents(&[&commit_a]);
+    let repo = tx.commit().unwrap();
+
+    let all = [
+        &commit_a, &commit_b, &commit_c, &commit_d, &commit_e, &commit_f,
+    ]
+    .iter()
+    .map(|commit| commit.id().clone())
+    .collect_vec();
+    let naive = |targets: &[CommitId]| -> Vec<CommitId> {
+        let expression = RevsetExpression::commits(all.clone())
+            .intersection(&RevsetExpression::commits(targets.to_vec()).ancestors());
+        let revset = expression
+            .resolve(repo.as_ref())
+            .unwrap()
+            .evaluate(repo.as_ref())
+            .unwrap();
+        revset.iter().sorted().collect()
+    };
+    let optimized = |targets: &[CommitId]| -> Vec<CommitId> {
+        repo.ancestors_containing(targets, &all)
+            .into_iter()
+            .sorted()
+            .collect()
+    };
+
+    for targets in [
+        vec![],
+        vec![commit_a.id().clone()],
+        vec![commit_d.id().clone()],
+        vec![commit_e.id().clone(), commit_f.id().clone()],
+        vec![commit_d.id().clone(), commit_e.id().clone()],
+    ] {
+        assert_eq!(optimized(&targets), naive(&targets), "targets: {targets:?}");
+    }
+    assert_eq!(
+        repo.ancestors_containing(&[commit_c.id().clone()], &all),
+        vec![
+            commit_a.id().clone(),
+            commit_b.id().clone(),
+            commit_c.id().clone()
+        ]
+    );
+    // Unknown commits are neither ancestors nor descendants
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_eq!(
+        repo.ancestors_containing(std::slice::from_ref(&unknown_id), &all),
+        vec![]
+    );
+    assert_eq!(
+        repo.ancestors_containing(
+            &[commit_c.id().clone()],
+            &[unknown_id, commit_a.id().clone()]
+        ),
+        vec![commit_a.id().clone()]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_duplicate_commits(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let tree = create_tree(repo, &[(&RepoPath::from_internal_string("file"), "a")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let original = mut_repo
+        .new_commit(&settings, vec![root_commit_id.clone()], tree.id().clone())
+        .set_description("description")
+        .write()
+        .unwrap();
+    let duplicate = mut_repo
+        .new_commit(&settings, vec![root_commit_id.clone()], tree.id().clone())
+        .set_description("description")
+        .set_author(original.author().clone())
+        .write()
+        .unwrap();
+    let other_description = mut_repo
+        .new_commit(&settings, vec![root_commit_id], tree.id().clone())
+        .set_description("other description")
+        .set_author(original.author().clone())
+        .write()
+        .unwrap();
+    let other_parent = mut_repo
+        .new_commit(&settings, vec![original.id().clone()], tree.id().clone())
+        .set_description("description")
+        .set_author(original.author().clone())
+        .write()
+        .unwrap();
+    assert_ne!(original.change_id(), duplicate.change_id());
+    assert_ne!(original.id(), duplicate.id());
+
+    let groups = mut_repo.duplicate_commits().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(
+        groups[0].iter().sorted().collect_vec(),
+        [original.id(), duplicate.id()]
+            .into_iter()
+            .sorted()
+            .collect_vec()
+    );
+    assert!(!groups[0].contains(other_description.id()));
+    assert!(!groups[0].contains(other_parent.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_annotate(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let path = RepoPath::from_internal_string("file");
+    let other_path = RepoPath::from_internal_string("other");
+
+    let tree1 = create_tree(repo, &[(&path, "a\nb\nc\n")]);
+    let tree2 = create_tree(repo, &[(&path, "a\nB\nc\nd\n")]);
+    let tree3 = create_tree(repo, &[(&path, "x\na\nB\nd\n"), (&other_path, "other\n")]);
+    let tree4 = create_tree(repo, &[(&path, "x\na\nB\nd\n")]);
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = mut_repo
+        .new_commit(&settings, vec![root_commit_id], tree1.id().clone())
+        .write()
+        .unwrap();
+    let commit2 = mut_repo
+        .new_commit(&settings, vec![commit1.id().clone()], tree2.id().clone())
+        .write()
+        .unwrap();
+    let commit3 = mut_repo
+        .new_commit(&settings, vec![commit2.id().clone()], tree3.id().clone())
+        .write()
+        .unwrap();
+    // Doesn't change the file
+    let commit4 = mut_repo
+        .new_commit(&settings, vec![commit3.id().clone()], tree4.id().clone())
+        .write()
+        .unwrap();
+
+    let annotate = |commit: &Commit| {
+        mut_repo
+            .annotate(commit, &path)
+            .unwrap()
+            .into_iter()
+            .map(|(line, commit_id)| (String::from_utf8(line).unwrap(), commit_id))
+            .collect_vec()
+    };
+    assert_eq!(
+        annotate(&commit4),
+        vec![
+            ("x\n".to_string(), commit3.id().clone()),
+            ("a\n".to_string(), commit1.id().clone()),
+            ("B\n".to_string(), commit2.id().clone()),
+            ("d\n".to_string(), commit2.id().clone()),
+        ]
+    );
+    assert_eq!(
+        annotate(&commit1),
+        vec![
+            ("a\n".to_string(), commit1.id().clone()),
+            ("b\n".to_string(), commit1.id().clone()),
+            ("c\n".to_string(), commit1.id().clone()),
+        ]
+    );
+    assert_eq!(
+        mut_repo
+            .annotate(&commit4, &RepoPath::from_internal_string("missing"))
+            .unwrap(),
+        vec![]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_get_commits(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit1 = write_random_commit(tx.mut_repo(), &settings);
+    let commit2 = write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit().unwrap();
+    // Use a fresh store so nothing is cached
+    let repo = testutils::load_repo_at_head(&settings, repo.repo_path());
+
+    let missing_id = CommitId::new(vec![0; repo.store().commit_id_length()]);
+    let ids = vec![
+        commit2.id().clone(),
+        repo.store().root_commit_id().clone(),
+        missing_id,
+        commit1.id().clone(),
+        commit2.id().clone(),
+    ];
+    let results = repo.get_commits(&ids);
+    assert_eq!(results.len(), ids.len());
+    for (id, result) in ids.iter().zip(results) {
+        match repo.store().get_commit(id) {
+            Ok(commit) => assert_eq!(result.unwrap(), commit),
+            Err(_) => assert!(result.is_err()),
+        }
+    }
+    assert_eq!(
+        repo.get_commits(&[commit1.id().clone()])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect_vec(),
+        vec![commit1]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_check_commit_arg(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let visible = write_random_commit(mut_repo, &settings);
+    let abandoned = write_random_commit(mut_repo, &settings);
+    mut_repo.record_abandoned_commit(abandoned.id().clone());
+    mut_repo.rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(repo.check_commit_arg(visible.id()).unwrap(), visible);
+    assert_eq!(
+        repo.check_commit_arg(repo.store().root_commit_id())
+            .unwrap(),
+        repo.store().root_commit()
+    );
+    assert_matches!(
+        repo.check_commit_arg(abandoned.id()),
+        Err(CommitArgError::NotVisible(id)) if id == *abandoned.id()
+    );
+    let missing_id = CommitId::new(vec![0x11; repo.store().commit_id_length()]);
+    assert_matches!(
+        repo.check_commit_arg(&missing_id),
+        Err(CommitArgError::NotFound(id)) if id == missing_id
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_is_obsolete_and_divergent(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let normal = write_random_commit(mut_repo, &settings);
+    let rewritten = write_random_commit(mut_repo, &settings);
+    let divergent1 = write_random_commit(mut_repo, &settings);
+    let divergent2 = create_random_commit(mut_repo, &settings)
+        .set_change_id(divergent1.change_id().clone())
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+    let mut tx = repo.start_transaction(&settings, "test");
+    let rewritten2 = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &rewritten)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+
+    assert!(!repo.is_obsolete(normal.id()));
+    assert!(repo.is_obsolete(rewritten.id()));
+    assert!(!repo.is_obsolete(rewritten2.id()));
+    assert!(!repo.is_obsolete(divergent1.id()));
+    assert!(!repo.is_obsolete(divergent2.id()));
+    assert!(!repo.is_obsolete(repo.store().root_commit_id()));
+
+    assert!(!repo.is_divergent(normal.change_id()));
+    assert!(!repo.is_divergent(rewritten.change_id()));
+    assert!(repo.is_divergent(divergent1.change_id()));
+
+    // Abandoning one of the divergent commits resolves the divergence
+    let mut tx = repo.start_transaction(&settings, "test");
+    tx.mut_repo()
+        .record_abandoned_commit(divergent2.id().clone());
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    assert!(tx.mut_repo().is_obsolete(divergent2.id()));
+    assert!(!tx.mut_repo().is_divergent(divergent1.change_id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_operation_patch_series(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let other_path = RepoPath::from_internal_string("other");
+    let file_path = RepoPath::from_internal_string("file");
+    let base_tree = create_tree(repo, &[(&other_path, "other\n")]);
+    let tree1 = create_tree(repo, &[(&other_path, "other\n"), (&file_path, "a\nb\nc\n")]);
+    let tree2 = create_tree(repo, &[(&other_path, "other\n"), (&file_path, "a\nB\nc\n")]);
+    let author = Signature {
+        name: "Some One".to_string(),
+        email: "some.one@example.com".to_string(),
+        timestamp: Timestamp {
+            timestamp: MillisSinceEpoch(1_000_000_000_000),
+            tz_offset: 60,
+        },
+    };
+
+    // Commits from an earlier operation aren't included
+    let mut tx = repo.start_transaction(&settings, "test");
+    let base = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            base_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit1 = tx
+        .mut_repo()
+        .new_commit(&settings, vec![base.id().clone()], tree1.id().clone())
+        .set_author(author.clone())
+        .set_description("add file\n\nWith some details.\n")
+        .write()
+        .unwrap();
+    let commit2 = tx
+        .mut_repo()
+        .new_commit(&settings, vec![commit1.id().clone()], tree2.id().clone())
+        .set_author(author)
+        .set_description("change file\n")
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    let patches = repo.operation_patch_series(repo.operation()).unwrap();
+    assert_eq!(
+        patches.iter().map(|patch| &patch.commit_id).collect_vec(),
+        vec![commit1.id(), commit2.id()]
+    );
+    assert_eq!(
+        String::from_utf8(patches[0].text.clone()).unwrap(),
+        format!(
+            r#"From {} Mon Sep 17 00:00:00 2001
+From: Some One <some.one@example.com>
+Date: Sun, 09 Sep 2001 02:46:40 +0100
+Subject: [PATCH 1/2] add file
+
+With some details.
+---
+diff --git a/file b/file
+new file mode 100644
+--- /dev/null
++++ b/file
+@@ -1,0 +1,3 @@
++a
++b
++c
+"#,
+            commit1.id().hex()
+        )
+    );
+    assert_eq!(
+        String::from_utf8(patches[1].text.clone()).unwrap(),
+        format!(
+            r#"From {} Mon Sep 17 00:00:00 2001
+From: Some One <some.one@example.com>
+Date: Sun, 09 Sep 2001 02:46:40 +0100
+Subject: [PATCH 2/2] change file
+
+---
+diff --git a/file b/file
+--- a/file
++++ b/file
+@@ -1,3 +1,3 @@
+ a
+-b
++B
+ c
+"#,
+            commit2.id().hex()
+        )
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_distance(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // E
+    // |\
+    // | D
+    // C |
+    // B |
+    // |/
+    // A   F
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_c, &commit_d]);
+    let commit_f = graph_builder.initial_commit();
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(
+        repo.distance(commit_a.id(), commit_a.id()).unwrap(),
+        Some(0)
+    );
+    assert_eq!(
+        repo.distance(commit_b.id(), commit_c.id()).unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        repo.distance(commit_a.id(), commit_c.id()).unwrap(),
+        Some(2)
+    );
+    // The shortest path goes through D
+    assert_eq!(
+        repo.distance(commit_a.id(), commit_e.id()).unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        repo.distance(commit_b.id(), commit_e.id()).unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        repo.distance(repo.store().root_commit_id(), commit_e.id())
+            .unwrap(),
+        Some(3)
+    );
+    // Not an ancestor
+    assert_eq!(repo.distance(commit_c.id(), commit_a.id()).unwrap(), None);
+    assert_eq!(repo.distance(commit_d.id(), commit_c.id()).unwrap(), None);
+    assert_eq!(repo.distance(commit_f.id(), commit_e.id()).unwrap(), None);
+    // Unknown commits have no distance
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_eq!(repo.distance(&unknown_id, commit_e.id()).unwrap(), None);
+
+    // The default implementation agrees with the index's
+    let index = IndexWithoutDistance(repo.index());
+    let store = repo.store();
+    let all_ids = [
+        &commit_a, &commit_b, &commit_c, &commit_d, &commit_e, &commit_f,
+    ]
+    .map(|commit| commit.id().clone());
+    for ancestor_id in &all_ids {
+        for descendant_id in &all_ids {
+            assert_eq!(
+                index.distance(ancestor_id, descendant_id, store).unwrap(),
+                repo.distance(ancestor_id, descendant_id).unwrap(),
+                "{ancestor_id:?} -> {descendant_id:?}"
+            );
+        }
+    }
+}
+
+/// Forwards everything but `distance()` to the wrapped index, so the trait's
+/// default implementation is used.
+struct IndexWithoutDistance<'a>(&'a dyn Index);
+
+impl Index for IndexWithoutDistance<'_> {
+    fn shortest_unique_commit_id_prefix_len(&self, commit_id: &CommitId) -> usize {
+        self.0.shortest_unique_commit_id_prefix_len(commit_id)
+    }
+
+    fn resolve_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<CommitId> {
+        self.0.resolve_prefix(prefix)
+    }
+
+    fn has_id(&self, commit_id: &CommitId) -> bool {
+        self.0.has_id(commit_id)
+    }
+
+    fn is_ancestor(&self, ancestor_id: &CommitId, descendant_id: &CommitId) -> bool {
+        self.0.is_ancestor(ancestor_id, descendant_id)
+    }
+
+    fn common_ancestors(&self, set1: &[CommitId], set2: &[CommitId]) -> Vec<CommitId> {
+        self.0.common_ancestors(set1, set2)
+    }
+
+    fn heads(&self, candidates: &mut dyn Iterator<Item = &CommitId>) -> Vec<CommitId> {
+        self.0.heads(candidates)
+    }
+
+    fn topo_order(&self, input: &mut dyn Iterator<Item = &CommitId>) -> Vec<CommitId> {
+        self.0.topo_order(input)
+    }
+
+    fn evaluate_revset<'index>(
+        &'index self,
+        expression: &ResolvedExpression,
+        store: &Arc<Store>,
+    ) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
+        self.0.evaluate_revset(expression, store)
+    }
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_objects_missing_from(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let unchanged_path = RepoPath::from_internal_string("dir/unchanged");
+    let modified_path = RepoPath::from_internal_string("dir/modified");
+    let added_path = RepoPath::from_internal_string("added");
+    let tree1 = create_tree(
+        repo,
+        &[(&unchanged_path, "unchanged"), (&modified_path, "before")],
+    );
+    let tree2 = create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "after"),
+            (&added_path, "added"),
+        ],
+    );
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree1.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let commit2 = mut_repo
+        .new_commit(&settings, vec![commit1.id().clone()], tree2.id().clone())
+        .write()
+        .unwrap();
+    mut_repo.set_local_branch("main".to_string(), RefTarget::Normal(commit2.id().clone()));
+    let repo = tx.commit().unwrap();
+
+    let file_id = |path: &RepoPath| match tree2.path_value(path) {
+        Some(TreeValue::File { id, .. }) => AnyObjectId::File(id),
+        _ => panic!("expected a file"),
+    };
+    let dir_id = match tree2.path_value(&RepoPath::from_internal_string("dir")) {
+        Some(TreeValue::Tree(id)) => AnyObjectId::Tree(id),
+        _ => panic!("expected a tree"),
+    };
+    // The remote has the base commit, so only the new commit, its trees, and
+    // the new file contents are missing
+    assert_eq!(
+        repo.objects_missing_from(&[commit1.id().clone()]).unwrap(),
+        hashset! {
+            AnyObjectId::Commit(commit2.id().clone()),
+            AnyObjectId::Tree(tree2.id().clone()),
+            dir_id,
+            file_id(&modified_path),
+            file_id(&added_path),
+        }
+    );
+    // Nothing is missing if the remote has the branch target
+    assert!(repo
+        .objects_missing_from(&[commit2.id().clone()])
+        .unwrap()
+        .is_empty());
+    // Everything is missing from an empty remote
+    let all_missing = repo.objects_missing_from(&[]).unwrap();
+    assert!(all_missing.contains(&AnyObjectId::Commit(commit1.id().clone())));
+    assert!(all_missing.contains(&file_id(&unchanged_path)));
+    assert!(!all_missing.contains(&AnyObjectId::Commit(repo.store().root_commit_id().clone())));
+    // Commits the remote has that we don't know about are ignored
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_eq!(
+        repo.objects_missing_from(&[commit1.id().clone(), unknown_id])
+            .unwrap(),
+        repo.objects_missing_from(&[commit1.id().clone()]).unwrap()
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_current_branch(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = write_random_commit(mut_repo, &settings);
+    let wc_commit = mut_repo
+        .check_out(ws_id.clone(), &settings, &commit1)
+        .unwrap();
+    assert_eq!(mut_repo.current_branch(&ws_id).unwrap(), None);
+
+    mut_repo.set_local_branch("main".to_string(), RefTarget::Normal(commit1.id().clone()));
+    mut_repo.set_local_branch("other".to_string(), RefTarget::Normal(commit2.id().clone()));
+    assert_eq!(
+        mut_repo.current_branch(&ws_id).unwrap(),
+        Some("main".to_string())
+    );
+
+    // A branch pointing to the working-copy commit itself is not current
+    mut_repo.set_local_branch(
+        "main".to_string(),
+        RefTarget::Normal(wc_commit.id().clone()),
+    );
+    assert_eq!(mut_repo.current_branch(&ws_id).unwrap(), None);
+
+    // Ambiguous if several branches point to the parent
+    mut_repo.set_local_branch("main".to_string(), RefTarget::Normal(commit1.id().clone()));
+    mut_repo.set_local_branch("main2".to_string(), RefTarget::Normal(commit1.id().clone()));
+    assert_eq!(mut_repo.current_branch(&ws_id).unwrap(), None);
+
+    assert_eq!(
+        mut_repo
+            .current_branch(&WorkspaceId::new("other".to_string()))
+            .unwrap(),
+        None
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_status(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let ws_id = WorkspaceId::default();
+
+    let modified_path = RepoPath::from_internal_string("modified");
+    let removed_path = RepoPath::from_internal_string("removed");
+    let added_path = RepoPath::from_internal_string("added");
+    let dirty_path = RepoPath::from_internal_string("dirty");
+    let parent_tree = create_tree(
+        repo,
+        &[(&modified_path, "before"), (&removed_path, "removed")],
+    );
+    let wc_tree = create_tree(repo, &[(&modified_path, "after"), (&added_path, "added")]);
+    let dirty_tree = create_tree(
+        repo,
+        &[
+            (&modified_path, "after"),
+            (&added_path, "added"),
+            (&dirty_path, "dirty"),
+        ],
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    assert!(mut_repo.status(&ws_id, None).unwrap().is_none());
+    let parent = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            parent_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let wc_commit = mut_repo
+        .new_commit(&settings, vec![parent.id().clone()], wc_tree.id().clone())
+        .write()
+        .unwrap();
+    mut_repo
+        .set_wc_commit(ws_id.clone(), wc_commit.id().clone())
+        .unwrap();
+    mut_repo.set_local_branch(
+        "main".to_string(),
+        RefTarget::Normal(wc_commit.id().clone()),
+    );
+    mut_repo.set_local_branch("other".to_string(), RefTarget::Normal(parent.id().clone()));
+
+    let status = mut_repo.status(&ws_id, None).unwrap().unwrap();
+    assert_eq!(status.wc_commit.id(), wc_commit.id());
+    assert_eq!(status.parents, vec![parent.clone()]);
+    assert_eq!(status.branches, vec!["main".to_string()]);
+    assert_eq!(status.conflicts, vec![]);
+    assert_eq!(
+        status.changes,
+        vec![
+            (added_path.clone(), DiffKind::Added),
+            (modified_path.clone(), DiffKind::Modified),
+            (removed_path.clone(), DiffKind::Removed),
+        ]
+    );
+
+    // Changes in the working copy that haven't been recorded yet
+    let status = mut_repo.status(&ws_id, Some(&dirty_tree)).unwrap().unwrap();
+    assert_eq!(status.wc_commit.id(), wc_commit.id());
+    assert_eq!(
+        status.changes,
+        vec![
+            (added_path, DiffKind::Added),
+            (dirty_path, DiffKind::Added),
+            (modified_path, DiffKind::Modified),
+            (removed_path, DiffKind::Removed),
+        ]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_commits_reachable_from_branches(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // D
+    // | C main
+    // |/
+    // B
+    // | E feature
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_a]);
+    tx.mut_repo()
+        .set_local_branch("main".to_string(), RefTarget::Normal(commit_c.id().clone()));
+    tx.mut_repo().set_local_branch(
+        "feature".to_string(),
+        RefTarget::Normal(commit_e.id().clone()),
+    );
+    let repo = tx.commit().unwrap();
+
+    let reachable = repo.commits_reachable_from_branches().unwrap();
+    assert_eq!(
+        reachable,
+        hashset! {
+            repo.store().root_commit_id().clone(),
+            commit_a.id().clone(),
+            commit_b.id().clone(),
+            commit_c.id().clone(),
+            commit_e.id().clone(),
+        }
+    );
+    // D is only reachable from an anonymous head
+    assert!(repo.view().heads().contains(commit_d.id()));
+    assert!(!reachable.contains(commit_d.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_conflicted_branches(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit_a = write_random_commit(tx.mut_repo(), &settings);
+    let commit_b = write_random_commit(tx.mut_repo(), &settings);
+    let commit_c = write_random_commit(tx.mut_repo(), &settings);
+    tx.mut_repo()
+        .set_local_branch("main".to_string(), RefTarget::Normal(commit_a.id().clone()));
+    tx.mut_repo().set_local_branch(
+        "stable".to_string(),
+        RefTarget::Normal(commit_a.id().clone()),
+    );
+    let repo = tx.commit().unwrap();
+    assert_eq!(repo.conflicted_branches(), vec![]);
+
+    // Move "main" to different commits in concurrent operations
+    let mut tx1 = repo.start_transaction(&settings, "test");
+    tx1.mut_repo()
+        .set_local_branch("main".to_string(), RefTarget::Normal(commit_b.id().clone()));
+    tx1.commit().unwrap();
+    let mut tx2 = repo.start_transaction(&settings, "test");
+    tx2.mut_repo()
+        .set_local_branch("main".to_string(), RefTarget::Normal(commit_c.id().clone()));
+    tx2.commit().unwrap();
+
+    let repo = repo.reload_at_head(&settings).unwrap();
+    let conflicted = repo.conflicted_branches();
+    assert_eq!(conflicted.len(), 1);
+    let (name, target) = &conflicted[0];
+    assert_eq!(name, "main");
+    assert_eq!(target.removes(), [commit_a.id().clone()]);
+    assert_eq!(
+        target.adds().iter().sorted().collect_vec(),
+        [commit_b.id(), commit_c.id()]
+            .into_iter()
+            .sorted()
+            .collect_vec()
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_path_history(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let path = RepoPath::from_internal_string("file");
+    let other_path = RepoPath::from_internal_string("other");
+
+    // The file is changed in commits 1, 3, and 5
+    let trees = [
+        create_tree(repo, &[(&path, "1")]),
+        create_tree(repo, &[(&path, "1"), (&other_path, "2")]),
+        create_tree(repo, &[(&path, "3"), (&other_path, "2")]),
+        create_tree(repo, &[(&path, "3"), (&other_path, "4")]),
+        create_tree(repo, &[(&path, "5"), (&other_path, "4")]),
+    ];
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let mut parent_id = repo.store().root_commit_id().clone();
+    let mut commits = vec![];
+    for tree in &trees {
+        let commit = mut_repo
+            .new_commit(&settings, vec![parent_id], tree.id().clone())
+            .write()
+            .unwrap();
+        parent_id = commit.id().clone();
+        commits.push(commit);
+    }
+
+    let value_at = |commit: &Commit| commit.tree().path_value(&path).unwrap();
+    assert_eq!(
+        mut_repo.path_history(commits[4].id(), &path).unwrap(),
+        vec![
+            (commits[4].id().clone(), value_at(&commits[4])),
+            (commits[2].id().clone(), value_at(&commits[2])),
+            (commits[0].id().clone(), value_at(&commits[0])),
+        ]
+    );
+    assert_eq!(
+        mut_repo.path_history(commits[3].id(), &path).unwrap(),
+        vec![
+            (commits[2].id().clone(), value_at(&commits[2])),
+            (commits[0].id().clone(), value_at(&commits[0])),
+        ]
+    );
+    assert_eq!(
+        mut_repo
+            .path_history(commits[4].id(), &RepoPath::from_internal_string("missing"))
+            .unwrap(),
+        vec![]
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_describe_commit(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = create_random_commit(mut_repo, &settings)
+        .set_description("first line\n\nmore details\n")
+        .write()
+        .unwrap();
+    let commit2 = create_random_commit(mut_repo, &settings)
+        .set_description("")
+        .write()
+        .unwrap();
+    mut_repo.set_local_branch("main".to_string(), RefTarget::Normal(commit1.id().clone()));
+    mut_repo.set_local_branch(
+        "feature".to_string(),
+        RefTarget::Conflict {
+            removes: vec![],
+            adds: vec![commit1.id().clone(), commit2.id().clone()],
+        },
+    );
+    let short_id = |commit: &Commit| commit.id().hex()[0..12].to_string();
+    assert_eq!(
+        mut_repo.describe_commit(&commit1),
+        format!(
+            "{} (feature?? main) Test User: first line",
+            short_id(&commit1)
+        )
+    );
+    assert_eq!(
+        mut_repo.describe_commit(&commit2),
+        format!(
+            "{} (feature??) Test User: (no description set)",
+            short_id(&commit2)
+        )
+    );
+    mut_repo.remove_local_branch("feature");
+    mut_repo.remove_head(commit2.id());
+    assert_eq!(
+        mut_repo.describe_commit(&commit2),
+        format!(
+            "{} (hidden) Test User: (no description set)",
+            short_id(&commit2)
+        )
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_visible_commits(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(
+        repo.visible_commits(None).unwrap(),
+        vec![
+            commit_c.id().clone(),
+            commit_b.id().clone(),
+            commit_a.id().clone()
+        ]
+    );
+    assert_eq!(
+        repo.visible_commits(Some(2)).unwrap(),
+        vec![commit_c.id().clone(), commit_b.id().clone()]
+    );
+    assert_eq!(repo.visible_commits(Some(0)).unwrap(), vec![]);
+
+    // The rewritten commit is excluded
+    let mut tx = repo.start_transaction(&settings, "test");
+    let rewritten_c = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &commit_c)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        repo.visible_commits(Some(1)).unwrap(),
+        vec![rewritten_c.id().clone()]
+    );
+    assert!(!repo.visible_commits(None).unwrap().contains(commit_c.id()));
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_descendants_excluding(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // C E
+    // B D
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_d]);
+    let repo = tx.commit().unwrap();
+
+    // Excluding C keeps the B-C branch (and A) in place
+    assert_eq!(
+        repo.descendants_excluding(&[commit_a.id().clone()], &[commit_c.id().clone()])
+            .unwrap(),
+        vec![commit_e.id().clone(), commit_d.id().clone()]
+    );
+    // Excluding a commit outside the subgraph changes nothing
+    assert_eq!(
+        repo.descendants_excluding(&[commit_d.id().clone()], &[commit_b.id().clone()])
+            .unwrap(),
+        vec![commit_e.id().clone(), commit_d.id().clone()]
+    );
+    assert_eq!(
+        repo.descendants_excluding(&[commit_b.id().clone()], &[])
+            .unwrap(),
+        vec![commit_c.id().clone(), commit_b.id().clone()]
+    );
+    // Unknown commits are reported as errors rather than panicking
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_matches!(
+        repo.descendants_excluding(&[commit_b.id().clone()], &[unknown_id]),
+        Err(RevsetEvaluationError::Other(_))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_prefix_stats(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let mut write_with_change_id = |hex: &str| {
+        create_random_commit(mut_repo, &settings)
+            .set_change_id(ChangeId::from_hex(hex))
+            .write()
+            .unwrap()
+    };
+    // The first two change ids collide at length 2 but not at length 3.
+    let change_id_a = format!("aa0{}", "0".repeat(29));
+    let change_id_b = format!("aa1{}", "0".repeat(29));
+    let change_id_c = format!("bb0{}", "0".repeat(29));
+    write_with_change_id(&change_id_a);
+    write_with_change_id(&change_id_b);
+    write_with_change_id(&change_id_c);
+    let repo = tx.commit().unwrap();
+
+    // The root commit is visible too, so there are 4 commits
+    let num_commits = 4;
+    assert_eq!(repo.prefix_stats(2).unwrap().change_id_collisions, 2);
+    assert_eq!(repo.prefix_stats(3).unwrap().change_id_collisions, 0);
+    assert_eq!(
+        repo.prefix_stats(0).unwrap(),
+        PrefixStats {
+            commit_id_collisions: num_commits,
+            change_id_collisions: num_commits,
+        }
+    );
+    assert_eq!(
+        repo.prefix_stats(usize::MAX).unwrap(),
+        PrefixStats {
+            commit_id_collisions: 0,
+            change_id_collisions: 0,
+        }
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_fork_point(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // E
+    // D
+    // | C
+    // | B
+    // |/
+    // A F
+    // |/
+    // root
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_d]);
+    let commit_f = graph_builder.initial_commit();
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(
+        repo.fork_point(commit_c.id(), commit_e.id()).unwrap(),
+        Some(commit_a.id().clone())
+    );
+    assert_eq!(
+        repo.ahead_behind(commit_c.id(), commit_e.id()).unwrap(),
+        (2, 2)
+    );
+    assert_eq!(
+        repo.fork_point(commit_b.id(), commit_e.id()).unwrap(),
+        Some(commit_a.id().clone())
+    );
+    assert_eq!(
+        repo.ahead_behind(commit_b.id(), commit_e.id()).unwrap(),
+        (1, 2)
+    );
+    // One is an ancestor of the other
+    assert_eq!(
+        repo.fork_point(commit_a.id(), commit_c.id()).unwrap(),
+        Some(commit_a.id().clone())
+    );
+    assert_eq!(
+        repo.ahead_behind(commit_a.id(), commit_c.id()).unwrap(),
+        (0, 2)
+    );
+    assert_eq!(
+        repo.fork_point(commit_c.id(), commit_f.id()).unwrap(),
+        Some(repo.store().root_commit_id().clone())
+    );
+    assert_eq!(
+        repo.ahead_behind(commit_c.id(), commit_f.id()).unwrap(),
+        (3, 1)
+    );
+
+    // Unknown commits are reported as errors rather than panicking
+    let unknown_id = CommitId::new(vec![0xff; repo.store().commit_id_length()]);
+    assert_matches!(
+        repo.fork_point(commit_a.id(), &unknown_id),
+        Err(RevsetEvaluationError::Other(_))
+    );
+    assert_matches!(
+        repo.ahead_behind(&unknown_id, commit_a.id()),
+        Err(RevsetEvaluationError::Other(_))
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_fork_point_criss_cross(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // D E
+    // |X|
+    // B C
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_b, &commit_c]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_c, &commit_b]);
+    let repo = tx.commit().unwrap();
+
+    // Either B or C is a valid merge base, but the choice is stable
+    let fork_point = repo
+        .fork_point(commit_d.id(), commit_e.id())
+        .unwrap()
+        .unwrap();
+    assert!([commit_b.id(), commit_c.id()].contains(&&fork_point));
+    assert_eq!(
+        repo.ahead_behind(commit_d.id(), commit_e.id()).unwrap(),
+        (1, 1)
+    );
+    assert_eq!(
+        repo.fork_point(commit_e.id(), commit_d.id()).unwrap(),
+        Some(fork_point)
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_commit_tree_size(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let tree = create_tree(
+        repo,
+        &[
+            (&RepoPath::from_internal_string("a"), "hello"),
+            (&RepoPath::from_internal_string("e"), "12345678"),
+            (&RepoPath::from_internal_string("dir/b"), "abc"),
+            // Same contents as "a", so only counted once
+            (&RepoPath::from_internal_string("dir/c"), "hello"),
+        ],
+    );
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let repo = tx.commit().unwrap();
+
+    assert_eq!(repo.commit_tree_size(&commit).unwrap(), 5 + 8 + 3);
+    let sub_tree = tree.sub_tree(&RepoPathComponent::from("dir")).unwrap();
+    assert_eq!(repo.tree_size(&sub_tree).unwrap(), 3 + 5);
+    assert_eq!(
+        repo.commit_tree_size(&repo.store().root_commit()).unwrap(),
+        0
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_merge_conflict_summary(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let dir_path = RepoPath::from_internal_string("dir");
+    let dir_file_path = RepoPath::from_internal_string("dir/file");
+    let clean_path = RepoPath::from_internal_string("clean");
+    let base_tree = create_tree(
+        repo,
+        &[
+            (&file_path, "base"),
+            (&dir_path, "base"),
+            (&clean_path, "base"),
+        ],
+    );
+    // Both sides change the file's contents, and one side replaces "dir" by a
+    // directory while the other changes it as a file
+    let left_tree = create_tree(
+        repo,
+        &[
+            (&file_path, "left"),
+            (&dir_path, "left"),
+            (&clean_path, "left"),
+        ],
+    );
+    let right_tree = create_tree(
+        repo,
+        &[
+            (&file_path, "right"),
+            (&dir_file_path, "right"),
+            (&clean_path, "base"),
+        ],
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let base = mut_repo
+        .new_commit(&settings, vec![root_commit_id], base_tree.id().clone())
+        .write()
+        .unwrap();
+    let left = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], left_tree.id().clone())
+        .write()
+        .unwrap();
+    let right = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], right_tree.id().clone())
+        .write()
+        .unwrap();
+    let merge = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), right.id().clone()],
+            "merge",
+            false,
+        )
+        .unwrap();
+
+    let summary = mut_repo.merge_conflict_summary(&merge).unwrap();
+    assert_eq!(
+        summary,
+        MergeConflictSummary {
+            content_conflicts: 1,
+            type_conflicts: 1,
+        }
+    );
+    assert_eq!(summary.total(), 2);
+    assert_eq!(
+        mut_repo.merge_conflict_summary(&left).unwrap(),
+        MergeConflictSummary::default()
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_estimate_materialized_size(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let dir_path = RepoPath::from_internal_string("dir");
+    let dir_file_path = RepoPath::from_internal_string("dir/file");
+    let content = |changed: &str| {
+        (0..100)
+            .map(|i| match i {
+                10 | 50 => format!("{changed} line {i}\n"),
+                _ => format!("line {i}\n"),
+            })
+            .join("")
+    };
+    let base_tree = create_tree(repo, &[(&file_path, &content("base")), (&dir_path, "base")]);
+    let left_tree = create_tree(repo, &[(&file_path, &content("left")), (&dir_path, "left")]);
+    let right_tree = create_tree(
+        repo,
+        &[(&file_path, &content("right")), (&dir_file_path, "right")],
+    );
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let base = mut_repo
+        .new_commit(&settings, vec![root_commit_id], base_tree.id().clone())
+        .write()
+        .unwrap();
+    let left = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], left_tree.id().clone())
+        .write()
+        .unwrap();
+    let right = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], right_tree.id().clone())
+        .write()
+        .unwrap();
+    let merge = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), right.id().clone()],
+            "merge",
+            false,
+        )
+        .unwrap();
+
+    let mut actual_size = 0;
+    for (path, conflict_id) in merge.tree().conflicts() {
+        let conflict = repo.store().read_conflict(&path, &conflict_id).unwrap();
+        let mut content = vec![];
+        conflict
+            .materialize(repo.store(), &path, &mut content)
+            .unwrap();
+        actual_size += content.len() as u64;
+    }
+    let estimate = mut_repo.estimate_materialized_size(&merge).unwrap();
+    assert!(estimate >= actual_size, "{estimate} < {actual_size}");
+    assert!(
+        estimate <= 3 * actual_size,
+        "{estimate} > 3 * {actual_size}"
+    );
+
+    assert_eq!(mut_repo.estimate_materialized_size(&base).unwrap(), 0);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_walk_ancestors_where(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // F
+    // |\
+    // D E
+    // |/
+    // C
+    // B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_e = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_f = graph_builder.commit_with_parents(&[&commit_d, &commit_e]);
+    let repo = tx.commit().unwrap();
+
+    let selected = hashset! {
+        commit_a.id().clone(),
+        commit_c.id().clone(),
+        commit_e.id().clone(),
+    };
+    let walked = repo
+        .walk_ancestors_where(&commit_f, |commit| selected.contains(commit.id()))
+        .map(|commit| commit.id().clone())
+        .collect_vec();
+    assert_eq!(
+        walked,
+        vec![
+            commit_e.id().clone(),
+            commit_c.id().clone(),
+            commit_a.id().clone()
+        ]
+    );
+
+    // The walk stops early when the caller does
+    let visited = Cell::new(0);
+    let walked = repo
+        .walk_ancestors_where(&commit_f, |_| {
+            visited.set(visited.get() + 1);
+            true
+        })
+        .take(2)
+        .collect_vec();
+    assert_eq!(walked.len(), 2);
+    assert_eq!(visited.get(), 2);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_latest_conflict_free_ancestor(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("file");
+    let base_tree = create_tree(repo, &[(&file_path, "base")]);
+    let left_tree = create_tree(repo, &[(&file_path, "left")]);
+    let right_tree = create_tree(repo, &[(&file_path, "right")]);
+
+    // The merge and its child are conflicted, the merge's first parent isn't
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let base = mut_repo
+        .new_commit(
+            &settings,
+            vec![root_commit_id.clone()],
+            base_tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    let left = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], left_tree.id().clone())
+        .write()
+        .unwrap();
+    let right = mut_repo
+        .new_commit(&settings, vec![base.id().clone()], right_tree.id().clone())
+        .write()
+        .unwrap();
+    let merge = mut_repo
+        .create_merge(
+            &settings,
+            &[left.id().clone(), right.id().clone()],
+            "merge",
+            false,
+        )
+        .unwrap();
+    let child = mut_repo
+        .new_commit(&settings, vec![merge.id().clone()], merge.tree_id().clone())
+        .write()
+        .unwrap();
+    assert!(child.tree().has_conflict());
+
+    assert_eq!(
+        mut_repo.latest_conflict_free_ancestor(child.id()).unwrap(),
+        Some(left.id().clone())
+    );
+    assert_eq!(
+        mut_repo.latest_conflict_free_ancestor(right.id()).unwrap(),
+        Some(right.id().clone())
+    );
+    assert_eq!(
+        mut_repo
+            .latest_conflict_free_ancestor(&root_commit_id)
+            .unwrap(),
+        Some(root_commit_id)
+    );
+    // Backend errors are returned rather than treated as "no such commit"
+    let missing_id = CommitId::new(vec![0xff; mut_repo.store().commit_id_length()]);
+    assert_matches!(
+        mut_repo.latest_conflict_free_ancestor(&missing_id),
+        Err(BackendError::ObjectNotFound { .. })
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_iter_all_objects(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let store = repo.store();
+
+    let path = RepoPath::from_internal_string("file");
+    let tree = create_tree(repo, &[(&path, "contents")]);
+    let file_id = match tree.path_value(&path) {
+        Some(TreeValue::File { id, .. }) => id,
+        value => panic!("unexpected value {value:?}"),
+    };
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = tx
+        .mut_repo()
+        .new_commit(
+            &settings,
+            vec![store.root_commit_id().clone()],
+            tree.id().clone(),
+        )
+        .write()
+        .unwrap();
+    tx.commit().unwrap();
+    // Not reachable from any tree or commit
+    let unreachable_file_id = testutils::write_file(store, &path, "unreachable");
+
+    let all_objects: HashSet<_> = store.iter_all_objects().unwrap().collect();
+    for id in [
+        AnyObjectId::Commit(commit.id().clone()),
+        AnyObjectId::Tree(tree.id().clone()),
+        AnyObjectId::File(file_id),
+        AnyObjectId::File(unreachable_file_id),
+    ] {
+        assert!(all_objects.contains(&id), "{id:?} not found");
+    }
+    // The root commit is virtual
+    assert!(!all_objects.contains(&AnyObjectId::Commit(store.root_commit_id().clone())));
+}
@@ -118,7 +118,7 @@ fn test_resolve_symbol_commit_id() {
             .unwrap();
         commits.push(commit);
     }
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Test the test setup
     assert_eq!(
@@ -273,7 +273,7 @@ fn test_resolve_symbol_change_id(readonly: bool) {
 
     let _readonly_repo;
     let repo: &dyn Repo = if readonly {
-        _readonly_repo = tx.commit();
+        _readonly_repo = tx.commit().unwrap();
         _readonly_repo.as_ref()
     } else {
         tx.mut_repo()
@@ -314,7 +314,11 @@ fn test_resolve_symbol_change_id(readonly: bool) {
     );
     assert_matches!(
         resolve_symbol(repo, "zvly", None),
-        Err(RevsetResolutionError::AmbiguousChangeIdPrefix(s)) if s == "zvly"
+        Err(RevsetResolutionError::AmbiguousChangeIdPrefix { prefix, candidates, num_omitted })
+            if prefix == "zvly" && candidates == vec![
+                "zvlynszrxlvlwvkwkwsymrpypvtsszor".to_string(),
+                "zvlyxpuvtsoopsqzlkorrpqrszrqvlnx".to_string(),
+            ] && num_omitted == 0
     );
     assert_matches!(
         resolve_symbol(repo, "zvlyw", None),
@@ -2620,7 +2624,7 @@ fn test_reverse_graph_iterator() {
     let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
     let commit_e = graph_builder.commit_with_parents(&[&commit_c]);
     let commit_f = graph_builder.commit_with_parents(&[&commit_d, &commit_e]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let revset = revset_for_commits(
         repo.as_ref(),
@@ -13,10 +13,11 @@
 // limitations under the License.
 
 use itertools::Itertools as _;
+use jj_lib::cancellation::CancellationToken;
 use jj_lib::op_store::{RefTarget, WorkspaceId};
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
-use jj_lib::rewrite::DescendantRebaser;
+use jj_lib::rewrite::{DescendantRebaser, RebaseStats};
 use maplit::{hashmap, hashset};
 use test_case::test_case;
 use testutils::{
@@ -71,6 +72,174 @@ fn test_rebase_descendants_sideways(use_git: bool) {
     );
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_rebase_descendants_cancelled(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // Commit B was replaced by commit F. The rebase is cancelled after commit C
+    // has been rebased, so commit D should be left in place.
+    //
+    // F
+    // | D
+    // | C
+    // | B
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_f = graph_builder.commit_with_parents(&[&commit_a]);
+
+    let cancel = CancellationToken::new();
+    let mut rebaser = DescendantRebaser::new(
+        &settings,
+        tx.mut_repo(),
+        hashmap! {
+            commit_b.id().clone() => hashset!{commit_f.id().clone()}
+        },
+        hashset! {},
+    );
+    let new_commit_c = assert_rebased(rebaser.rebase_next().unwrap(), &commit_c, &[&commit_f]);
+    cancel.cancel();
+    assert!(!rebaser.rebase_all_cancellable(&cancel).unwrap());
+    assert_eq!(rebaser.rebased().len(), 1);
+    assert!(rebaser.rebase_next().unwrap().is_none());
+
+    assert!(!tx.mut_repo().has_rewrites());
+    assert_eq!(
+        *tx.mut_repo().view().heads(),
+        hashset! {
+            new_commit_c.id().clone(),
+            commit_d.id().clone(),
+        }
+    );
+    let repo = tx.commit().unwrap();
+    assert_eq!(
+        *repo.view().heads(),
+        hashset! {
+            new_commit_c.id().clone(),
+            commit_d.id().clone(),
+        }
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_rebase_descendants_not_cancelled(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // Commit B was replaced by commit F. Commits C and D should be rebased.
+    //
+    // F
+    // | D
+    // | C
+    // | B
+    // |/
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let _commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let commit_f = graph_builder.commit_with_parents(&[&commit_a]);
+
+    let mut rebaser = DescendantRebaser::new(
+        &settings,
+        tx.mut_repo(),
+        hashmap! {
+            commit_b.id().clone() => hashset!{commit_f.id().clone()}
+        },
+        hashset! {},
+    );
+    assert!(rebaser
+        .rebase_all_cancellable(&CancellationToken::new())
+        .unwrap());
+    assert_eq!(rebaser.rebased().len(), 2);
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_rebase_all_descendants(use_git: bool) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+
+    // Commit A is rewritten. Commits B, C, and D should all be rebased in a single
+    // call.
+    //
+    // D
+    // C
+    // B
+    // A
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_c]);
+    let repo = tx.commit().unwrap();
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a2 = mut_repo
+        .rewrite_commit(&settings, &commit_a)
+        .set_description("rewritten")
+        .write()
+        .unwrap();
+    let stats = mut_repo.rebase_all_descendants(&settings).unwrap();
+    assert_eq!(
+        stats,
+        RebaseStats {
+            num_rebased: 3,
+            num_skipped: 0,
+        }
+    );
+    assert!(!mut_repo.has_rewrites());
+    let heads = mut_repo.view().heads().clone();
+    assert_eq!(heads.len(), 1);
+    let new_commit_d = repo
+        .store()
+        .get_commit(heads.iter().next().unwrap())
+        .unwrap();
+    assert_eq!(new_commit_d.change_id(), commit_d.change_id());
+    let new_commit_c = new_commit_d.parents()[0].clone();
+    assert_eq!(new_commit_c.change_id(), commit_c.change_id());
+    let new_commit_b = new_commit_c.parents()[0].clone();
+    assert_eq!(new_commit_b.change_id(), commit_b.change_id());
+    assert_eq!(new_commit_b.parent_ids(), &[commit_a2.id().clone()]);
+
+    // Nothing more to do
+    assert_eq!(
+        mut_repo.rebase_all_descendants(&settings).unwrap(),
+        RebaseStats::default()
+    );
+
+    // A divergent rewrite leaves the descendants in place
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a2 = write_random_commit(mut_repo, &settings);
+    let commit_a3 = write_random_commit(mut_repo, &settings);
+    mut_repo.record_rewritten_commit(commit_a.id().clone(), commit_a2.id().clone());
+    mut_repo.record_rewritten_commit(commit_a.id().clone(), commit_a3.id().clone());
+    assert_eq!(
+        mut_repo.rebase_all_descendants(&settings).unwrap(),
+        RebaseStats {
+            num_rebased: 0,
+            num_skipped: 1,
+        }
+    );
+    assert!(mut_repo.view().heads().contains(commit_d.id()));
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_rebase_descendants_forward(use_git: bool) {
@@ -936,7 +1105,7 @@ fn test_rebase_descendants_basic_branch_update() {
     let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
     tx.mut_repo()
         .set_local_branch("main".to_string(), RefTarget::Normal(commit_b.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_b2 = tx
@@ -979,7 +1148,7 @@ fn test_rebase_descendants_branch_move_two_steps() {
     let commit_c = graph_builder.commit_with_parents(&[&commit_b]);
     tx.mut_repo()
         .set_local_branch("main".to_string(), RefTarget::Normal(commit_c.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_b2 = tx
@@ -1032,7 +1201,7 @@ fn test_rebase_descendants_basic_branch_update_with_non_local_branch() {
     );
     tx.mut_repo()
         .set_tag("v1".to_string(), RefTarget::Normal(commit_b.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_b2 = tx
@@ -1081,7 +1250,7 @@ fn test_rebase_descendants_update_branch_after_abandon() {
     let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
     tx.mut_repo()
         .set_local_branch("main".to_string(), RefTarget::Normal(commit_b.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
@@ -1117,7 +1286,7 @@ fn test_rebase_descendants_update_branches_after_divergent_rewrite() {
     let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
     tx.mut_repo()
         .set_local_branch("main".to_string(), RefTarget::Normal(commit_b.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_b2 = tx
@@ -1187,7 +1356,7 @@ fn test_rebase_descendants_rewrite_updates_branch_conflict() {
             adds: vec![commit_b.id().clone(), commit_c.id().clone()],
         },
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_a2 = tx
@@ -1268,7 +1437,7 @@ fn test_rebase_descendants_rewrite_resolves_branch_conflict() {
             adds: vec![commit_b.id().clone(), commit_c.id().clone()],
         },
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_b2 = tx
@@ -1311,7 +1480,7 @@ fn test_rebase_descendants_branch_delete_modify_abandon() {
             adds: vec![commit_b.id().clone()],
         },
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
@@ -1350,7 +1519,7 @@ fn test_rebase_descendants_update_checkout(use_git: bool) {
     tx.mut_repo()
         .set_wc_commit(ws3_id.clone(), commit_a.id().clone())
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     let commit_c = tx
@@ -1360,7 +1529,7 @@ fn test_rebase_descendants_update_checkout(use_git: bool) {
         .write()
         .unwrap();
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Workspaces 1 and 2 had B checked out, so they get updated to C. Workspace 3
     // had A checked out, so it doesn't get updated.
@@ -1400,12 +1569,12 @@ fn test_rebase_descendants_update_checkout_abandoned(use_git: bool) {
     tx.mut_repo()
         .set_wc_commit(ws3_id.clone(), commit_a.id().clone())
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     tx.mut_repo().record_abandoned_commit(commit_b.id().clone());
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     // Workspaces 1 and 2 had B checked out, so they get updated to the same new
     // commit on top of C. Workspace 3 had A checked out, so it doesn't get updated.
@@ -1454,12 +1623,12 @@ fn test_rebase_descendants_update_checkout_abandoned_merge(use_git: bool) {
     tx.mut_repo()
         .set_wc_commit(workspace_id.clone(), commit_d.id().clone())
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx = repo.start_transaction(&settings, "test");
     tx.mut_repo().record_abandoned_commit(commit_d.id().clone());
     tx.mut_repo().rebase_descendants(&settings).unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let new_checkout_id = repo.view().get_wc_commit_id(&workspace_id).unwrap();
     let checkout = repo.store().get_commit(new_checkout_id).unwrap();
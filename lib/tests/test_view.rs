@@ -50,7 +50,7 @@ fn test_heads_fork(use_git: bool) {
     let initial = graph_builder.initial_commit();
     let child1 = graph_builder.commit_with_parents(&[&initial]);
     let child2 = graph_builder.commit_with_parents(&[&initial]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     assert_eq!(
         *repo.view().heads(),
@@ -74,7 +74,7 @@ fn test_heads_merge(use_git: bool) {
     let child1 = graph_builder.commit_with_parents(&[&initial]);
     let child2 = graph_builder.commit_with_parents(&[&initial]);
     let merge = graph_builder.commit_with_parents(&[&child1, &child2]);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     assert_eq!(*repo.view().heads(), hashset! {merge.id().clone()});
 }
@@ -97,7 +97,7 @@ fn test_merge_views_heads() {
     mut_repo.add_public_head(&public_head_remove_tx1);
     let public_head_remove_tx2 = write_random_commit(mut_repo, &settings);
     mut_repo.add_public_head(&public_head_remove_tx2);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     tx1.mut_repo().remove_head(head_remove_tx1.id());
@@ -106,7 +106,7 @@ fn test_merge_views_heads() {
     let head_add_tx1 = write_random_commit(tx1.mut_repo(), &settings);
     let public_head_add_tx1 = write_random_commit(tx1.mut_repo(), &settings);
     tx1.mut_repo().add_public_head(&public_head_add_tx1);
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     tx2.mut_repo().remove_head(head_remove_tx2.id());
@@ -115,7 +115,7 @@ fn test_merge_views_heads() {
     let head_add_tx2 = write_random_commit(tx2.mut_repo(), &settings);
     let public_head_add_tx2 = write_random_commit(tx2.mut_repo(), &settings);
     tx2.mut_repo().add_public_head(&public_head_add_tx2);
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
 
@@ -184,7 +184,7 @@ fn test_merge_views_checkout() {
         .mut_repo()
         .set_wc_commit(ws5_id.clone(), commit1.id().clone())
         .unwrap();
-    let repo = initial_tx.commit();
+    let repo = initial_tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     tx1.mut_repo()
@@ -200,7 +200,7 @@ fn test_merge_views_checkout() {
     tx1.mut_repo()
         .set_wc_commit(ws6_id.clone(), commit2.id().clone())
         .unwrap();
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     tx2.mut_repo()
@@ -219,7 +219,7 @@ fn test_merge_views_checkout() {
     // Make sure the end time different, assuming the clock has sub-millisecond
     // precision.
     std::thread::sleep(std::time::Duration::from_millis(1));
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
 
@@ -267,7 +267,7 @@ fn test_merge_views_branches() {
         "feature".to_string(),
         RefTarget::Normal(feature_branch_local_tx0.id().clone()),
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let main_branch_local_tx1 = write_random_commit(tx1.mut_repo(), &settings);
@@ -285,7 +285,7 @@ fn test_merge_views_branches() {
         "feature".to_string(),
         RefTarget::Normal(feature_branch_tx1.id().clone()),
     );
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     let main_branch_local_tx2 = write_random_commit(tx2.mut_repo(), &settings);
@@ -298,7 +298,7 @@ fn test_merge_views_branches() {
         "origin".to_string(),
         RefTarget::Normal(main_branch_origin_tx1.id().clone()),
     );
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
     let expected_main_branch = BranchTarget {
@@ -341,7 +341,7 @@ fn test_merge_views_tags() {
     mut_repo.set_tag("v1.0".to_string(), RefTarget::Normal(v1_tx0.id().clone()));
     let v2_tx0 = write_random_commit(mut_repo, &settings);
     mut_repo.set_tag("v2.0".to_string(), RefTarget::Normal(v2_tx0.id().clone()));
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let v1_tx1 = write_random_commit(tx1.mut_repo(), &settings);
@@ -350,13 +350,13 @@ fn test_merge_views_tags() {
     let v2_tx1 = write_random_commit(tx1.mut_repo(), &settings);
     tx1.mut_repo()
         .set_tag("v2.0".to_string(), RefTarget::Normal(v2_tx1.id().clone()));
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     let v1_tx2 = write_random_commit(tx2.mut_repo(), &settings);
     tx2.mut_repo()
         .set_tag("v1.0".to_string(), RefTarget::Normal(v1_tx2.id().clone()));
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
     let expected_v1 = RefTarget::Conflict {
@@ -393,7 +393,7 @@ fn test_merge_views_git_refs() {
         "refs/heads/feature".to_string(),
         RefTarget::Normal(feature_branch_tx0.id().clone()),
     );
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let main_branch_tx1 = write_random_commit(tx1.mut_repo(), &settings);
@@ -406,7 +406,7 @@ fn test_merge_views_git_refs() {
         "refs/heads/feature".to_string(),
         RefTarget::Normal(feature_branch_tx1.id().clone()),
     );
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     let main_branch_tx2 = write_random_commit(tx2.mut_repo(), &settings);
@@ -414,7 +414,7 @@ fn test_merge_views_git_refs() {
         "refs/heads/main".to_string(),
         RefTarget::Normal(main_branch_tx2.id().clone()),
     );
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
     let expected_main_branch = RefTarget::Conflict {
@@ -443,19 +443,19 @@ fn test_merge_views_git_heads() {
     let tx0_head = write_random_commit(tx0.mut_repo(), &settings);
     tx0.mut_repo()
         .set_git_head(RefTarget::Normal(tx0_head.id().clone()));
-    let repo = tx0.commit();
+    let repo = tx0.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let tx1_head = write_random_commit(tx1.mut_repo(), &settings);
     tx1.mut_repo()
         .set_git_head(RefTarget::Normal(tx1_head.id().clone()));
-    tx1.commit();
+    tx1.commit().unwrap();
 
     let mut tx2 = repo.start_transaction(&settings, "test");
     let tx2_head = write_random_commit(tx2.mut_repo(), &settings);
     tx2.mut_repo()
         .set_git_head(RefTarget::Normal(tx2_head.id().clone()));
-    tx2.commit();
+    tx2.commit().unwrap();
 
     let repo = repo.reload_at_head(&settings).unwrap();
     let expected_git_head = RefTarget::Conflict {
@@ -469,7 +469,7 @@ fn commit_transactions(settings: &UserSettings, txs: Vec<Transaction>) -> Arc<Re
     let repo_loader = txs[0].base_repo().loader();
     let mut op_ids = vec![];
     for tx in txs {
-        op_ids.push(tx.commit().op_id().clone());
+        op_ids.push(tx.commit().unwrap().op_id().clone());
         std::thread::sleep(std::time::Duration::from_millis(1));
     }
     let repo = repo_loader.load_at_head(settings).unwrap();
@@ -491,7 +491,7 @@ fn test_merge_views_divergent() {
 
     let mut tx = test_repo.repo.start_transaction(&settings, "test");
     let commit_a = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let commit_a2 = tx1
@@ -530,7 +530,7 @@ fn test_merge_views_child_on_rewritten(child_first: bool) {
 
     let mut tx = test_repo.repo.start_transaction(&settings, "test");
     let commit_a = write_random_commit(tx.mut_repo(), &settings);
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let commit_b = create_random_commit(tx1.mut_repo(), &settings)
@@ -580,7 +580,7 @@ fn test_merge_views_child_on_rewritten_divergent(on_rewritten: bool, child_first
         .set_change_id(commit_a2.change_id().clone())
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let parent = if on_rewritten { &commit_a2 } else { &commit_a3 };
@@ -636,7 +636,7 @@ fn test_merge_views_child_on_abandoned(child_first: bool) {
         .set_parents(vec![commit_a.id().clone()])
         .write()
         .unwrap();
-    let repo = tx.commit();
+    let repo = tx.commit().unwrap();
 
     let mut tx1 = repo.start_transaction(&settings, "test");
     let commit_c = create_random_commit(tx1.mut_repo(), &settings)
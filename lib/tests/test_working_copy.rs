@@ -20,20 +20,25 @@ use std::os::unix::fs::PermissionsExt;
 use std::os::unix::net::UnixListener;
 use std::sync::Arc;
 
+use assert_matches::assert_matches;
 use itertools::Itertools;
-use jj_lib::backend::{TreeId, TreeValue};
+use jj_lib::backend::{FileId, TreeId, TreeValue};
 use jj_lib::conflicts::Conflict;
 use jj_lib::fsmonitor::FsmonitorKind;
-#[cfg(unix)]
-use jj_lib::op_store::OperationId;
-use jj_lib::op_store::WorkspaceId;
+use jj_lib::local_backend::LocalBackend;
+use jj_lib::op_store::{OperationId, WorkspaceId};
 use jj_lib::repo::{ReadonlyRepo, Repo};
 use jj_lib::repo_path::{RepoPath, RepoPathComponent, RepoPathJoin};
 use jj_lib::settings::UserSettings;
+use jj_lib::store::Store;
+use jj_lib::tree::Tree;
 use jj_lib::tree_builder::TreeBuilder;
-use jj_lib::working_copy::{LockedWorkingCopy, SnapshotOptions, WorkingCopy};
+use jj_lib::working_copy::{
+    assert_resolved, reset_to, AssertResolvedError, CheckoutStats, LockedWorkingCopy,
+    SnapshotOptions, WorkingCopy,
+};
 use test_case::test_case;
-use testutils::{write_random_commit, TestWorkspace};
+use testutils::{write_random_commit, CountingBackend, TestRepo, TestWorkspace};
 
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
@@ -162,7 +167,7 @@ fn test_checkout_file_transitions(use_git: bool) {
             Kind::GitSubmodule => {
                 let mut tx = repo.start_transaction(settings, "test");
                 let id = write_random_commit(tx.mut_repo(), settings).id().clone();
-                tx.commit();
+                tx.commit().unwrap();
                 TreeValue::GitSubmodule(id)
             }
         };
@@ -273,6 +278,44 @@ fn test_checkout_file_transitions(use_git: bool) {
     }
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_checkout_resolvable_conflict(use_git: bool) {
+    // Test that a conflict that simplifies to a single value is checked out as a
+    // plain file without conflict markers
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings, use_git);
+    let repo = &test_workspace.repo;
+    let store = repo.store().clone();
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+
+    let path = RepoPath::from_internal_string("file");
+    let base_file_id = testutils::write_file(&store, &path, "base\n");
+    let left_file_id = testutils::write_file(&store, &path, "left\n");
+    let file_value = |id: &FileId| {
+        Some(TreeValue::File {
+            id: id.clone(),
+            executable: false,
+        })
+    };
+    let conflict = Conflict::new(
+        vec![file_value(&base_file_id)],
+        vec![file_value(&left_file_id), file_value(&base_file_id)],
+    );
+    let conflict_id = store.write_conflict(&path, &conflict).unwrap();
+    let mut tree_builder = store.tree_builder(store.empty_tree_id().clone());
+    tree_builder.set(path.clone(), TreeValue::Conflict(conflict_id));
+    let tree_id = tree_builder.write_tree();
+    let tree = store.get_tree(&RepoPath::root(), &tree_id).unwrap();
+
+    let wc = test_workspace.workspace.working_copy_mut();
+    wc.check_out(repo.op_id().clone(), None, &tree).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path.to_fs_path(&workspace_root)).unwrap(),
+        "left\n"
+    );
+}
+
 #[test_case(false ; "local backend")]
 #[test_case(true ; "git backend")]
 fn test_tree_builder_file_directory_transition(use_git: bool) {
@@ -387,6 +430,154 @@ fn test_reset() {
     locked_wc.discard();
 }
 
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_reset_to(use_git: bool) {
+    // Test that reset_to() only touches the files that differ between the trees
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let store = repo.store();
+    let temp_dir = testutils::new_temp_dir();
+    let workdir = temp_dir.path();
+
+    let unchanged_path = RepoPath::from_internal_string("unchanged");
+    let modified_path = RepoPath::from_internal_string("dir/modified");
+    let removed_path = RepoPath::from_internal_string("dir/removed");
+    let added_path = RepoPath::from_internal_string("added");
+    let untracked_path = RepoPath::from_internal_string("untracked");
+    let empty_tree = store
+        .get_tree(&RepoPath::root(), store.empty_tree_id())
+        .unwrap();
+    let tree1 = testutils::create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "before"),
+            (&removed_path, "removed"),
+        ],
+    );
+    let tree2 = testutils::create_tree(
+        repo,
+        &[
+            (&unchanged_path, "unchanged"),
+            (&modified_path, "after"),
+            (&added_path, "added"),
+        ],
+    );
+
+    let stats = reset_to(store, workdir, &tree1, &empty_tree).unwrap();
+    assert_eq!(
+        stats,
+        CheckoutStats {
+            updated_files: 0,
+            added_files: 3,
+            removed_files: 0,
+        }
+    );
+
+    // Edit the unchanged file on disk so we can tell whether it gets rewritten
+    std::fs::write(unchanged_path.to_fs_path(workdir), "edited").unwrap();
+    std::fs::write(untracked_path.to_fs_path(workdir), "untracked").unwrap();
+
+    let stats = reset_to(store, workdir, &tree2, &tree1).unwrap();
+    assert_eq!(
+        stats,
+        CheckoutStats {
+            updated_files: 1,
+            added_files: 1,
+            removed_files: 1,
+        }
+    );
+    let read = |path: &RepoPath| std::fs::read_to_string(path.to_fs_path(workdir)).unwrap();
+    assert_eq!(read(&unchanged_path), "edited");
+    assert_eq!(read(&modified_path), "after");
+    assert_eq!(read(&added_path), "added");
+    assert_eq!(read(&untracked_path), "untracked");
+    assert!(!removed_path.to_fs_path(workdir).exists());
+
+    // Only the executable bit changes
+    let mut tree_builder = store.tree_builder(tree2.id().clone());
+    testutils::write_executable_file(&mut tree_builder, &modified_path, "after");
+    let tree3 = store
+        .get_tree(&RepoPath::root(), &tree_builder.write_tree())
+        .unwrap();
+    let stats = reset_to(store, workdir, &tree3, &tree2).unwrap();
+    assert_eq!(
+        stats,
+        CheckoutStats {
+            updated_files: 1,
+            added_files: 0,
+            removed_files: 0,
+        }
+    );
+    assert_eq!(read(&modified_path), "after");
+    #[cfg(unix)]
+    assert_ne!(
+        std::fs::metadata(modified_path.to_fs_path(workdir))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o111,
+        0
+    );
+}
+
+#[test_case(false ; "local backend")]
+#[test_case(true ; "git backend")]
+fn test_assert_resolved(use_git: bool) {
+    let test_repo = TestRepo::init(use_git);
+    let repo = &test_repo.repo;
+    let store = repo.store();
+    let temp_dir = testutils::new_temp_dir();
+    let workdir = temp_dir.path();
+
+    let clean_path = RepoPath::from_internal_string("clean");
+    let markers_path = RepoPath::from_internal_string("dir/markers");
+    let markers = "<<<<<<<\n%%%%%%%\n-a\n+b\n+++++++\nc\n>>>>>>>\n";
+    let base_tree = testutils::create_tree(repo, &[(&clean_path, "clean\n")]);
+    let tree = testutils::create_tree(repo, &[(&clean_path, "clean\n"), (&markers_path, "b\n")]);
+    let unresolved_paths =
+        |base_tree: &Tree, tree: &Tree| match assert_resolved(store, workdir, base_tree, tree) {
+            Err(AssertResolvedError::Unresolved(err)) => err.paths,
+            result => panic!("unexpected result: {result:?}"),
+        };
+
+    // Files missing from the working copy are checked in the store
+    assert_resolved(store, workdir, &base_tree, &tree).unwrap();
+    std::fs::create_dir(workdir.join("dir")).unwrap();
+    std::fs::write(markers_path.to_fs_path(workdir), markers).unwrap();
+    assert_eq!(
+        unresolved_paths(&base_tree, &tree),
+        vec![markers_path.clone()]
+    );
+    // Only files that changed are checked
+    assert_resolved(store, workdir, &tree, &tree).unwrap();
+    std::fs::write(markers_path.to_fs_path(workdir), "b\n").unwrap();
+    assert_resolved(store, workdir, &base_tree, &tree).unwrap();
+
+    // Markers of conflicts with more than two sides are detected too
+    let markers_3_sided = "<<<<<<<\n%%%%%%%\n-a\n+b\n+++++++\nc\n%%%%%%%\n-a\n+d\n>>>>>>>\n";
+    std::fs::write(markers_path.to_fs_path(workdir), markers_3_sided).unwrap();
+    assert_eq!(
+        unresolved_paths(&base_tree, &tree),
+        vec![markers_path.clone()]
+    );
+
+    let tree = testutils::create_tree(repo, &[(&clean_path, "clean\n"), (&markers_path, markers)]);
+    std::fs::remove_file(markers_path.to_fs_path(workdir)).unwrap();
+    assert_eq!(
+        unresolved_paths(&base_tree, &tree),
+        vec![markers_path.clone()]
+    );
+
+    // Files that can't be read are reported rather than skipped
+    std::fs::create_dir(markers_path.to_fs_path(workdir)).unwrap();
+    assert_matches!(
+        assert_resolved(store, workdir, &base_tree, &tree),
+        Err(AssertResolvedError::ReadFile { path, .. }) if path == markers_path
+    );
+}
+
 #[test]
 fn test_checkout_discard() {
     // Start a mutation, do a checkout, and then discard the mutation. The working
@@ -467,6 +658,74 @@ fn test_snapshot_racy_timestamps(use_git: bool) {
     }
 }
 
+#[test]
+fn test_snapshot_skips_unchanged_files() {
+    // Tests that the file states persisted in the working copy's state file let
+    // a snapshot skip files whose mtime and size haven't changed.
+    let temp_dir = testutils::new_temp_dir();
+    let store_path = temp_dir.path().join("store");
+    let working_copy_path = temp_dir.path().join("repo");
+    let state_path = temp_dir.path().join("state");
+    for path in [&store_path, &working_copy_path, &state_path] {
+        std::fs::create_dir(path).unwrap();
+    }
+    let store = Store::new(Box::new(CountingBackend::new(Box::new(
+        LocalBackend::init(&store_path),
+    ))));
+    let backend = || {
+        store
+            .backend_impl()
+            .downcast_ref::<CountingBackend>()
+            .unwrap()
+    };
+
+    let file_paths = (0..5)
+        .map(|i| RepoPath::from_internal_string(&format!("file{i}")))
+        .collect_vec();
+    for path in &file_paths {
+        testutils::write_working_copy_file(&working_copy_path, path, "contents");
+    }
+    // Make sure the files' mtimes are older than the state file will be
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    let mut wc = WorkingCopy::init(
+        store.clone(),
+        working_copy_path.clone(),
+        state_path.clone(),
+        OperationId::from_hex("abc123"),
+        WorkspaceId::default(),
+    );
+    let mut locked_wc = wc.start_mutation();
+    let tree_id = locked_wc
+        .snapshot(SnapshotOptions::empty_for_test())
+        .unwrap();
+    locked_wc.finish(OperationId::from_hex("abc123"));
+    assert_eq!(backend().file_writes(), file_paths.len());
+
+    // Snapshotting again, even from a freshly loaded working copy, doesn't read
+    // any of the unchanged files
+    let mut wc = WorkingCopy::load(store.clone(), working_copy_path.clone(), state_path);
+    assert_eq!(wc.current_tree_id(), &tree_id);
+    let mut locked_wc = wc.start_mutation();
+    let new_tree_id = locked_wc
+        .snapshot(SnapshotOptions::empty_for_test())
+        .unwrap();
+    locked_wc.discard();
+    assert_eq!(new_tree_id, tree_id);
+    assert_eq!(backend().file_writes(), file_paths.len());
+    assert_eq!(backend().file_reads(), 0);
+
+    // A modified file is picked up
+    testutils::write_working_copy_file(&working_copy_path, &file_paths[0], "changed");
+    let mut locked_wc = wc.start_mutation();
+    let new_tree_id = locked_wc
+        .snapshot(SnapshotOptions::empty_for_test())
+        .unwrap();
+    locked_wc.discard();
+    assert_ne!(new_tree_id, tree_id);
+    assert_eq!(backend().file_writes(), file_paths.len() + 1);
+}
+
 #[cfg(unix)]
 #[test]
 fn test_snapshot_special_file() {
@@ -755,7 +1014,7 @@ fn test_gitsubmodule() {
 
     let mut tx = repo.start_transaction(&settings, "create submodule commit");
     let submodule_id = write_random_commit(tx.mut_repo(), &settings).id().clone();
-    tx.commit();
+    tx.commit().unwrap();
 
     tree_builder.set(
         submodule_path.clone(),
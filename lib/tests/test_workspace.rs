@@ -13,8 +13,12 @@
 // limitations under the License.
 
 use assert_matches::assert_matches;
+use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::op_store::WorkspaceId;
 use jj_lib::repo::{Repo, StoreFactories};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::settings::UserSettings;
+use jj_lib::working_copy::SnapshotOptions;
 use jj_lib::workspace::{Workspace, WorkspaceLoadError};
 use test_case::test_case;
 use testutils::TestWorkspace;
@@ -73,3 +77,96 @@ fn test_init_additional_workspace(use_git: bool) {
     );
     assert_eq!(same_workspace.workspace_root(), ws2.workspace_root());
 }
+
+#[test_case(false, false ; "local backend, auto-snapshot disabled")]
+#[test_case(false, true ; "local backend, auto-snapshot enabled")]
+#[test_case(true, true ; "git backend, auto-snapshot enabled")]
+fn test_start_transaction_auto_snapshot(use_git: bool, auto_snapshot: bool) {
+    // Test that Workspace::start_transaction() snapshots a dirty working copy
+    // into the working-copy commit first if enabled
+    let settings = UserSettings::from_config(
+        config::Config::builder()
+            .add_source(testutils::user_settings().config().clone())
+            .add_source(config::File::from_str(
+                &format!("ui.auto-snapshot = {auto_snapshot}"),
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap(),
+    );
+    let mut test_workspace = TestWorkspace::init(&settings, use_git);
+    let workspace = &mut test_workspace.workspace;
+    let repo = test_workspace.repo.clone();
+    let workspace_id = workspace.workspace_id().clone();
+    let old_wc_commit_id = repo.view().get_wc_commit_id(&workspace_id).unwrap().clone();
+
+    // Nothing is snapshotted if the working copy is clean
+    let tx = workspace
+        .start_transaction(&repo, &settings, SnapshotOptions::empty_for_test(), "test")
+        .unwrap();
+    assert_eq!(tx.base_repo().op_id(), repo.op_id());
+    drop(tx);
+
+    let file_path = RepoPath::from_internal_string("file");
+    testutils::write_working_copy_file(workspace.workspace_root(), &file_path, "contents");
+    let mut tx = workspace
+        .start_transaction(
+            &repo,
+            &settings,
+            SnapshotOptions::empty_for_test(),
+            "check out",
+        )
+        .unwrap();
+    let base_repo = tx.base_repo().clone();
+    let wc_commit_id = base_repo
+        .view()
+        .get_wc_commit_id(&workspace_id)
+        .unwrap()
+        .clone();
+    let wc_commit = base_repo.store().get_commit(&wc_commit_id).unwrap();
+    if auto_snapshot {
+        assert_ne!(base_repo.op_id(), repo.op_id());
+        assert_ne!(wc_commit_id, old_wc_commit_id);
+        assert!(wc_commit.tree().path_value(&file_path).is_some());
+    } else {
+        assert_eq!(base_repo.op_id(), repo.op_id());
+        assert_eq!(wc_commit_id, old_wc_commit_id);
+        assert_eq!(wc_commit.tree().path_value(&file_path), None);
+    }
+
+    // A subsequent checkout keeps the snapshotted commit
+    let root_commit = base_repo.store().root_commit();
+    tx.mut_repo()
+        .check_out(workspace_id, &settings, &root_commit)
+        .unwrap();
+    tx.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo = tx.commit().unwrap();
+    assert_eq!(repo.view().heads().contains(&wc_commit_id), auto_snapshot);
+}
+
+#[test]
+fn test_snapshot_working_copy_base_ignores() {
+    // Test that Workspace::snapshot_working_copy() doesn't snapshot files
+    // ignored by the base ignores
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings, false);
+    let workspace = &mut test_workspace.workspace;
+    let repo = test_workspace.repo.clone();
+    let workspace_id = workspace.workspace_id().clone();
+
+    let added_path = RepoPath::from_internal_string("added");
+    let ignored_path = RepoPath::from_internal_string("ignored");
+    testutils::write_working_copy_file(workspace.workspace_root(), &added_path, "contents");
+    testutils::write_working_copy_file(workspace.workspace_root(), &ignored_path, "contents");
+    let options = SnapshotOptions {
+        base_ignores: GitIgnoreFile::empty().chain("", b"/ignored\n"),
+        ..SnapshotOptions::empty_for_test()
+    };
+    let repo = workspace
+        .snapshot_working_copy(&repo, &settings, options)
+        .unwrap();
+    let wc_commit_id = repo.view().get_wc_commit_id(&workspace_id).unwrap();
+    let wc_commit = repo.store().get_commit(wc_commit_id).unwrap();
+    assert!(wc_commit.tree().path_value(&added_path).is_some());
+    assert_eq!(wc_commit.tree().path_value(&ignored_path), None);
+}
@@ -12,13 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::any::Any;
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Once};
 
 use itertools::Itertools;
-use jj_lib::backend::{Backend, BackendInitError, FileId, ObjectId, TreeId, TreeValue};
+use jj_lib::backend::{
+    self, AnyObjectId, Backend, BackendInitError, BackendResult, ChangeId, CommitId, Conflict,
+    ConflictId, FileId, ObjectId, SymlinkId, TreeId, TreeValue,
+};
 use jj_lib::commit::Commit;
 use jj_lib::commit_builder::CommitBuilder;
 use jj_lib::git_backend::GitBackend;
@@ -26,7 +31,7 @@ use jj_lib::local_backend::LocalBackend;
 use jj_lib::repo::{MutableRepo, ReadonlyRepo, Repo, RepoLoader, StoreFactories};
 use jj_lib::repo_path::RepoPath;
 use jj_lib::rewrite::RebasedDescendant;
-use jj_lib::settings::UserSettings;
+use jj_lib::settings::{DurabilityMode, UserSettings};
 use jj_lib::store::Store;
 use jj_lib::tree::Tree;
 use jj_lib::tree_builder::TreeBuilder;
@@ -291,6 +296,132 @@ pub fn write_working_copy_file(workspace_root: &Path, path: &RepoPath, contents:
     file.write_all(contents.as_bytes()).unwrap();
 }
 
+/// A backend that forwards to another backend and counts how many files have
+/// been read and written.
+pub struct CountingBackend {
+    inner: Box<dyn Backend>,
+    file_reads: AtomicUsize,
+    file_writes: AtomicUsize,
+    flushes: AtomicUsize,
+}
+
+impl CountingBackend {
+    pub fn new(inner: Box<dyn Backend>) -> Self {
+        CountingBackend {
+            inner,
+            file_reads: AtomicUsize::new(0),
+            file_writes: AtomicUsize::new(0),
+            flushes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn file_reads(&self) -> usize {
+        self.file_reads.load(Ordering::SeqCst)
+    }
+
+    pub fn file_writes(&self) -> usize {
+        self.file_writes.load(Ordering::SeqCst)
+    }
+
+    pub fn flushes(&self) -> usize {
+        self.flushes.load(Ordering::SeqCst)
+    }
+}
+
+impl std::fmt::Debug for CountingBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CountingBackend").finish_non_exhaustive()
+    }
+}
+
+impl Backend for CountingBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn commit_id_length(&self) -> usize {
+        self.inner.commit_id_length()
+    }
+
+    fn change_id_length(&self) -> usize {
+        self.inner.change_id_length()
+    }
+
+    fn read_file(&self, path: &RepoPath, id: &FileId) -> BackendResult<Box<dyn Read>> {
+        self.file_reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.read_file(path, id)
+    }
+
+    fn write_file(&self, path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
+        self.file_writes.fetch_add(1, Ordering::SeqCst);
+        self.inner.write_file(path, contents)
+    }
+
+    fn read_symlink(&self, path: &RepoPath, id: &SymlinkId) -> BackendResult<String> {
+        self.inner.read_symlink(path, id)
+    }
+
+    fn write_symlink(&self, path: &RepoPath, target: &str) -> BackendResult<SymlinkId> {
+        self.inner.write_symlink(path, target)
+    }
+
+    fn root_commit_id(&self) -> &CommitId {
+        self.inner.root_commit_id()
+    }
+
+    fn root_change_id(&self) -> &ChangeId {
+        self.inner.root_change_id()
+    }
+
+    fn empty_tree_id(&self) -> &TreeId {
+        self.inner.empty_tree_id()
+    }
+
+    fn read_tree(&self, path: &RepoPath, id: &TreeId) -> BackendResult<backend::Tree> {
+        self.inner.read_tree(path, id)
+    }
+
+    fn write_tree(&self, path: &RepoPath, contents: &backend::Tree) -> BackendResult<TreeId> {
+        self.inner.write_tree(path, contents)
+    }
+
+    fn read_conflict(&self, path: &RepoPath, id: &ConflictId) -> BackendResult<Conflict> {
+        self.inner.read_conflict(path, id)
+    }
+
+    fn write_conflict(&self, path: &RepoPath, contents: &Conflict) -> BackendResult<ConflictId> {
+        self.inner.write_conflict(path, contents)
+    }
+
+    fn read_commit(&self, id: &CommitId) -> BackendResult<backend::Commit> {
+        self.inner.read_commit(id)
+    }
+
+    fn write_commit(
+        &self,
+        contents: backend::Commit,
+    ) -> BackendResult<(CommitId, backend::Commit)> {
+        self.inner.write_commit(contents)
+    }
+
+    fn all_object_ids(&self) -> BackendResult<Vec<AnyObjectId>> {
+        self.inner.all_object_ids()
+    }
+
+    fn set_durability_mode(&self, mode: DurabilityMode) {
+        self.inner.set_durability_mode(mode);
+    }
+
+    fn flush(&self) -> BackendResult<()> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        self.inner.flush()
+    }
+}
+
 pub struct CommitGraphBuilder<'settings, 'repo> {
     settings: &'settings UserSettings,
     mut_repo: &'repo mut MutableRepo,
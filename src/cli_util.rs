@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 use std::env::{self, ArgsOs, VarError};
 use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
@@ -39,11 +39,11 @@ use jj_lib::hex_util::to_reverse_hex;
 use jj_lib::id_prefix::IdPrefixContext;
 use jj_lib::matchers::{EverythingMatcher, Matcher, PrefixMatcher, Visit};
 use jj_lib::op_heads_store::{self, OpHeadResolutionError, OpHeadsStore};
-use jj_lib::op_store::{OpStore, OpStoreError, OperationId, RefTarget, WorkspaceId};
+use jj_lib::op_store::{OpStore, OperationId, RefTarget, WorkspaceId};
 use jj_lib::operation::Operation;
 use jj_lib::repo::{
-    CheckOutCommitError, EditCommitError, MutableRepo, ReadonlyRepo, Repo, RepoLoader,
-    RewriteRootCommit, StoreFactories, StoreLoadError,
+    CheckOutCommitError, EditCommitError, FlushError, MutableRepo, OpResolveError, ReadonlyRepo,
+    Repo, RepoLoader, RewriteRootCommit, StoreFactories, StoreLoadError,
 };
 use jj_lib::repo_path::{FsPathParseError, RepoPath};
 use jj_lib::revset::{
@@ -59,7 +59,7 @@ use jj_lib::working_copy::{
     CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions, WorkingCopy,
 };
 use jj_lib::workspace::{Workspace, WorkspaceInitError, WorkspaceLoadError, WorkspaceLoader};
-use jj_lib::{dag_walk, file_util, git, revset};
+use jj_lib::{dag_walk, file_util, git, repo, revset};
 use once_cell::unsync::OnceCell;
 use thiserror::Error;
 use toml_edit;
@@ -164,6 +164,23 @@ impl From<BackendError> for CommandError {
     }
 }
 
+impl From<FlushError> for CommandError {
+    fn from(err: FlushError) -> Self {
+        user_error(format!("Failed to sync the repository to disk: {err}"))
+    }
+}
+
+impl From<OpResolveError> for CommandError {
+    fn from(err: OpResolveError) -> Self {
+        match err {
+            OpResolveError::OpStore(err) => {
+                CommandError::InternalError(format!("Failed to read operation: {err}"))
+            }
+            err => user_error(err.to_string()),
+        }
+    }
+}
+
 impl From<WorkspaceInitError> for CommandError {
     fn from(err: WorkspaceInitError) -> Self {
         match err {
@@ -182,6 +199,8 @@ impl From<WorkspaceInitError> for CommandError {
             WorkspaceInitError::Backend(err) => {
                 user_error(format!("Failed to access the repository: {err}"))
             }
+            WorkspaceInitError::Config(err) => err.into(),
+            WorkspaceInitError::Flush(err) => err.into(),
         }
     }
 }
@@ -205,7 +224,10 @@ impl From<SnapshotError> for CommandError {
 
 impl From<TreeMergeError> for CommandError {
     fn from(err: TreeMergeError) -> Self {
-        CommandError::InternalError(format!("Merge failed: {err}"))
+        match err {
+            TreeMergeError::Config(err) => err.into(),
+            err => CommandError::InternalError(format!("Merge failed: {err}")),
+        }
     }
 }
 
@@ -290,9 +312,16 @@ impl From<RevsetResolutionError> for CommandError {
                 name: _,
                 candidates,
             } => format_similarity_hint(candidates),
+            RevsetResolutionError::AmbiguousChangeIdPrefix {
+                prefix: _,
+                candidates,
+                num_omitted,
+            } => format_similarity_hint(candidates).map(|hint| match num_omitted {
+                0 => hint,
+                _ => format!("{hint} ({num_omitted} more not shown)"),
+            }),
             RevsetResolutionError::EmptyString
             | RevsetResolutionError::AmbiguousCommitIdPrefix(_)
-            | RevsetResolutionError::AmbiguousChangeIdPrefix(_)
             | RevsetResolutionError::StoreError(_) => None,
         };
 
@@ -745,7 +774,7 @@ impl WorkspaceCommandHelper {
                     // state to it without updating working copy files.
                     locked_working_copy.reset(&new_git_head_commit.tree())?;
                     tx.mut_repo().rebase_descendants(&self.settings)?;
-                    self.user_repo = ReadonlyUserRepo::new(tx.commit());
+                    self.user_repo = ReadonlyUserRepo::new(tx.commit()?);
                     locked_working_copy.finish(op_id);
                 }
                 _ => {
@@ -914,12 +943,7 @@ impl WorkspaceCommandHelper {
     pub fn resolve_single_op(&self, op_str: &str) -> Result<Operation, CommandError> {
         // When resolving the "@" operation in a `ReadonlyRepo`, we resolve it to the
         // operation the repo was loaded at.
-        resolve_single_op(
-            self.repo().op_store(),
-            self.repo().op_heads_store(),
-            || Ok(self.repo().operation().clone()),
-            op_str,
-        )
+        Ok(self.repo().resolve_operation(op_str)?)
     }
 
     pub fn resolve_single_rev(&self, revision_str: &str) -> Result<Commit, CommandError> {
@@ -1175,7 +1199,7 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
                 print_failed_git_export(ui, &failed_branches)?;
             }
 
-            self.user_repo = ReadonlyUserRepo::new(tx.commit());
+            self.user_repo = ReadonlyUserRepo::new(tx.commit()?);
         }
         locked_wc.finish(self.user_repo.repo.op_id().clone());
         Ok(())
@@ -1246,7 +1270,7 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
             .get_wc_commit_id(self.workspace_id())
             .map(|commit_id| store.get_commit(commit_id))
             .transpose()?;
-        self.user_repo = ReadonlyUserRepo::new(tx.commit());
+        self.user_repo = ReadonlyUserRepo::new(tx.commit()?);
         if self.may_update_working_copy {
             self.update_working_copy(ui, maybe_old_commit.as_ref())?;
         }
@@ -1457,6 +1481,7 @@ jj init --git-repo=.",
         ) => CommandError::InternalError(format!(
             "The repository appears broken or inaccessible: {err}"
         )),
+        WorkspaceLoadError::StoreLoadError(StoreLoadError::Config(err)) => err.into(),
     }
 }
 
@@ -1616,86 +1641,9 @@ fn resolve_single_op(
     get_current_op: impl FnOnce() -> Result<Operation, OpHeadResolutionError<CommandError>>,
     op_str: &str,
 ) -> Result<Operation, CommandError> {
-    let op_symbol = op_str.trim_end_matches('-');
-    let op_postfix = &op_str[op_symbol.len()..];
-    let mut operation = match op_symbol {
-        "@" => get_current_op(),
-        s => resolve_single_op_from_store(op_store, op_heads_store, s)
-            .map_err(OpHeadResolutionError::Err),
-    }?;
-    for _ in op_postfix.chars() {
-        operation = match operation.parents().as_slice() {
-            [op] => Ok(op.clone()),
-            [] => Err(user_error(format!(
-                r#"The "{op_str}" expression resolved to no operations"#
-            ))),
-            [_, _, ..] => Err(user_error(format!(
-                r#"The "{op_str}" expression resolved to more than one operation"#
-            ))),
-        }?;
-    }
-    Ok(operation)
-}
-
-fn find_all_operations(
-    op_store: &Arc<dyn OpStore>,
-    op_heads_store: &Arc<dyn OpHeadsStore>,
-) -> Vec<Operation> {
-    let mut visited = HashSet::new();
-    let mut work: VecDeque<_> = op_heads_store.get_op_heads().into_iter().collect();
-    let mut operations = vec![];
-    while let Some(op_id) = work.pop_front() {
-        if visited.insert(op_id.clone()) {
-            let store_operation = op_store.read_operation(&op_id).unwrap();
-            work.extend(store_operation.parents.iter().cloned());
-            let operation = Operation::new(op_store.clone(), op_id, store_operation);
-            operations.push(operation);
-        }
-    }
-    operations
-}
-
-fn resolve_single_op_from_store(
-    op_store: &Arc<dyn OpStore>,
-    op_heads_store: &Arc<dyn OpHeadsStore>,
-    op_str: &str,
-) -> Result<Operation, CommandError> {
-    if op_str.is_empty() || !op_str.as_bytes().iter().all(|b| b.is_ascii_hexdigit()) {
-        return Err(user_error(format!(
-            "Operation ID \"{op_str}\" is not a valid hexadecimal prefix"
-        )));
-    }
-    if let Ok(binary_op_id) = hex::decode(op_str) {
-        let op_id = OperationId::new(binary_op_id);
-        match op_store.read_operation(&op_id) {
-            Ok(operation) => {
-                return Ok(Operation::new(op_store.clone(), op_id, operation));
-            }
-            Err(OpStoreError::NotFound) => {
-                // Fall through
-            }
-            Err(err) => {
-                return Err(CommandError::InternalError(format!(
-                    "Failed to read operation: {err}"
-                )));
-            }
-        }
-    }
-    let mut matches = vec![];
-    for op in find_all_operations(op_store, op_heads_store) {
-        if op.id().hex().starts_with(op_str) {
-            matches.push(op);
-        }
-    }
-    if matches.is_empty() {
-        Err(user_error(format!("No operation ID matching \"{op_str}\"")))
-    } else if matches.len() == 1 {
-        Ok(matches.pop().unwrap())
-    } else {
-        Err(user_error(format!(
-            "Operation ID prefix \"{op_str}\" is ambiguous"
-        )))
-    }
+    repo::resolve_op(op_store, op_heads_store.get_op_heads(), op_str, || {
+        Ok(get_current_op()?)
+    })
 }
 
 fn load_revset_aliases(
@@ -31,7 +31,7 @@ use clap::parser::ValueSource;
 use clap::{ArgGroup, Command, CommandFactory, FromArgMatches, Subcommand};
 use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
-use jj_lib::backend::{CommitId, ObjectId, TreeValue};
+use jj_lib::backend::{BackendError, CommitId, ObjectId, TreeValue};
 use jj_lib::commit::Commit;
 use jj_lib::conflicts::Conflict;
 use jj_lib::dag_walk::topo_order_reverse;
@@ -45,11 +45,11 @@ use jj_lib::revset::{
     RevsetGraphEdge, RevsetGraphEdgeType, RevsetIteratorExt,
 };
 use jj_lib::rewrite::{back_out_commit, merge_commit_trees, rebase_commit, DescendantRebaser};
-use jj_lib::settings::UserSettings;
+use jj_lib::settings::{LogOrder, UserSettings};
 use jj_lib::tree::{merge_trees, Tree};
-use jj_lib::working_copy::SnapshotOptions;
+use jj_lib::working_copy::{AssertResolvedError, SnapshotOptions};
 use jj_lib::workspace::Workspace;
-use jj_lib::{file_util, revset};
+use jj_lib::{file_util, revset, working_copy};
 use maplit::{hashmap, hashset};
 
 use crate::cli_util::{
@@ -1266,13 +1266,10 @@ fn cmd_checkout(
     let target = workspace_command.resolve_single_rev(&args.revision)?;
     let mut tx =
         workspace_command.start_transaction(&format!("check out commit {}", target.id().hex()));
+    let parent_ids = command.settings().checkout_policy()?.parent_ids(&target);
     let commit_builder = tx
         .mut_repo()
-        .new_commit(
-            command.settings(),
-            vec![target.id().clone()],
-            target.tree_id().clone(),
-        )
+        .new_commit(command.settings(), parent_ids, target.tree_id().clone())
         .set_description(&args.message);
     let new_commit = commit_builder.write()?;
     tx.edit(&new_commit).unwrap();
@@ -1345,7 +1342,7 @@ Make sure they're ignored, then try again.",
     if num_rebased > 0 {
         writeln!(ui, "Rebased {num_rebased} descendant commits")?;
     }
-    let repo = tx.commit();
+    let repo = tx.commit()?;
     locked_working_copy.finish(repo.op_id().clone());
     Ok(())
 }
@@ -1589,11 +1586,19 @@ fn cmd_log(ui: &mut Ui, command: &CommandHelper, args: &LogArgs) -> Result<(), C
         if !args.no_graph {
             let mut graph = get_graphlog(command.settings(), formatter.raw());
             let default_node_symbol = graph.default_node_symbol().to_owned();
+            let log_order = command.settings().log_order()?;
+            let nodes: Box<dyn Iterator<Item = (CommitId, Vec<RevsetGraphEdge>)>> =
+                if log_order == LogOrder::Topological {
+                    revset.iter_graph()
+                } else {
+                    let nodes = log_order.sort_graph_nodes(store, revset.iter_graph().collect())?;
+                    Box::new(nodes.into_iter())
+                };
             let iter: Box<dyn Iterator<Item = (CommitId, Vec<RevsetGraphEdge>)>> = if args.reversed
             {
-                Box::new(ReverseRevsetGraphIterator::new(revset.iter_graph()))
+                Box::new(ReverseRevsetGraphIterator::new(nodes))
             } else {
-                revset.iter_graph()
+                nodes
             };
             for (commit_id, edges) in iter {
                 let mut graphlog_edges = vec![];
@@ -1653,12 +1658,23 @@ fn cmd_log(ui: &mut Ui, command: &CommandHelper, args: &LogArgs) -> Result<(), C
                 )?;
             }
         } else {
-            let iter: Box<dyn Iterator<Item = CommitId>> = if args.reversed {
-                Box::new(revset.iter().reversed())
-            } else {
-                Box::new(revset.iter())
-            };
-            for commit_or_error in iter.commits(store) {
+            let log_order = command.settings().log_order()?;
+            let iter: Box<dyn Iterator<Item = Result<Commit, BackendError>>> =
+                if log_order == LogOrder::Topological {
+                    if args.reversed {
+                        Box::new(revset.iter().reversed().commits(store))
+                    } else {
+                        Box::new(revset.iter().commits(store))
+                    }
+                } else {
+                    let mut commits: Vec<_> = revset.iter().commits(store).try_collect()?;
+                    log_order.sort_commits(&mut commits);
+                    if args.reversed {
+                        commits.reverse();
+                    }
+                    Box::new(commits.into_iter().map(Ok))
+                };
+            for commit_or_error in iter {
                 let commit = commit_or_error?;
                 with_content_format
                     .write(formatter, |formatter| template.format(&commit, formatter))?;
@@ -1997,6 +2013,30 @@ fn cmd_commit(ui: &mut Ui, command: &CommandHelper, args: &CommitArgs) -> Result
         .get_wc_commit_id()
         .ok_or_else(|| user_error("This command requires a working copy"))?;
     let commit = workspace_command.repo().store().get_commit(commit_id)?;
+    if !command.settings().allow_empty_commits()
+        && workspace_command.repo().commit_is_empty(&commit)?
+    {
+        return Err(user_error_with_hint(
+            "Refusing to commit an empty change",
+            "Empty commits are disallowed by the `ui.allow-empty-commits` setting",
+        ));
+    }
+    if command.settings().check_conflict_markers() {
+        let base_tree = merge_commit_trees(workspace_command.repo().as_ref(), &commit.parents())?;
+        working_copy::assert_resolved(
+            workspace_command.repo().store(),
+            workspace_command.workspace_root(),
+            &base_tree,
+            &commit.tree(),
+        )
+        .map_err(|err| match err {
+            AssertResolvedError::Unresolved(err) => user_error_with_hint(
+                err.to_string(),
+                "Resolve the conflicts or disable the `ui.check-conflict-markers` setting",
+            ),
+            err => user_error(err.to_string()),
+        })?;
+    }
     let description = if let Some(message) = &args.message {
         message.into()
     } else {
@@ -2004,12 +2044,23 @@ fn cmd_commit(ui: &mut Ui, command: &CommandHelper, args: &CommitArgs) -> Result
         edit_description(workspace_command.repo(), &template, command.settings())?
     };
 
+    let current_branch = if command.settings().advance_branches() {
+        workspace_command
+            .repo()
+            .current_branch(workspace_command.workspace_id())?
+    } else {
+        None
+    };
     let mut tx = workspace_command.start_transaction(&format!("commit {}", commit.id().hex()));
     let new_commit = tx
         .mut_repo()
         .rewrite_commit(command.settings(), &commit)
         .set_description(description)
         .write()?;
+    if let Some(branch_name) = current_branch {
+        tx.mut_repo()
+            .set_local_branch(branch_name, RefTarget::Normal(new_commit.id().clone()));
+    }
     let workspace_ids = tx
         .mut_repo()
         .view()
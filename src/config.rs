@@ -260,6 +260,20 @@ fn env_overrides() -> config::Config {
     if let Ok(value) = env::var("JJ_EMAIL") {
         builder = builder.set_override("user.email", value).unwrap();
     }
+    // SOURCE_DATE_EPOCH (seconds since the epoch) makes commit timestamps
+    // reproducible. JJ_TIMESTAMP takes precedence if both are set.
+    if let Some(datetime) = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .and_then(|seconds| chrono::NaiveDateTime::from_timestamp_opt(seconds, 0))
+    {
+        builder = builder
+            .set_override(
+                "debug.commit-timestamp",
+                chrono::DateTime::<chrono::Utc>::from_utc(datetime, chrono::Utc).to_rfc3339(),
+            )
+            .unwrap();
+    }
     if let Ok(value) = env::var("JJ_TIMESTAMP") {
         builder = builder
             .set_override("debug.commit-timestamp", value)
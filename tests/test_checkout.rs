@@ -51,6 +51,33 @@ fn test_checkout() {
     "###);
 }
 
+#[test]
+fn test_checkout_sibling_policy() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.checkout-policy = "sibling""#);
+
+    std::fs::write(repo_path.join("file"), "first\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "first"]);
+    std::fs::write(repo_path.join("file"), "second\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "second"]);
+
+    // The new working-copy commit is a sibling of "second" with the same content
+    test_env.jj_cmd_success(&repo_path, &["checkout", "@-"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  5dd3e6ddb778696f7e2dcb6e4138c4a78b6a1bb8
+    │ ◉  87acca01a4375375be35d5520e63858638e0227a second
+    ├─╯
+    ◉  58d734756d6532921e82acd45a0f3c3651086cc3 first
+    ◉  0000000000000000000000000000000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    second
+    "###);
+}
+
 #[test]
 fn test_checkout_not_single_rev() {
     let test_env = TestEnvironment::default();
@@ -88,6 +88,113 @@ fn test_commit_without_working_copy() {
     "###);
 }
 
+#[test]
+fn test_commit_empty() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+
+    // Empty commits are allowed by default
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=first"]);
+
+    test_env.add_config(r#"ui.allow-empty-commits = false"#);
+    let stderr = test_env.jj_cmd_failure(&workspace_path, &["commit", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Refusing to commit an empty change
+    Hint: Empty commits are disallowed by the `ui.allow-empty-commits` setting
+    "###);
+
+    // A merge commit is empty if its tree matches the merged parent trees
+    std::fs::write(workspace_path.join("file1"), "a\n").unwrap();
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=second"]);
+    test_env.jj_cmd_success(&workspace_path, &["new", "root"]);
+    std::fs::write(workspace_path.join("file2"), "b\n").unwrap();
+    test_env.jj_cmd_success(&workspace_path, &["new", "@", "description(second)"]);
+    let stderr = test_env.jj_cmd_failure(&workspace_path, &["commit", "-m=merge"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Refusing to commit an empty change
+    Hint: Empty commits are disallowed by the `ui.allow-empty-commits` setting
+    "###);
+    std::fs::write(workspace_path.join("file3"), "c\n").unwrap();
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=merge"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @  a45759586402
+    ◉    e68261e0fa86 merge
+    ├─╮
+    ◉ │  d3740e94c007
+    │ ◉  e6f2ff82b238 second
+    │ ◉  69542c1984c1 first
+    ├─╯
+    ◉  000000000000
+    "###);
+}
+
+#[test]
+fn test_commit_conflict_markers() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.check-conflict-markers = true"#);
+
+    std::fs::write(workspace_path.join("file1"), "a\n").unwrap();
+    std::fs::write(
+        workspace_path.join("file2"),
+        "<<<<<<<\n%%%%%%%\n-a\n+b\n+++++++\nc\n>>>>>>>\n",
+    )
+    .unwrap();
+    let stderr = test_env.jj_cmd_failure(&workspace_path, &["commit", "-m=first"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Unresolved conflicts in file2
+    Hint: Resolve the conflicts or disable the `ui.check-conflict-markers` setting
+    "###);
+
+    std::fs::write(workspace_path.join("file2"), "b\n").unwrap();
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=first"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @  90519073d7d1
+    ◉  ab97ccb2e638 first
+    ◉  000000000000
+    "###);
+}
+
+#[test]
+fn test_commit_advance_branches() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.advance-branches = true"#);
+
+    // The branch at the parent of the working copy follows the new commit
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=first"]);
+    test_env.jj_cmd_success(&workspace_path, &["branch", "create", "main", "-r=@-"]);
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=second"]);
+    insta::assert_snapshot!(get_log_output_with_branches(&test_env, &workspace_path), @r###"
+    @  89bb1c83160e
+    ◉  23ffa39c340e second main
+    ◉  69542c1984c1 first
+    ◉  000000000000
+    "###);
+
+    // A branch elsewhere doesn't move
+    test_env.jj_cmd_success(
+        &workspace_path,
+        &["branch", "create", "old", "-r=description(first)"],
+    );
+    test_env.jj_cmd_success(&workspace_path, &["commit", "-m=third"]);
+    insta::assert_snapshot!(get_log_output_with_branches(&test_env, &workspace_path), @r###"
+    @  e3f3152a4a60
+    ◉  8c2208f6ad43 third main
+    ◉  23ffa39c340e second
+    ◉  69542c1984c1 first old
+    ◉  000000000000
+    "###);
+}
+
+fn get_log_output_with_branches(test_env: &TestEnvironment, cwd: &Path) -> String {
+    let template = r#"commit_id.short() ++ " " ++ description.first_line() ++ " " ++ branches"#;
+    test_env.jj_cmd_success(cwd, &["log", "-T", template])
+}
+
 fn get_log_output(test_env: &TestEnvironment, cwd: &Path) -> String {
     let template = r#"commit_id.short() ++ " " ++ description"#;
     test_env.jj_cmd_success(cwd, &["log", "-T", template])
@@ -363,6 +363,39 @@ fn test_early_args() {
     );
 }
 
+#[test]
+fn test_source_date_epoch() {
+    // Test that commits created with the same SOURCE_DATE_EPOCH (and
+    // randomness seed) are identical
+    let test_env = TestEnvironment::default();
+    let create_commit = |repo_name: &str| {
+        test_env.jj_cmd_success(test_env.env_root(), &["init", repo_name, "--git"]);
+        let repo_path = test_env.env_root().join(repo_name);
+        test_env
+            .jj_cmd(&repo_path, &["new", "root", "-m", "test"])
+            .env_remove("JJ_TIMESTAMP")
+            .env("SOURCE_DATE_EPOCH", "1000000000")
+            .env("JJ_RANDOMNESS_SEED", "0")
+            .assert()
+            .success();
+        test_env.jj_cmd_success(
+            &repo_path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                "@",
+                "-T",
+                r#"commit_id ++ " " ++ committer.timestamp()"#,
+            ],
+        )
+    };
+    let stdout1 = create_commit("repo1");
+    let stdout2 = create_commit("repo2");
+    assert_eq!(stdout1, stdout2);
+    insta::assert_snapshot!(stdout1, @"f5fdbc455e425b131b0fdcc1233461f2b552c402 2001-09-09 01:46:40.000 +00:00");
+}
+
 #[test]
 fn test_invalid_config() {
     // Test that we get a reasonable error if the config is invalid (#55)
@@ -376,6 +409,21 @@ fn test_invalid_config() {
     "###);
 }
 
+#[test]
+fn test_invalid_durability_mode() {
+    // Test that a typo in storage.durability is reported instead of ignored
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.add_config(r#"storage.durability = "synced""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: unknown durability mode: synced
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
 #[test]
 fn test_no_user_configured() {
     // Test that the user is reminded if they haven't configured their name or email
@@ -683,6 +683,84 @@ fn test_log_reversed() {
     "###);
 }
 
+#[test]
+fn test_log_order() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "first"]);
+    // Created after "first", but with an older committer timestamp
+    test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "new",
+            "root",
+            "-m",
+            "second",
+            "--config-toml",
+            "debug.commit-timestamp='1990-01-01T00:00:00Z'",
+        ],
+    );
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description", "--no-graph"]);
+    insta::assert_snapshot!(stdout, @r###"
+    second
+    first
+    "###);
+
+    test_env.add_config(r#"ui.log-order = "committer-timestamp""#);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description", "--no-graph"]);
+    insta::assert_snapshot!(stdout, @r###"
+    first
+    second
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "-T", "description", "--no-graph", "--reversed"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    second
+    first
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  first
+    │ @  second
+    ├─╯
+    ◉
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description", "--reversed"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉
+    ├─╮
+    │ @  second
+    ◉  first
+    "###);
+
+    // A child with an older committer timestamp than its parent is still listed
+    // before the parent in the graph
+    test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "new",
+            "-m",
+            "third",
+            "--config-toml",
+            "debug.commit-timestamp='1980-01-01T00:00:00Z'",
+        ],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  first
+    │ @  third
+    │ ◉  second
+    ├─╯
+    ◉
+    "###);
+}
+
 #[test]
 fn test_log_filtered_by_path() {
     let test_env = TestEnvironment::default();
@@ -567,6 +567,25 @@ fn test_rebase_with_descendants() {
     "###);
 }
 
+#[test]
+fn test_rebase_invalid_merge_whitespace() {
+    // Test that a typo in merge.whitespace is reported instead of ignored
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    create_commit(&test_env, &repo_path, "c", &["a"]);
+
+    test_env.add_config(r#"merge.whitespace = "bogus""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-r", "b", "-d", "c"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: unknown whitespace handling: bogus
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     test_env.jj_cmd_success(repo_path, &["log", "-T", "branches"])
 }